@@ -0,0 +1,36 @@
+use miette::Error;
+
+use crate::{context::CtxTree, evaluator::{Eval, Value}, exptree::ExprTree, ir::Interpreter, vm::Vm};
+
+/// ## Execution backend
+/// All backends run the same resolved expression tree with the same semantics
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum Backend {
+    /// walks the expression tree
+    #[default]
+    Tree,
+    /// compiles the tree into bytecode and runs it on the stack based virtual machine
+    Vm,
+    /// lowers the tree into the intermediate representation and interprets it
+    Ir,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::Tree, Backend::Vm, Backend::Ir];
+
+    /// ## Runs resolved program
+    /// Returns the value of the last statement
+    pub fn run<'de>(self, ctx: &CtxTree<'de>, stmts: &[ExprTree<'de>]) -> Result<Value<'de>, Error> {
+        match self {
+            Backend::Tree => {
+                let mut last = Value::Nil;
+                for stmt in stmts {
+                    last = stmt.eval(ctx)?;
+                }
+                Ok(last)
+            },
+            Backend::Vm => Vm::new().run(ctx, stmts),
+            Backend::Ir => Interpreter::run(ctx, stmts),
+        }
+    }
+}
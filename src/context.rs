@@ -1,8 +1,8 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::{Rc, Weak}};
 
 use miette::Error;
 
-use crate::{evaluator::Value, exptree::FnBlock};
+use crate::{evaluator::{NativeFn, Value}, resolver::Binding};
 
 
 #[allow(non_camel_case_types)]
@@ -16,17 +16,104 @@ pub struct CtxTree<'de> (Rc<RefCell<Context<'de>>>);
 
 
 struct Context<'de> {
-    vars: HashMap<String, Value>,
-    funcs: HashMap<String, FnBlock<'de>>,
+    vars: HashMap<String, Value<'de>>,
+    /// local variables resolved to their slots
+    slots: Vec<Value<'de>>,
+    /// namespaces of the imported modules by the path they were imported with
+    modules: HashMap<String, Value<'de>>,
     prev: Option<CtxTree<'de>>
 }
 
+/// ## Environment captured by a function
+/// Function stored in the very node it captured holds the node only weakly,
+/// otherwise the two would keep each other alive and the node would never be freed.
+/// Reading the function from the node captures the node strongly again
+#[derive(Clone)]
+pub(crate) struct Captured<'de>(Capture<'de>);
+
+#[derive(Clone)]
+enum Capture<'de> {
+    Strong(CtxTree<'de>),
+    Weak(Weak<RefCell<Context<'de>>>),
+}
+
+impl<'de> Captured<'de> {
+    pub fn new(env: CtxTree<'de>) -> Self {
+        Self(Capture::Strong(env))
+    }
+
+    /// ## Captured node
+    /// weakly captured functions live only in the node itself, so it is always alive
+    pub fn ctx(&self) -> CtxTree<'de> {
+        match &self.0 {
+            Capture::Strong(env) => env.clone(),
+            Capture::Weak(env) => CtxTree(env.upgrade().expect("weakly captured node is alive")),
+        }
+    }
+
+    /// ## Captures the same node
+    pub fn same(&self, other: &Self) -> bool {
+        std::ptr::eq(self.as_ptr(), other.as_ptr())
+    }
+
+    fn as_ptr(&self) -> *const RefCell<Context<'de>> {
+        match &self.0 {
+            Capture::Strong(env) => Rc::as_ptr(&env.0),
+            Capture::Weak(env) => env.as_ptr(),
+        }
+    }
+
+    /// weak capture if `node` is the captured node
+    fn weakened(&self, node: &CtxTree<'de>) -> Option<Self> {
+        match &self.0 {
+            Capture::Strong(env) if Rc::ptr_eq(&env.0, &node.0) => Some(Self(Capture::Weak(Rc::downgrade(&env.0)))),
+            _ => None,
+        }
+    }
+
+    /// strong capture if the node is captured weakly
+    fn strengthened(&self) -> Option<Self> {
+        match &self.0 {
+            Capture::Weak(_) => Some(Self(Capture::Strong(self.ctx()))),
+            Capture::Strong(_) => None,
+        }
+    }
+}
+
+/// ## Function capturing its environment
+pub(crate) trait Capturing<'de>: Sized {
+    fn env(&self) -> &Captured<'de>;
+
+    /// copy of the function capturing `env` instead
+    fn with_env(&self, env: Captured<'de>) -> Self;
+
+    fn stored_in(self: Rc<Self>, node: &CtxTree<'de>) -> Rc<Self> {
+        match self.env().weakened(node) {
+            Some(env) => Rc::new(self.with_env(env)),
+            None => self,
+        }
+    }
+
+    fn loaded(self: Rc<Self>) -> Rc<Self> {
+        match self.env().strengthened() {
+            Some(env) => Rc::new(self.with_env(env)),
+            None => self,
+        }
+    }
+}
+
+impl Default for CtxTree<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'de> CtxTree<'de> {
 
     /// ## Creates new context tree
     /// creates new rooted tree of context returning the root
     pub fn new() -> Self {
-        Self(Rc::new(RefCell::new(Context { vars: HashMap::new(), funcs: HashMap::new(), prev: None })))
+        Self(Rc::new(RefCell::new(Context { vars: HashMap::new(), slots: Vec::new(), modules: HashMap::new(), prev: None })))
     }
 
     /// ## Forks the tree
@@ -35,20 +122,46 @@ impl<'de> CtxTree<'de> {
     pub fn fork(&self) -> Self {
         let ctx: Context = Context {
             vars: HashMap::new(),
-            funcs: HashMap::new(),
+            slots: Vec::new(),
+            modules: HashMap::new(),
             prev: Some(self.clone())
         };
         Self(Rc::new(RefCell::new(ctx)))
     }
 
 
+    /// ## Parent node
+    /// returns the node this node was forked from
+    pub fn parent(&self) -> Option<Self> {
+        self.0.borrow().prev.clone()
+    }
+
+    /// functions declared in this node are stored capturing it weakly
+    fn store(&self, value: Value<'de>) -> Value<'de> {
+        match value {
+            Value::Function(fun) => Value::Function(fun.stored_in(self)),
+            Value::CompiledFunction(fun) => Value::CompiledFunction(fun.stored_in(self)),
+            Value::LoweredFunction(fun) => Value::LoweredFunction(fun.stored_in(self)),
+            v => v,
+        }
+    }
+
+    fn load(value: Value<'de>) -> Value<'de> {
+        match value {
+            Value::Function(fun) => Value::Function(fun.loaded()),
+            Value::CompiledFunction(fun) => Value::CompiledFunction(fun.loaded()),
+            Value::LoweredFunction(fun) => Value::LoweredFunction(fun.loaded()),
+            v => v,
+        }
+    }
+
     /// ## Searches for the variable in the context tree
     /// searches for the variable in current node and all the 
     /// parrent nodes up to the root
-    pub fn search(&self, name: &str) -> Option<Value> {
+    pub fn search(&self, name: &str) -> Option<Value<'de>> {
         let ctx = &self.0;
         if let Some(v) = ctx.borrow().vars.get(name) {
-            return Some(v.clone())
+            return Some(Self::load(v.clone()))
         }
         if let Some(ref prev) = ctx.borrow().prev {
             return prev.search(name)
@@ -56,45 +169,130 @@ impl<'de> CtxTree<'de> {
         None
     }
 
-    // ## Inserts the new function
-    // If the function with that name already exists it overides it
-    pub fn insert_fn(&self, name: &'de str,  fun: FnBlock<'de>) {
+    /// ## Inserts the new value
+    /// it inserts or owewrites the value in current node
+    /// parrent nodes will not be able to search or update this variable
+    pub fn insert(&self, name: &str, value: Value<'de>) {
+        let value = self.store(value);
         let mut ctx = self.0.borrow_mut();
-        ctx.funcs.insert(name.to_string(), fun);
-        *ctx = Context {
-            vars: HashMap::new(),
-            funcs: HashMap::new(),
-            prev: Some(self.clone())
-        }; // we update current ctx
+        ctx.vars.insert(name.to_string(), value);
     }
 
-    // ## Executes function
-    // executes function with given name and arguments
-    // returns None if no function with given name exists in current scope
-    pub fn exec_fn(&self, name: &str, args: Vec<Value>) -> Option<Result<Value, Error>> {
-        let ctx = &self.0;
-        if let Some(f) = ctx.borrow().funcs.get(name) {
-            return Some(f.exec(self, args));
+    /// ## Registers native function
+    /// Exposes rust closure to the scripts under given name in current node.
+    /// `arity` of `None` means the function accepts any number of arguments
+    /// 
+    /// ## Example
+    /// ```
+    /// use lang_interpreter::context::CtxTree;
+    /// 
+    /// let ctx = CtxTree::new();
+    /// ctx.register_fn("square", Some(1), |args| {
+    ///     let n = f64::try_from(args[0].clone())?;
+    ///     Ok((n * n).into())
+    /// });
+    /// ```
+    pub fn register_fn(&self, name: &str, arity: Option<usize>, fun: impl Fn(Vec<Value<'de>>) -> Result<Value<'de>, Error> + 'de) {
+        self.insert(name, Value::NativeFunction(Rc::new(NativeFn::new(name, arity, fun))));
+    }
+
+    /// ## Links imported module
+    /// `import` of the `path` within this node or its forks declares the `namespace`
+    pub fn link(&self, path: &str, namespace: Value<'de>) {
+        self.0.borrow_mut().modules.insert(path.to_string(), namespace);
+    }
+
+    /// ## Searches for the linked module
+    /// searches current node and all the parrent nodes up to the root
+    pub fn module(&self, path: &str) -> Option<Value<'de>> {
+        let ctx = self.0.borrow();
+        match ctx.modules.get(path) {
+            Some(namespace) => Some(namespace.clone()),
+            None => ctx.prev.as_ref()?.module(path),
         }
-        if let Some(ref prev) = ctx.borrow().prev {
-            return prev.exec_fn(name, args);
+    }
+
+    /// ## Names of all the variables
+    /// returns names of the variables in current node and all the parrent nodes,
+    /// local variables stored in slots don't have names
+    pub fn names(&self) -> Vec<String> {
+        let ctx = self.0.borrow();
+        let mut names: Vec<String> = ctx.vars.keys().cloned().collect();
+        if let Some(ref prev) = ctx.prev {
+            names.extend(prev.names());
         }
-        None
+        names
     }
 
-    /// ## Inserts the new value
-    /// it inserts or owewrites the value in current node
-    /// parrent nodes will not be able to search or update this variable
-    pub fn insert(&self, name: &str, value: Value) {
-        let mut ctx = self.0.borrow_mut();
-        ctx.vars.insert(name.to_string(), value);
+    /// ## Defines local variable
+    /// stores the value in given slot of current node
+    pub fn define(&self, slot: usize, value: Value<'de>) {
+        let value = self.store(value);
+        let slots = &mut self.0.borrow_mut().slots;
+        if slots.len() <= slot {
+            slots.resize(slot + 1, Value::Nil);
+        }
+        slots[slot] = value;
+    }
+
+    /// ## Reads local variable
+    /// reads the slot of the node `depth` levels above current one
+    pub fn get_at(&self, depth: usize, slot: usize) -> Option<Value<'de>> {
+        if depth == 0 {
+            return self.0.borrow().slots.get(slot).cloned().map(Self::load)
+        }
+        self.0.borrow().prev.as_ref()?.get_at(depth - 1, slot)
+    }
+
+    /// ## Updates local variable
+    /// updates the slot of the node `depth` levels above current one 
+    /// or returns an error if the slot was not defined yet
+    pub fn set_at(&self, depth: usize, slot: usize, value: Value<'de>) -> Result<(), CtxError> {
+        if depth == 0 {
+            let value = self.store(value);
+            return match self.0.borrow_mut().slots.get_mut(slot) {
+                Some(v) => { *v = value; Ok(()) },
+                None => Err(CtxError::VARIABLE_NOT_FOUND)
+            }
+        }
+        match self.0.borrow().prev {
+            Some(ref prev) => prev.set_at(depth - 1, slot, value),
+            None => Err(CtxError::VARIABLE_NOT_FOUND)
+        }
+    }
+
+    /// ## Reads resolved variable
+    /// globals are searched by name, locals are read from their slots
+    pub fn lookup(&self, name: &str, binding: Binding) -> Option<Value<'de>> {
+        match binding {
+            Binding::Global => self.search(name),
+            Binding::Local { depth, slot } => self.get_at(depth, slot),
+        }
+    }
+
+    /// ## Updates resolved variable
+    pub fn assign(&self, name: &str, binding: Binding, value: Value<'de>) -> Result<(), CtxError> {
+        match binding {
+            Binding::Global => self.set(name, value),
+            Binding::Local { depth, slot } => self.set_at(depth, slot, value),
+        }
+    }
+
+    /// ## Declares resolved variable
+    /// declarations are always made in current node
+    pub fn declare(&self, name: &str, binding: Binding, value: Value<'de>) {
+        match binding {
+            Binding::Global => self.insert(name, value),
+            Binding::Local { slot, .. } => self.define(slot, value),
+        }
     }
 
     /// ## Uptades the value of the variable
     /// updates the value of the variable in current node or returns an 
     /// error if variable does not exists
-    pub fn set(&self, name: &str, value: Value) -> Result<(), CtxError> {
-        if self.0.borrow().vars.get(name).is_some() {
+    pub fn set(&self, name: &str, value: Value<'de>) -> Result<(), CtxError> {
+        if self.0.borrow().vars.contains_key(name) {
+            let value = self.store(value);
             self.0.borrow_mut().vars.insert(name.to_string(), value);
             Ok(())
         } else if let Some(ref prev) = self.0.borrow().prev {
@@ -130,6 +328,26 @@ mod tests {
         assert_eq!(error, Err(CtxError::VARIABLE_NOT_FOUND));
     }
 
+    #[test]
+    fn test_slots() {
+        let context = CtxTree::new();
+        let fork1 = context.fork();
+        let fork2 = fork1.fork();
+
+        fork1.define(1, Value::Number(1f64));
+        assert_eq!(fork1.get_at(0, 0), Some(Value::Nil));
+        assert_eq!(fork2.get_at(1, 1), Some(Value::Number(1f64)));
+        assert_eq!(fork2.get_at(0, 1), None);
+
+        fork2.set_at(1, 1, Value::Bool(true)).unwrap();
+        assert_eq!(fork1.get_at(0, 1), Some(Value::Bool(true)));
+        assert_eq!(fork2.set_at(0, 0, Value::Nil), Err(CtxError::VARIABLE_NOT_FOUND));
+        assert_eq!(fork2.set_at(5, 0, Value::Nil), Err(CtxError::VARIABLE_NOT_FOUND));
+
+        // slots don't have names
+        assert!(fork2.names().is_empty());
+    }
+
     #[test]
     fn test_fork_line_basic() {
         let context = CtxTree::new();
@@ -0,0 +1,107 @@
+use miette::Error;
+
+use crate::{formatter::comments, lsp::{Outline, Symbol, SymbolKind}, parser::Parser, token::Trivia};
+
+/// ## Output format of the documentation
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum DocFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+/// ## Documented file
+/// Functions declared at the top level of the file, in the order of declaration
+pub struct Module<'de> {
+    pub name: String,
+    pub functions: Vec<Symbol<'de>>,
+}
+
+impl<'de> Module<'de> {
+    /// ## Documents the source
+    /// Functions are documented by their `///` doc comments, functions without them
+    /// by the `//` comment lines right above the declaration
+    pub fn new(name: impl Into<String>, src: &'de str) -> Result<Self, Error> {
+        let stmts = Parser::new(src).parse()?;
+        let mut symbols = Vec::new();
+        for stmt in &stmts {
+            stmt.outline(&mut symbols);
+        }
+        let comments = comments(src);
+        let functions = symbols.into_iter()
+            .filter(|s| s.kind == SymbolKind::Function)
+            .map(|mut s| {
+                if s.doc.is_empty() {
+                    s.doc = comments_above(src, &comments, s.span.offset()).into();
+                }
+                s
+            })
+            .collect();
+        Ok(Self { name: name.into(), functions })
+    }
+}
+
+/// Lines of the `//` comments, each on its own line, that end right above the line
+/// the declaration at the offset starts
+fn comments_above<'de>(src: &'de str, comments: &[Trivia<'de>], offset: usize) -> Vec<&'de str> {
+    let mut lines = Vec::new();
+    let mut next = line_start(src, offset);
+    if !src[next..offset].trim().is_empty() {
+        return lines
+    }
+    for comment in comments.iter().rev().skip_while(|c| c.offset >= offset) {
+        let between = &src[comment.offset + comment.text.len()..next];
+        let line_start = line_start(src, comment.offset);
+        let own_line = src[line_start..comment.offset].trim().is_empty();
+        if !comment.text.starts_with("//") || !own_line || !between.trim().is_empty() || between.matches('\n').count() != 1 {
+            break
+        }
+        let text = comment.text.trim_start_matches('/');
+        lines.push(text.strip_prefix(' ').unwrap_or(text));
+        next = line_start;
+    }
+    lines.reverse();
+    lines
+}
+
+fn line_start(src: &str, offset: usize) -> usize {
+    src[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// ## Markdown page
+/// Every module is a section listing its functions
+pub fn markdown(modules: &[Module]) -> String {
+    let mut out = String::from("# Documentation\n");
+    for module in modules {
+        out.push_str(&format!("\n## {}\n", module.name));
+        for fun in &module.functions {
+            out.push_str(&format!("\n### `{}`\n", fun.signature()));
+            if !fun.doc.is_empty() {
+                out.push_str(&format!("{}\n", fun.doc.join("\n")));
+            }
+        }
+    }
+    out
+}
+
+/// ## HTML page
+/// Same structure as the `markdown` page
+pub fn html(modules: &[Module]) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Documentation</title>\n</head>\n<body>\n<h1>Documentation</h1>\n");
+    for module in modules {
+        out.push_str(&format!("<h2>{}</h2>\n", escape(&module.name)));
+        for fun in &module.functions {
+            out.push_str(&format!("<h3><code>{}</code></h3>\n", escape(&fun.signature())));
+            if !fun.doc.is_empty() {
+                let lines: Vec<_> = fun.doc.iter().map(|l| escape(l)).collect();
+                out.push_str(&format!("<p>{}</p>\n", lines.join("<br>\n")));
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -1,4 +1,4 @@
-use miette::{Diagnostic, SourceSpan};
+use miette::{Diagnostic, Error, SourceSpan};
 use thiserror::Error;
 
 #[derive(Diagnostic, Debug, Error)]
@@ -30,7 +30,391 @@ pub struct UnterminatedStringError {
 }
 
 #[derive(Diagnostic, Debug, Error)]
-#[error("Runtime error")]
-pub struct DefaultRuntimeError {
+#[error("Unterminated block comment")]
+#[diagnostic(help("block comments nest, every '/*' needs its own '*/'"))]
+pub struct UnterminatedCommentError {
+    #[source_code]
+    pub(crate) src: String,
+
+    #[label = "comment starts here"]
+    pub(crate) err_span: SourceSpan,
+}
+
+#[derive(Diagnostic, Debug, Error)]
+#[error("'{keyword}' outside of {scope}")]
+#[diagnostic(help("'{keyword}' can only be used inside of {scope}"))]
+pub struct ControlFlowError {
+    // The `Source` that miette will use.
+    #[source_code]
+    pub(crate) src: String,
+
+    pub keyword: &'static str,
+    pub scope: &'static str,
+
+    #[label = "here"]
+    pub(crate) err_span: SourceSpan,
+}
+
+#[derive(Diagnostic, Debug, Error)]
+#[error("Integer literal '{literal}' is out of range")]
+#[diagnostic(help("integers are 64-bit, from -9223372036854775808 to 9223372036854775807"))]
+pub struct IntegerOutOfRange {
+    #[source_code]
+    pub(crate) src: String,
+
+    pub literal: String,
+
+    #[label = "here"]
+    pub(crate) err_span: SourceSpan,
+}
+
+/// ## Multiple syntax errors
+/// All errors found by the lexer or the parser in one run, each of them
+/// is reported as a related diagnostic
+#[derive(Diagnostic, Debug, Error)]
+#[error("Found {} syntax errors", errors.len())]
+pub struct SyntaxErrors {
+    #[source_code]
+    pub(crate) src: String,
+
+    #[related]
+    pub errors: Vec<Error>,
+}
+
+impl SyntaxErrors {
+    /// ## Reports collected errors
+    /// Single error is returned as it is, more of them are reported together
+    pub fn check(src: &str, mut errors: Vec<Error>) -> Result<(), Error> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().expect("checked length")),
+            _ => Err(SyntaxErrors { src: src.to_string(), errors }.into())
+        }
+    }
+}
+
+/// ## Resolver errors
+/// Errors found by the static analysis of the parsed code before it runs,
+/// the source code is attached by whoever owns it
+#[derive(Diagnostic, Debug, Error, Clone, PartialEq)]
+pub enum ResolveError {
+    #[error("Undeclared variable '{name}'")]
+    #[diagnostic(help("declare it first with `var {name};`"))]
+    UndeclaredVariable {
+        name: String,
+        #[label = "used here"]
+        span: SourceSpan,
+    },
+
+    #[error("Undeclared function '{name}'")]
+    #[diagnostic(help("declare it with `fun {name}() {{ }}`"))]
+    UndeclaredFunction {
+        name: String,
+        #[label = "called here"]
+        span: SourceSpan,
+    },
+
+    #[error("Variable '{name}' is used before its declaration")]
+    #[diagnostic(help("move the declaration of '{name}' above its first use"))]
+    UsedBeforeDeclaration {
+        name: String,
+        #[label = "used here"]
+        span: SourceSpan,
+    },
+
+    #[error("Duplicate parameter '{name}'")]
+    #[diagnostic(help("every parameter of the function needs its own name"))]
+    DuplicateParameter {
+        name: String,
+        #[label = "in this function"]
+        span: SourceSpan,
+    },
 
-}
\ No newline at end of file
+    #[error("Class '{name}' can't inherit from itself")]
+    SelfInheritance {
+        name: String,
+        #[label = "inherited here"]
+        span: SourceSpan,
+    },
+
+    #[error("Initializer can't return a value")]
+    #[diagnostic(help("`init` always returns the instance, use `return;` to leave it early"))]
+    ReturnFromInitializer {
+        #[label = "returned here"]
+        span: SourceSpan,
+    },
+
+    #[error("Variable '{name}' is used in its own initializer")]
+    #[diagnostic(help("use a named `fun` declaration for recursive functions"))]
+    SelfReferencingInitializer {
+        name: String,
+        #[label = "used here"]
+        span: SourceSpan,
+    },
+}
+
+/// ## Import errors
+/// Errors found while loading the modules imported by the program,
+/// the source code of the importing file is attached by the loader
+#[derive(Diagnostic, Debug, Error, Clone, PartialEq)]
+pub enum ImportError {
+    #[error("Module '{path}' can't be read: {reason}")]
+    Unreadable {
+        path: String,
+        reason: String,
+        #[label = "imported here"]
+        span: SourceSpan,
+    },
+
+    #[error("Import cycle {cycle}")]
+    #[diagnostic(help("modules can't import each other, move the shared code into its own module"))]
+    Cycle {
+        /// files of the cycle joined by arrows, starting and ending with the same file
+        cycle: String,
+        #[label = "imported here"]
+        span: SourceSpan,
+    },
+}
+
+/// ## Compile errors
+/// Limits of the bytecode hit while compiling the program for the virtual machine
+#[derive(Diagnostic, Debug, Error, Clone, PartialEq)]
+pub enum CompileError {
+    #[error("Too many {what} for the virtual machine")]
+    #[diagnostic(help("instructions can refer to at most 65535 {what}"))]
+    TooMany {
+        what: &'static str,
+        #[label = "compiled here"]
+        span: SourceSpan,
+    },
+}
+
+/// ## Runtime errors
+/// All errors that can occur while evaluating already parsed code.
+/// 
+/// Each error points at the expression that caused it, the source code 
+/// itself is attached later by whoever owns it (see `with_source_code`)
+#[derive(Diagnostic, Debug, Error, Clone, PartialEq)]
+pub enum RuntimeError {
+    #[error("Undefined variable '{name}'")]
+    #[diagnostic(help("declare it first with `var {name};`"))]
+    UndefinedVariable { 
+        name: String,
+        #[label = "not found in this scope"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Undefined function '{name}'")]
+    UndefinedFunction { 
+        name: String,
+        #[label = "not found in this scope"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Function '{name}' expects {expected} argument(s) but {found} were given")]
+    ArityMismatch { 
+        name: String, 
+        expected: usize, 
+        found: usize,
+        #[label = "in this call"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Unsupported operand types for '{op}': {lhs} and {rhs}")]
+    TypeMismatch { 
+        op: String, 
+        lhs: &'static str, 
+        rhs: &'static str,
+        #[label = "in this expression"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Unsupported operand type for '{op}': {operand}")]
+    UnaryTypeMismatch { 
+        op: String, 
+        operand: &'static str,
+        #[label = "in this expression"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Integer overflow in '{op}'")]
+    #[diagnostic(help("integers have 64 bits, floats like `1.0` can hold larger numbers"))]
+    IntegerOverflow {
+        op: String,
+        #[label = "in this expression"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Negative shift count for '{op}'")]
+    NegativeShift {
+        op: String,
+        #[label = "in this expression"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Division by zero")]
+    DivisionByZero {
+        #[label = "in this expression"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Invalid assignment target")]
+    #[diagnostic(help("only variables can be assigned to"))]
+    InvalidAssignmentTarget {
+        #[label = "can't assign to this"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("'{event}' escaped its construct")]
+    #[diagnostic(help("`break` and `continue` belong in loops, `return` in functions"))]
+    EventEscape { 
+        event: &'static str,
+        #[label = "used here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Value of type {target} is not callable")]
+    NotCallable {
+        target: &'static str,
+        #[label = "called here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Expected value of type {expected} but found {found}")]
+    UnexpectedType {
+        expected: &'static str,
+        found: &'static str,
+        #[label = "in this call"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Index {index} out of bounds for length {len}")]
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+        #[label = "indexed here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Invalid index '{found}'")]
+    #[diagnostic(help("indexes must be non-negative whole numbers"))]
+    InvalidIndex {
+        found: String,
+        #[label = "this index"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Key '{key}' not found in map")]
+    #[diagnostic(help("check the key first with `contains(map, key)`"))]
+    KeyNotFound {
+        key: String,
+        #[label = "indexed here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Map keys must be strings, found {found}")]
+    InvalidKey {
+        found: &'static str,
+        #[label = "this key"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Value of type {target} can't be indexed")]
+    NotIndexable {
+        target: &'static str,
+        #[label = "indexed here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Undefined property '{name}' of {class} instance")]
+    #[diagnostic(help("fields are created by assigning to them, like `this.{name} = value;`"))]
+    UndefinedProperty {
+        name: String,
+        class: String,
+        #[label = "accessed here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Class '{class}' takes no arguments but {found} were given")]
+    #[diagnostic(help("declare `init` method to accept arguments"))]
+    ClassArityMismatch {
+        class: String,
+        found: usize,
+        #[label = "in this call"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Superclass must be a class, found {found}")]
+    InvalidSuperclass {
+        found: &'static str,
+        #[label = "inherited here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Module '{path}' is not loaded")]
+    #[diagnostic(help("modules are loaded only when the program is run from a file"))]
+    ModuleNotLoaded {
+        path: String,
+        #[label = "imported here"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Invalid argument for '{name}': {reason}")]
+    InvalidArgument {
+        name: String,
+        reason: String,
+        #[label = "in this call"]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("Statement does not produce a value")]
+    NoValue {
+        #[label = "used as a value here"]
+        span: Option<SourceSpan>,
+    },
+}
+
+impl RuntimeError {
+    /// ## Points the error at the span
+    /// Sets the span of the error only if it does not point anywhere yet
+    /// so the innermost expression always wins
+    pub fn at(mut self, span: SourceSpan) -> Self {
+        let err_span = match &mut self {
+            RuntimeError::UndefinedVariable { span, .. } |
+            RuntimeError::UndefinedFunction { span, .. } |
+            RuntimeError::ArityMismatch { span, .. } |
+            RuntimeError::TypeMismatch { span, .. } |
+            RuntimeError::UnaryTypeMismatch { span, .. } |
+            RuntimeError::IntegerOverflow { span, .. } |
+            RuntimeError::NegativeShift { span, .. } |
+            RuntimeError::DivisionByZero { span } |
+            RuntimeError::InvalidAssignmentTarget { span } |
+            RuntimeError::EventEscape { span, .. } |
+            RuntimeError::NotCallable { span, .. } |
+            RuntimeError::UnexpectedType { span, .. } |
+            RuntimeError::IndexOutOfBounds { span, .. } |
+            RuntimeError::InvalidIndex { span, .. } |
+            RuntimeError::KeyNotFound { span, .. } |
+            RuntimeError::InvalidKey { span, .. } |
+            RuntimeError::NotIndexable { span, .. } |
+            RuntimeError::UndefinedProperty { span, .. } |
+            RuntimeError::ClassArityMismatch { span, .. } |
+            RuntimeError::InvalidSuperclass { span, .. } |
+            RuntimeError::ModuleNotLoaded { span, .. } |
+            RuntimeError::InvalidArgument { span, .. } |
+            RuntimeError::NoValue { span } => span,
+        };
+        err_span.get_or_insert(span);
+        self
+    }
+
+    /// ## Points the miette error at the span
+    /// Same as `at` but for already type erased errors, 
+    /// errors that are not runtime errors are returned untouched
+    pub fn locate(err: Error, span: SourceSpan) -> Error {
+        match err.downcast::<RuntimeError>() {
+            Ok(e) => e.at(span).into(),
+            Err(e) => e,
+        }
+    }
+}
@@ -1,29 +1,47 @@
-use std::{fmt, ops::{Add, Div, Mul, Neg, Not, Sub}};
+use std::{cell::RefCell, cmp::Ordering, collections::{BTreeMap, HashMap}, fmt, ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub}, rc::Rc};
 
 use miette::Error;
 
-use crate::{context::CtxTree, error::DefaultRuntimeError};
+use crate::{context::{Captured, Capturing, CtxTree}, error::RuntimeError, exptree::FnBlock, ir, token::quote, vm};
 
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
-pub enum Value {
+#[derive(Clone, Debug)]
+pub enum Value<'de> {
     String(String),
+    /// float, literals with a decimal point
     Number(f64),
+    /// 64-bit integer, literals without a decimal point
+    Integer(i64),
     Bool(bool),
     Nil,
+    /// lists are shared, every copy of the value points to the same list
+    List(Rc<RefCell<Vec<Value<'de>>>>),
+    /// maps are shared like lists, keys are strings kept in sorted order
+    Map(Rc<RefCell<BTreeMap<String, Value<'de>>>>),
+    Function(Rc<Closure<'de>>),
+    /// function implemented by the host in rust
+    NativeFunction(Rc<NativeFn<'de>>),
+    /// function compiled for the virtual machine
+    CompiledFunction(Rc<vm::Closure<'de>>),
+    /// function lowered to the intermediate representation
+    LoweredFunction(Rc<ir::Closure<'de>>),
+    /// class declared by the program, calling it creates new instance
+    Class(Rc<Class<'de>>),
+    /// instances are shared like lists, every copy points to the same fields
+    Instance(Rc<Instance<'de>>),
 
-    Event(Event),
+    Event(Event<'de>),
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
-pub enum Event {
+pub enum Event<'de> {
     Continue,
-    Break(Box<Value>),
-    Return(Box<Value>),
+    Break(Box<Value<'de>>),
+    Return(Box<Value<'de>>),
     NoVal, // more than nil, only for stmts that cant return
 }
 
-impl fmt::Display for Event {
+impl fmt::Display for Event<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Event::Continue => write!(f, "continue"),
@@ -34,109 +52,909 @@ impl fmt::Display for Event {
     }
 }
 
+impl Event<'_> {
+    /// ## Name of the event
+    /// Returns the keyword that produced this event
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Continue => "continue",
+            Event::Break(_) => "break",
+            Event::Return(_) => "return",
+            Event::NoVal => "statement",
+        }
+    }
+}
+
+impl<'de> Value<'de> {
+    /// ## Name of the type
+    /// Returns the name of the value's type used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Integer(_) => "integer",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Function(_) | Value::NativeFunction(_) | Value::CompiledFunction(_) | Value::LoweredFunction(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::Event(e) => e.name(),
+        }
+    }
+
+    /// ## Creates new list
+    /// Wraps items into a new shared list value
+    pub fn list(items: Vec<Value<'de>>) -> Value<'de> {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    /// ## Creates new map
+    /// Wraps entries into a new shared map value
+    pub fn map(entries: BTreeMap<String, Value<'de>>) -> Value<'de> {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    /// ## Reads the element at index
+    /// Works on lists, strings (returning single character string), maps (indexed by the key)
+    /// and instances (indexed by the name of the field or method)
+    pub fn get_index(&self, index: &Value<'de>) -> Result<Value<'de>, RuntimeError> {
+        match self {
+            Value::Instance(instance) => instance.get(&Self::to_key(index)?, self),
+            Value::Map(entries) => {
+                let key = Self::to_key(index)?;
+                entries.borrow().get(&key).cloned().ok_or(RuntimeError::KeyNotFound { key, span: None })
+            },
+            Value::List(items) => {
+                let items = items.borrow();
+                let i = Self::to_index(index, items.len())?;
+                Ok(items[i].clone())
+            },
+            Value::String(s) => {
+                let len = s.chars().count();
+                let i = Self::to_index(index, len)?;
+                Ok(Value::String(s.chars().nth(i).expect("checked bounds").to_string()))
+            },
+            v => Err(RuntimeError::NotIndexable { target: v.type_name(), span: None })
+        }
+    }
+
+    /// ## Writes the element at index
+    /// Only lists, maps and instances can be modified, the write is visible through every copy of them,
+    /// writing a missing key adds it to the map or the instance
+    pub fn set_index(&self, index: &Value<'de>, value: Value<'de>) -> Result<(), RuntimeError> {
+        match self {
+            Value::Instance(instance) => {
+                instance.fields.borrow_mut().insert(Self::to_key(index)?, value);
+                Ok(())
+            },
+            Value::Map(entries) => {
+                entries.borrow_mut().insert(Self::to_key(index)?, value);
+                Ok(())
+            },
+            Value::List(items) => {
+                let mut items = items.borrow_mut();
+                let i = Self::to_index(index, items.len())?;
+                items[i] = value;
+                Ok(())
+            },
+            v => Err(RuntimeError::NotIndexable { target: v.type_name(), span: None })
+        }
+    }
+
+    fn to_index(index: &Value<'de>, len: usize) -> Result<usize, RuntimeError> {
+        match index {
+            Value::Integer(i) => usize::try_from(*i).ok()
+                .filter(|i| *i < len)
+                .ok_or(RuntimeError::IndexOutOfBounds { index: *i, len, span: None }),
+            Value::Number(n) if n.fract() == 0.0 => {
+                if *n < 0.0 || *n >= len as f64 {
+                    Err(RuntimeError::IndexOutOfBounds { index: *n as i64, len, span: None })
+                } else {
+                    Ok(*n as usize)
+                }
+            },
+            v => Err(RuntimeError::InvalidIndex { found: v.to_string(), span: None })
+        }
+    }
+
+    fn to_key(key: &Value<'de>) -> Result<String, RuntimeError> {
+        match key {
+            Value::String(s) => Ok(s.clone()),
+            v => Err(RuntimeError::InvalidKey { found: v.type_name(), span: None })
+        }
+    }
+
+    /// ## Binds method to the instance
+    /// Function gets new scope holding `this` in its first slot,
+    /// other values are returned as they are
+    pub fn bind(&self, this: Value<'de>) -> Value<'de> {
+        match self {
+            Value::Function(fun) => Value::Function(Rc::new(fun.bind(this))),
+            Value::CompiledFunction(fun) => Value::CompiledFunction(Rc::new(fun.bind(this))),
+            Value::LoweredFunction(fun) => Value::LoweredFunction(Rc::new(fun.bind(this))),
+            v => v.clone(),
+        }
+    }
+
+    /// ## Reads the method of the superclass
+    /// `self` is the superclass, the method is bound to `this`
+    pub fn get_super(&self, name: &str, this: Value<'de>) -> Result<Value<'de>, RuntimeError> {
+        match self {
+            Value::Class(class) => class.bound_method(name, this),
+            v => Err(RuntimeError::InvalidSuperclass { found: v.type_name(), span: None })
+        }
+    }
+
+    /// ## Rejects events
+    /// Returns the value itself or an error if it is an event 
+    /// that escaped the construct it belongs to
+    pub fn no_event(self) -> Result<Value<'de>, RuntimeError> {
+        match self {
+            Value::Event(Event::NoVal) => Err(RuntimeError::NoValue { span: None }),
+            Value::Event(e) => Err(RuntimeError::EventEscape { event: e.name(), span: None }),
+            v => Ok(v)
+        }
+    }
+}
+
 pub trait Eval<'a> {
-    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value, Error>;
+    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value<'a>, Error>;
+}
+
+
+/// ## Operands of arithmetic
+/// Two integers (bools count as 0 and 1) stay integers,
+/// as soon as one of them is a float both are promoted to floats
+enum Numeric {
+    Integers(i64, i64),
+    Floats(f64, f64),
 }
 
+impl Numeric {
+    fn of(lhs: &Value<'_>, rhs: &Value<'_>) -> Option<Numeric> {
+        let integer = |v: &Value<'_>| match v {
+            Value::Integer(i) => Some(*i),
+            Value::Bool(b) => Some(*b as i64),
+            _ => None,
+        };
+        let float = |v: &Value<'_>| match v {
+            Value::Number(n) => Some(*n),
+            v => integer(v).map(|i| i as f64),
+        };
+        match (integer(lhs), integer(rhs)) {
+            (Some(l), Some(r)) => Some(Numeric::Integers(l, r)),
+            _ => Some(Numeric::Floats(float(lhs)?, float(rhs)?)),
+        }
+    }
+}
+
+impl<'de> Value<'de> {
+    /// ## Arithmetic operation
+    /// `integers` returns `None` when the result does not fit into 64 bits
+    fn arithmetic(
+        self,
+        rhs: Self,
+        op: &str,
+        integers: fn(i64, i64) -> Option<i64>,
+        floats: fn(f64, f64) -> f64
+    ) -> Result<Value<'de>, RuntimeError> {
+        match Numeric::of(&self, &rhs) {
+            Some(Numeric::Integers(l, r)) => integers(l, r)
+                .map(Value::Integer)
+                .ok_or(RuntimeError::IntegerOverflow { op: op.to_string(), span: None }),
+            Some(Numeric::Floats(l, r)) => Ok(Value::Number(floats(l, r))),
+            None => Err(RuntimeError::TypeMismatch { op: op.to_string(), lhs: self.type_name(), rhs: rhs.type_name(), span: None })
+        }
+    }
+
+    /// ## Integer division
+    /// Rounds the quotient towards negative infinity, floats are divided
+    /// the same way but the result stays a float
+    pub fn int_div(self, rhs: Self) -> Result<Value<'de>, RuntimeError> {
+        if let Some(Numeric::Integers(_, 0)) = Numeric::of(&self, &rhs) {
+            return Err(RuntimeError::DivisionByZero { span: None })
+        }
+        self.arithmetic(rhs, "div", |l, r| {
+            let q = l.checked_div(r)?;
+            Some(if l % r != 0 && (l < 0) != (r < 0) { q - 1 } else { q })
+        }, |l, r| (l / r).floor())
+    }
 
-impl Div for Value {
-    type Output = Result<Value, Error>;
+    /// ## Exponentiation
+    /// Integer raised to non-negative integer stays an integer,
+    /// negative exponent gives a float, `2 ** -1` is `0.5`
+    pub fn pow(self, rhs: Self) -> Result<Value<'de>, RuntimeError> {
+        if let Some(Numeric::Integers(base, exp)) = Numeric::of(&self, &rhs) {
+            if exp < 0 {
+                return Ok(Value::Number((base as f64).powf(exp as f64)))
+            }
+        }
+        self.arithmetic(rhs, "**", |base, exp| base.checked_pow(u32::try_from(exp).ok()?), f64::powf)
+    }
+
+    /// ## Bitwise operation
+    /// Works only on integers, bools count as 0 and 1 again
+    fn bitwise(self, rhs: Self, op: &str, integers: fn(i64, i64) -> Option<i64>) -> Result<Value<'de>, RuntimeError> {
+        match Numeric::of(&self, &rhs) {
+            Some(Numeric::Integers(l, r)) => integers(l, r)
+                .map(Value::Integer)
+                .ok_or(RuntimeError::IntegerOverflow { op: op.to_string(), span: None }),
+            _ => Err(RuntimeError::TypeMismatch { op: op.to_string(), lhs: self.type_name(), rhs: rhs.type_name(), span: None })
+        }
+    }
+
+    /// ## Shift by the number of bits
+    /// Negative number of bits is rejected
+    fn shift(self, rhs: Self, op: &str, integers: fn(i64, i64) -> Option<i64>) -> Result<Value<'de>, RuntimeError> {
+        if let Some(Numeric::Integers(_, bits)) = Numeric::of(&self, &rhs) {
+            if bits < 0 {
+                return Err(RuntimeError::NegativeShift { op: op.to_string(), span: None })
+            }
+        }
+        self.bitwise(rhs, op, integers)
+    }
+
+    /// ## Bitwise negation
+    pub fn bit_not(self) -> Result<Value<'de>, RuntimeError> {
+        match self {
+            Value::Integer(i) => Ok(Value::Integer(!i)),
+            Value::Bool(b) => Ok(Value::Integer(!(b as i64))),
+            v => Err(RuntimeError::UnaryTypeMismatch { op: "~".to_string(), operand: v.type_name(), span: None }),
+        }
+    }
+}
+
+impl<'de> BitAnd for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.bitwise(rhs, "&", |l, r| Some(l & r))
+    }
+}
 
+impl<'de> BitOr for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.bitwise(rhs, "|", |l, r| Some(l | r))
+    }
+}
+
+impl<'de> BitXor for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        self.bitwise(rhs, "^", |l, r| Some(l ^ r))
+    }
+}
+
+impl<'de> Shl for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
+
+    /// bits shifted out of the integer are an overflow
+    fn shl(self, rhs: Self) -> Self::Output {
+        self.shift(rhs, "<<", |l, bits| {
+            if bits >= 64 {
+                return (l == 0).then_some(0)
+            }
+            let shifted = l << bits;
+            (shifted >> bits == l).then_some(shifted)
+        })
+    }
+}
+
+impl<'de> Shr for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
+
+    /// keeps the sign, shifting out all the bits gives 0 or -1
+    fn shr(self, rhs: Self) -> Self::Output {
+        self.shift(rhs, ">>", |l, bits| Some(l >> bits.min(63)))
+    }
+}
+
+impl<'de> Div for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
+
+    /// division of integers gives a float too, `div` keeps them integers
     fn div(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 / n2)),
-            _ => Err(DefaultRuntimeError {}.into()) // TODO change error
+        match Numeric::of(&self, &rhs) {
+            Some(Numeric::Integers(l, r)) => Ok(Value::Number(l as f64 / r as f64)),
+            Some(Numeric::Floats(l, r)) => Ok(Value::Number(l / r)),
+            None => Err(RuntimeError::TypeMismatch { op: "/".to_string(), lhs: self.type_name(), rhs: rhs.type_name(), span: None })
         }
     }
 }
 
-impl Mul for Value {
-    type Output = Result<Value, Error>;
+impl<'de> Rem for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
 
-    fn mul(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 * n2)),
-            (Value::Number(n1), Value::Bool(b1)) => Ok(Value::Number(n1 * b1 as usize as f64)),
-            (Value::Bool(b1), Value::Number(n1)) => Ok(Value::Number(n1 * b1 as usize as f64)),
-            (Value::Bool(b1), Value::Bool(b2)) => Ok(Value::Number((b1 as usize * b2 as usize) as f64)),
-            _ => Err(DefaultRuntimeError {}.into()) // TODO change error
+    /// remainder takes the sign of the divisor so it pairs with `div`
+    fn rem(self, rhs: Self) -> Self::Output {
+        if let Some(Numeric::Integers(_, 0)) = Numeric::of(&self, &rhs) {
+            return Err(RuntimeError::DivisionByZero { span: None })
         }
+        self.arithmetic(rhs, "%", |l, r| {
+            let m = l.wrapping_rem(r);
+            Some(if m != 0 && (m < 0) != (r < 0) { m + r } else { m })
+        }, float_rem)
+    }
+}
+
+/// Remainder of floats with the sign of the divisor
+pub(crate) fn float_rem(l: f64, r: f64) -> f64 {
+    let m = l % r;
+    if m != 0.0 && (m < 0.0) != (r < 0.0) { m + r } else { m }
+}
+
+impl<'de> Mul for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.arithmetic(rhs, "*", i64::checked_mul, |l, r| l * r)
     }
 }
 
-impl Add for Value {
-    type Output = Result<Value, Error>;
+impl<'de> Add for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
 
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Value::String(s1), Value::String(s2)) => Ok(Value::String(format!("{s1}{s2}"))),
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 + n2)),
-            (Value::Number(n1), Value::Bool(b)) => Ok(Value::Number(n1 + ((b as usize) as f64))),
-            (Value::Bool(b), Value::Number(n2)) => Ok(Value::Number(((b as usize) as f64) + n2)),
-            (Value::Bool(b1), Value::Bool(b2)) => Ok(Value::Number((b1 as usize + b2 as usize) as f64)),
-            _ => Err(DefaultRuntimeError {}.into()) // TODO change error
+            (Value::List(l1), Value::List(l2)) => {
+                let mut items = l1.borrow().clone();
+                items.extend(l2.borrow().iter().cloned());
+                Ok(Value::list(items))
+            },
+            (l, r) => l.arithmetic(r, "+", i64::checked_add, |l, r| l + r)
         }
     }
 }
 
-impl Sub for Value {
-    type Output = Result<Value, Error>;
+impl<'de> Sub for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 - n2)),
-            (Value::Number(n1), Value::Bool(b)) => Ok(Value::Number(n1 - ((b as usize) as f64))),
-            (Value::Bool(b), Value::Number(n2)) => Ok(Value::Number(((b as usize) as f64) - n2)),
-            (Value::Bool(b1), Value::Bool(b2)) => Ok(Value::Number((b1 as usize - b2 as usize) as f64)),
-            _ => Err(DefaultRuntimeError {}.into()) // TODO change error
-        }
+        self.arithmetic(rhs, "-", i64::checked_sub, |l, r| l - r)
     }
 }
 
-impl From<Value> for bool {
-    fn from(value: Value) -> Self {
+impl From<Value<'_>> for bool {
+    fn from(value: Value<'_>) -> Self {
         match value {
             Value::String(_) => true,
             Value::Number(n) => n != 0f64,
+            Value::Integer(i) => i != 0,
             Value::Bool(b) => b,
             Value::Nil => false,
+            Value::List(_) | Value::Map(_) => true,
+            Value::Function(_) | Value::NativeFunction(_) | Value::CompiledFunction(_) | Value::LoweredFunction(_) => true,
+            Value::Class(_) | Value::Instance(_) => true,
             Value::Event(_) => false,
         }
     }
 }
 
-impl Neg for Value {
-    type Output = Result<Value, Error>;
+impl<'de> Neg for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
 
     fn neg(self) -> Self::Output {
         match self {
             Value::Number(n) => Ok(Value::Number(-n)),
-            _ => Err(DefaultRuntimeError {}.into()), // TODO change error
+            Value::Integer(i) => i.checked_neg()
+                .map(Value::Integer)
+                .ok_or(RuntimeError::IntegerOverflow { op: "-".to_string(), span: None }),
+            v => Err(RuntimeError::UnaryTypeMismatch { op: "-".to_string(), operand: v.type_name(), span: None }),
         }
     }
 }
 
-impl Not for Value {
-    type Output = Result<Value, Error>;
+impl<'de> Not for Value<'de> {
+    type Output = Result<Value<'de>, RuntimeError>;
 
     fn not(self) -> Self::Output {
         match self {
             Value::Bool(b) => Ok(Value::Bool(!b)),
             Value::Number(n) => Ok(Value::Bool(n == 0f64)),
+            Value::Integer(i) => Ok(Value::Bool(i == 0)),
             Value::Nil => Ok(Value::Bool(true)),
-            _ => Err(DefaultRuntimeError {}.into()), // TODO change error
+            v => Err(RuntimeError::UnaryTypeMismatch { op: "!".to_string(), operand: v.type_name(), span: None }),
+        }
+    }
+}
+
+// integers and floats are compared by their exact numeric value,
+// values of other different types are never equal
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::Integer(l), Value::Integer(r)) => l == r,
+            (Value::Integer(i), Value::Number(n)) | (Value::Number(n), Value::Integer(i)) => {
+                compare_exactly(*i, *n) == Some(Ordering::Equal)
+            },
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Nil, Value::Nil) => true,
+            (Value::List(l), Value::List(r)) => l == r,
+            (Value::Map(l), Value::Map(r)) => l == r,
+            (Value::Function(l), Value::Function(r)) => l == r,
+            (Value::NativeFunction(l), Value::NativeFunction(r)) => l == r,
+            (Value::CompiledFunction(l), Value::CompiledFunction(r)) => l == r,
+            (Value::LoweredFunction(l), Value::LoweredFunction(r)) => l == r,
+            (Value::Class(l), Value::Class(r)) => l == r,
+            (Value::Instance(l), Value::Instance(r)) => l == r,
+            (Value::Event(l), Value::Event(r)) => l == r,
+            _ => false,
         }
     }
 }
 
-impl fmt::Display for Value {
+// values of different types are ordered by their type like the variants are declared
+impl PartialOrd for Value<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::String(l), Value::String(r)) => l.partial_cmp(r),
+            (Value::Number(l), Value::Number(r)) => l.partial_cmp(r),
+            (Value::Integer(l), Value::Integer(r)) => l.partial_cmp(r),
+            (Value::Integer(l), Value::Number(r)) => compare_exactly(*l, *r),
+            (Value::Number(l), Value::Integer(r)) => compare_exactly(*r, *l).map(Ordering::reverse),
+            (Value::Bool(l), Value::Bool(r)) => l.partial_cmp(r),
+            (Value::Nil, Value::Nil) => Some(Ordering::Equal),
+            (Value::List(l), Value::List(r)) => l.partial_cmp(r),
+            (Value::Map(l), Value::Map(r)) => l.partial_cmp(r),
+            (Value::Function(l), Value::Function(r)) => l.partial_cmp(r),
+            (Value::NativeFunction(l), Value::NativeFunction(r)) => l.partial_cmp(r),
+            (Value::CompiledFunction(l), Value::CompiledFunction(r)) => l.partial_cmp(r),
+            (Value::LoweredFunction(l), Value::LoweredFunction(r)) => l.partial_cmp(r),
+            (Value::Class(l), Value::Class(r)) => l.partial_cmp(r),
+            (Value::Instance(l), Value::Instance(r)) => l.partial_cmp(r),
+            (Value::Event(l), Value::Event(r)) => l.partial_cmp(r),
+            (l, r) => l.rank().partial_cmp(&r.rank()),
+        }
+    }
+}
+
+/// ## Compares integer with float
+/// Converting the integer to float loses precision above 2^53, so the float is
+/// compared instead: floats out of the range of i64 are beyond every integer,
+/// others are compared by their whole part and the fraction breaks the tie
+fn compare_exactly(i: i64, n: f64) -> Option<Ordering> {
+    // 2^63, the smallest float above i64::MAX
+    const LIMIT: f64 = 9223372036854775808.0;
+    if n.is_nan() {
+        return None
+    }
+    if n >= LIMIT {
+        return Some(Ordering::Less)
+    }
+    if n < -LIMIT {
+        return Some(Ordering::Greater)
+    }
+    let whole = n.floor();
+    match i.cmp(&(whole as i64)) {
+        Ordering::Equal if whole < n => Some(Ordering::Less),
+        ord => Some(ord),
+    }
+}
+
+impl Value<'_> {
+    /// Position of the type in the ordering of different types
+    fn rank(&self) -> u8 {
+        match self {
+            Value::String(_) => 0,
+            Value::Number(_) | Value::Integer(_) => 1,
+            Value::Bool(_) => 2,
+            Value::Nil => 3,
+            Value::List(_) => 4,
+            Value::Map(_) => 5,
+            Value::Function(_) => 6,
+            Value::NativeFunction(_) => 7,
+            Value::CompiledFunction(_) => 8,
+            Value::LoweredFunction(_) => 9,
+            Value::Class(_) => 10,
+            Value::Instance(_) => 11,
+            Value::Event(_) => 12,
+        }
+    }
+}
+
+impl fmt::Display for Value<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Value::String(s) => write!(f, "{}", s),
             Value::Number(n) => write!(f, "{}", n),
+            Value::Integer(i) => write!(f, "{}", i),
             Value::Bool(b) => write!(f, "{}", b),
             Value::Nil => write!(f, "nil"),
+            Value::List(items) => {
+                let items = items.borrow()
+                    .iter()
+                    .map(|v| match v {
+                        Value::String(s) => quote(s),
+                        v => v.to_string()
+                    })
+                    .collect::<Vec<String>>();
+                write!(f, "[{}]", items.join(", "))
+            },
+            // empty map is written the same way as its literal
+            Value::Map(entries) if entries.borrow().is_empty() => write!(f, "{{:}}"),
+            // keys and strings are written the way the lexer reads them
+            Value::Map(entries) => {
+                let entries = entries.borrow()
+                    .iter()
+                    .map(|(k, v)| match v {
+                        Value::String(s) => format!("{}: {}", quote(k), quote(s)),
+                        v => format!("{}: {v}", quote(k))
+                    })
+                    .collect::<Vec<String>>();
+                write!(f, "{{{}}}", entries.join(", "))
+            },
+            Value::Function(fun) => write!(f, "{fun}"),
+            Value::NativeFunction(fun) => write!(f, "{fun}"),
+            Value::CompiledFunction(fun) => write!(f, "{fun}"),
+            Value::LoweredFunction(fun) => write!(f, "{fun}"),
+            Value::Class(class) => write!(f, "{class}"),
+            Value::Instance(instance) => write!(f, "{instance}"),
             Value::Event(e) => write!(f, "{}", e),
         }
     }
-}
\ No newline at end of file
+}
+
+/// ## Function value
+/// Function declaration together with the environment it was declared in,
+/// calling it evaluates the body in a fork of that environment
+pub struct Closure<'de> {
+    fun: FnBlock<'de>,
+    env: Captured<'de>
+}
+
+impl<'de> Closure<'de> {
+    pub fn new(fun: FnBlock<'de>, env: CtxTree<'de>) -> Self {
+        Self { fun, env: Captured::new(env) }
+    }
+
+    pub fn name(&self) -> &'de str {
+        self.fun.name()
+    }
+
+    pub fn call(&self, args: Vec<Value<'de>>) -> Result<Value<'de>, Error> {
+        self.fun.exec(&self.env.ctx(), args)
+    }
+
+    /// ## Binds the method
+    /// `this` is defined in new scope between the function and its environment
+    pub fn bind(&self, this: Value<'de>) -> Self {
+        let env = self.env.ctx().fork();
+        env.define(0, this);
+        Self::new(self.fun.clone(), env)
+    }
+}
+
+impl<'de> Capturing<'de> for Closure<'de> {
+    fn env(&self) -> &Captured<'de> {
+        &self.env
+    }
+
+    fn with_env(&self, env: Captured<'de>) -> Self {
+        Self { fun: self.fun.clone(), env }
+    }
+}
+
+// functions are compared by identity, copies of the same declaration
+// capturing the same environment are equal
+impl PartialEq for Closure<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.fun.same(&other.fun) && self.env.same(&other.env)
+    }
+}
+
+impl PartialOrd for Closure<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for Closure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Closure({})", self.name())
+    }
+}
+
+impl fmt::Display for Closure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
+
+/// ## Class value
+/// Methods are the functions of whichever backend declared the class,
+/// they are bound to the instance when they are read from it
+pub struct Class<'de> {
+    name: &'de str,
+    superclass: Option<Rc<Class<'de>>>,
+    methods: HashMap<&'de str, Value<'de>>,
+}
+
+impl<'de> Class<'de> {
+    /// ## Creates new class
+    /// `superclass` has to be a class
+    pub fn new(name: &'de str, superclass: Option<Value<'de>>, methods: HashMap<&'de str, Value<'de>>) -> Result<Self, RuntimeError> {
+        let superclass = match superclass {
+            Some(Value::Class(class)) => Some(class),
+            Some(v) => return Err(RuntimeError::InvalidSuperclass { found: v.type_name(), span: None }),
+            None => None,
+        };
+        Ok(Self { name, superclass, methods })
+    }
+
+    pub fn name(&self) -> &'de str {
+        self.name
+    }
+
+    /// ## Finds the method
+    /// Searches the class first and then its superclasses
+    pub fn method(&self, name: &str) -> Option<&Value<'de>> {
+        self.methods.get(name).or_else(|| self.superclass.as_ref()?.method(name))
+    }
+
+    /// ## Finds the method bound to `this`
+    pub fn bound_method(&self, name: &str, this: Value<'de>) -> Result<Value<'de>, RuntimeError> {
+        match self.method(name) {
+            Some(method) => Ok(method.bind(this)),
+            None => Err(RuntimeError::UndefinedProperty { name: name.to_string(), class: self.name.to_string(), span: None })
+        }
+    }
+
+    /// ## Creates new instance
+    /// Returns the instance together with its bound `init` method that has to be called
+    /// with the arguments, classes without `init` don't take any arguments
+    pub fn instantiate(self: &Rc<Self>, argc: usize) -> Result<(Value<'de>, Option<Value<'de>>), RuntimeError> {
+        let instance = Value::Instance(Rc::new(Instance { class: self.clone(), fields: RefCell::default() }));
+        match self.method("init") {
+            Some(init) => Ok((instance.clone(), Some(init.bind(instance)))),
+            None if argc == 0 => Ok((instance, None)),
+            None => Err(RuntimeError::ClassArityMismatch { class: self.name.to_string(), found: argc, span: None })
+        }
+    }
+}
+
+// classes are compared by identity
+impl PartialEq for Class<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PartialOrd for Class<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for Class<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Class({})", self.name)
+    }
+}
+
+impl fmt::Display for Class<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+/// ## Instance of the class
+/// Fields are created by assigning to them and shadow the methods of the same name
+pub struct Instance<'de> {
+    class: Rc<Class<'de>>,
+    fields: RefCell<BTreeMap<String, Value<'de>>>,
+}
+
+impl<'de> Instance<'de> {
+    pub fn class(&self) -> &Rc<Class<'de>> {
+        &self.class
+    }
+
+    /// ## Reads the field or the method
+    /// `this` is the value of the instance itself, methods are bound to it
+    fn get(&self, name: &str, this: &Value<'de>) -> Result<Value<'de>, RuntimeError> {
+        if let Some(v) = self.fields.borrow().get(name) {
+            return Ok(v.clone())
+        }
+        self.class.bound_method(name, this.clone())
+    }
+}
+
+// instances are compared by identity
+impl PartialEq for Instance<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PartialOrd for Instance<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for Instance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Instance({})", self.class.name)
+    }
+}
+
+impl fmt::Display for Instance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
+
+/// Signature of functions provided by the host
+pub type NativeCallback<'de> = dyn Fn(Vec<Value<'de>>) -> Result<Value<'de>, Error> + 'de;
+
+/// ## Native function value
+/// Rust closure registered by the host that scripts call like any other function
+pub struct NativeFn<'de> {
+    name: String,
+    arity: Option<usize>,
+    fun: Box<NativeCallback<'de>>
+}
+
+impl<'de> NativeFn<'de> {
+    /// ## Creates native function
+    /// `arity` of `None` means the function accepts any number of arguments
+    pub fn new(name: &str, arity: Option<usize>, fun: impl Fn(Vec<Value<'de>>) -> Result<Value<'de>, Error> + 'de) -> Self {
+        Self { name: name.to_string(), arity, fun: Box::new(fun) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `None` means the function accepts any number of arguments
+    pub fn arity(&self) -> Option<usize> {
+        self.arity
+    }
+
+    pub fn call(&self, args: Vec<Value<'de>>) -> Result<Value<'de>, Error> {
+        if let Some(arity) = self.arity {
+            if arity != args.len() {
+                return Err(RuntimeError::ArityMismatch {
+                    name: self.name.clone(),
+                    expected: arity,
+                    found: args.len(),
+                    span: None
+                }.into())
+            }
+        }
+        // events belong to the script, host can't break out of its loops
+        Ok((self.fun)(args)?.no_event()?)
+    }
+}
+
+// functions are compared by identity
+impl PartialEq for NativeFn<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PartialOrd for NativeFn<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for NativeFn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFn({})", self.name)
+    }
+}
+
+impl fmt::Display for NativeFn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl From<f64> for Value<'_> {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<i64> for Value<'_> {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<bool> for Value<'_> {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<String> for Value<'_> {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value<'_> {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<()> for Value<'_> {
+    fn from(_: ()) -> Self {
+        Value::Nil
+    }
+}
+
+impl<'de, T: Into<Value<'de>>> From<Option<T>> for Value<'de> {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Nil, Into::into)
+    }
+}
+
+impl<'de, T: Into<Value<'de>>> From<Vec<T>> for Value<'de> {
+    fn from(value: Vec<T>) -> Self {
+        Value::list(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<'de, T: Into<Value<'de>>> From<BTreeMap<String, T>> for Value<'de> {
+    fn from(value: BTreeMap<String, T>) -> Self {
+        Value::map(value.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl TryFrom<Value<'_>> for f64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value<'_>) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(n) => Ok(n),
+            Value::Integer(i) => Ok(i as f64),
+            v => Err(RuntimeError::UnexpectedType { expected: "number", found: v.type_name(), span: None })
+        }
+    }
+}
+
+impl TryFrom<Value<'_>> for String {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value<'_>) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            v => Err(RuntimeError::UnexpectedType { expected: "string", found: v.type_name(), span: None })
+        }
+    }
+}
+
+impl<'de> TryFrom<Value<'de>> for Vec<Value<'de>> {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value<'de>) -> Result<Self, Self::Error> {
+        match value {
+            Value::List(items) => Ok(items.borrow().clone()),
+            v => Err(RuntimeError::UnexpectedType { expected: "list", found: v.type_name(), span: None })
+        }
+    }
+}
+
+impl<'de> TryFrom<Value<'de>> for BTreeMap<String, Value<'de>> {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value<'de>) -> Result<Self, Self::Error> {
+        match value {
+            Value::Map(entries) => Ok(entries.borrow().clone()),
+            v => Err(RuntimeError::UnexpectedType { expected: "map", found: v.type_name(), span: None })
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+use miette::{Error, SourceSpan};
+
+use crate::{context::CtxTree, error::ResolveError, evaluator::{Class, Closure, Eval, Event, Value}, resolver::{Binding, Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp, Var}, lsp::{Outline, Symbol, SymbolKind}, formatter::{Format, Formatter}};
+
+use super::{Atom, ExprTree, FnBlock};
+
+/// ## Class declaration
+/// Methods of the subclass are declared in the scope holding `super`,
+/// every method gets the scope holding `this` when it's bound to the instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDecl<'de> {
+    name: &'de str,
+    binding: Binding,
+    /// variable holding the superclass
+    superclass: Option<Box<ExprTree<'de>>>,
+    methods: Vec<FnBlock<'de>>,
+    /// lines of the doc comment
+    doc: Rc<[&'de str]>,
+    span: SourceSpan
+}
+
+impl<'de: 'a, 'a> Eval<'a> for ClassDecl<'de> {
+    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let (superclass, env) = match self.superclass {
+            Some(ref superclass) => {
+                let superclass = operand!(superclass, ctx);
+                let env = ctx.fork();
+                env.define(0, superclass.clone());
+                (Some(superclass), env)
+            },
+            None => (None, ctx.clone()),
+        };
+        let methods: HashMap<_, _> = self.methods.iter()
+            .map(|m| (m.name(), Value::Function(Rc::new(Closure::new(m.clone(), env.clone())))))
+            .collect();
+        let class = Class::new(self.name, superclass, methods).map_err(|e| e.at(self.class_span()))?;
+        ctx.declare(self.name, self.binding, Value::Class(Rc::new(class)));
+        Ok(Value::Event(Event::NoVal))
+    }
+}
+
+impl<'de> Resolve<'de> for ClassDecl<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        // class can't inherit from itself, but its methods can use it
+        self.binding = resolver.declare(self.name);
+        if let Some(ref mut superclass) = self.superclass {
+            if let ExprTree::Atom(Atom::Ident(name, _), span) = &**superclass {
+                if *name == self.name {
+                    return Err(ResolveError::SelfInheritance { name: name.to_string(), span: *span }.into())
+                }
+            }
+            superclass.resolve(resolver)?;
+        }
+        resolver.define(self.name);
+
+        let inherits = self.superclass.is_some();
+        if inherits {
+            resolver.begin_scope();
+            resolver.define_implicit("super", self.name);
+        }
+        resolver.begin_scope();
+        resolver.define_implicit("this", self.name);
+        let res = self.methods.iter_mut().try_for_each(|m| {
+            let initializer = m.name() == "init";
+            m.resolve_body(resolver, initializer)
+        });
+        resolver.end_scope();
+        if inherits {
+            resolver.end_scope();
+        }
+        res
+    }
+}
+
+impl<'de> Compile<'de> for ClassDecl<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        let inherits = self.superclass.is_some();
+        if let Some(ref superclass) = self.superclass {
+            compiler.operand(superclass);
+            compiler.begin_scope(true, self.span);
+            compiler.define_var("super", Binding::Local { depth: 0, slot: 0 }, superclass.span());
+            compiler.emit(vm::Op::Pop, superclass.span());
+            compiler.get_var("super", Binding::Local { depth: 0, slot: 0 }, superclass.span());
+        }
+        for method in &self.methods {
+            let fun = compiler.compile_method(method.name(), method.args().len(), method.block());
+            let fun = compiler.function(fun, method.span());
+            compiler.emit(vm::Op::Closure(fun), method.span());
+        }
+        let name = compiler.name(self.name, self.class_span());
+        let methods = compiler.narrow(self.methods.len(), "methods", self.class_span());
+        compiler.emit(vm::Op::Class { name, methods, inherits }, self.class_span());
+        if inherits {
+            compiler.end_scope(self.span);
+        }
+        compiler.define_var(self.name, self.binding, self.span);
+    }
+}
+
+impl<'de> Lower<'de> for ClassDecl<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let superclass = self.superclass.as_ref().map(|superclass| {
+            let src = builder.operand(superclass);
+            builder.begin_scope(self.span);
+            builder.emit(Inst::Define { var: Var::Local { name: "super", depth: 0, slot: 0 }, src }, superclass.span());
+            src
+        });
+        let methods = self.methods.iter()
+            .map(|method| {
+                let fun = Builder::lower_function(method.name(), method.args().len(), method.block());
+                let fun = builder.function(fun);
+                let dst = builder.temp();
+                builder.emit(Inst::Closure { dst, fun }, method.span());
+                (method.name(), dst)
+            })
+            .collect();
+        let dst = builder.temp();
+        builder.emit(Inst::Class { dst, name: self.name, superclass, methods }, self.class_span());
+        if superclass.is_some() {
+            builder.end_scope(self.span);
+        }
+        builder.emit(Inst::Define { var: Builder::var(self.name, self.binding), src: dst }, self.span);
+        None
+    }
+}
+
+impl<'de> Outline<'de> for ClassDecl<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        let methods = self.methods.iter()
+            .map(|method| {
+                let mut children = Vec::new();
+                method.block().outline(&mut children);
+                Symbol { name: method.name(), kind: SymbolKind::Method, span: method.span(), params: method.args().to_vec(), doc: method.doc().into(), children }
+            })
+            .collect();
+        symbols.push(Symbol { name: self.name, kind: SymbolKind::Class, span: self.span, params: Vec::new(), doc: self.doc.clone(), children: methods });
+    }
+}
+
+impl<'de> Format<'de> for ClassDecl<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        f.write(&format!("class {}", self.name));
+        if let Some(ref superclass) = self.superclass {
+            f.write(" < ");
+            superclass.format(f);
+        }
+        f.write(" ");
+        f.methods(&self.methods, self.span);
+    }
+}
+
+impl<'de> ClassDecl<'de> {
+    pub fn new(name: &'de str, superclass: Option<Box<ExprTree<'de>>>, methods: Vec<FnBlock<'de>>, span: SourceSpan) -> Self {
+        Self { name, binding: Binding::Global, superclass, methods, doc: Rc::new([]), span }
+    }
+
+    pub fn with_doc(self, doc: Vec<&'de str>) -> Self {
+        Self { doc: doc.into(), ..self }
+    }
+
+    pub fn name(&self) -> &'de str {
+        self.name
+    }
+
+    pub fn methods(&self) -> &[FnBlock<'de>] {
+        &self.methods
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    /// invalid superclass is reported at the superclass
+    fn class_span(&self) -> SourceSpan {
+        self.superclass.as_ref().map_or(self.span, |superclass| superclass.span())
+    }
+}
+
+impl fmt::Display for ClassDecl<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(class {}", self.name)?;
+        if let Some(ref superclass) = self.superclass {
+            match **superclass {
+                ExprTree::Atom(Atom::Ident(name, _), _) => write!(f, " < {name}")?,
+                ref superclass => write!(f, " < {superclass}")?,
+            }
+        }
+        for method in &self.methods {
+            write!(f, " {method}")?;
+        }
+        write!(f, ")")
+    }
+}
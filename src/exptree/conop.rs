@@ -1,7 +1,7 @@
 use std::fmt;
-use miette::Error;
+use miette::{Error, SourceSpan};
 
-use crate::{error::DefaultRuntimeError, evaluator::{Eval, Value}};
+use crate::{error::RuntimeError, evaluator::{Eval, Value}, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{BinOp, Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
 
 use super::{Atom, ExprTree};
 
@@ -12,6 +12,17 @@ pub enum Op {
     Plus,
     Star,
     Slash,
+    /// integer division
+    Div,
+    Percent,
+    StarStar,
+
+    // bitwise
+    Ampersand,
+    Pipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
 
     BangEqual,
     EqualEqual,
@@ -22,40 +33,63 @@ pub enum Op {
     Equal,
     And,
     Or,
+
+    // postfix
+    Index,
+    Call,
+    Dot,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConOp<'de> {
     op: Op,
     lhs: Box<ExprTree<'de>>,
-    rhs: Box<ExprTree<'de>>
+    rhs: Box<ExprTree<'de>>,
+    span: SourceSpan
 }
 
 impl<'de: 'a, 'a> Eval<'a> for ConOp<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error> {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
         if self.op == Op::Equal {
-            return if let ExprTree::Atom(Atom::Ident(id)) = *self.lhs {
-                let set_res = ctx.set(id, self.rhs.eval(ctx)?);
-                if set_res.is_err() { 
-                    Err(DefaultRuntimeError {}.into()) // TODO change errors 
-                } else {
-                    Ok(Value::Nil)
-                }
-            } else {
-                Err(DefaultRuntimeError {}.into()) // TODO change errors
+            return match *self.lhs {
+                ExprTree::Atom(Atom::Ident(id, binding), lhs_span) => {
+                    let v = operand!(self.rhs, ctx);
+                    match ctx.assign(id, binding, v) {
+                        Ok(()) => Ok(Value::Nil),
+                        Err(_) => Err(RuntimeError::UndefinedVariable { name: id.to_string(), span: Some(lhs_span) }.into())
+                    }
+                },
+                ExprTree::Index(ref index) => {
+                    let v = operand!(self.rhs, ctx);
+                    index.assign(ctx, v)
+                },
+                _ => Err(RuntimeError::InvalidAssignmentTarget { span: Some(self.lhs.span()) }.into())
             }
         }
 
-        let left = self.lhs.eval(ctx)?;
-        let right = self.rhs.eval(ctx)?;
-        if matches!(left, Value::Event(_)) || matches!(right, Value::Event(_)) {
-            return Err(DefaultRuntimeError {}.into()); // TODO change errors
-        }
+        let left = operand!(self.lhs, ctx);
+        // logical operators evaluate rhs only if lhs does not decide the result,
+        // the deciding operand itself is the result
         match self.op {
+            Op::And if !bool::from(left.clone()) => return Ok(left),
+            Op::Or if left.clone().into() => return Ok(left),
+            Op::And | Op::Or => return Ok(operand!(self.rhs, ctx)),
+            _ => {}
+        }
+        let right = operand!(self.rhs, ctx);
+        let res = match self.op {
             Op::Minus => left - right,
             Op::Plus => left + right,
             Op::Star => left * right,
             Op::Slash => left / right,
+            Op::Div => left.int_div(right),
+            Op::Percent => left % right,
+            Op::StarStar => left.pow(right),
+            Op::Ampersand => left & right,
+            Op::Pipe => left | right,
+            Op::Caret => left ^ right,
+            Op::LessLess => left << right,
+            Op::GreaterGreater => left >> right,
 
             Op::BangEqual => Ok(Value::Bool(left != right)),
             Op::EqualEqual => Ok(Value::Bool(left == right)),
@@ -63,17 +97,163 @@ impl<'de: 'a, 'a> Eval<'a> for ConOp<'de> {
             Op::GreaterEqual => Ok(Value::Bool(left >= right)),
             Op::Less => Ok(Value::Bool(left < right)),
             Op::Greater => Ok(Value::Bool(left > right)),
-            Op::And => Ok(Value::Bool(left.into() && right.into())),
-            Op::Or => Ok(Value::Bool(left.into() || right.into())),
+            Op::Equal | Op::And | Op::Or => unreachable!("already checked"),
+            Op::Index | Op::Call | Op::Dot => unreachable!("postfix operators are parsed into their own nodes"),
+        };
+        res.map_err(|e| e.at(self.span).into())
+    }
+}
+
+impl<'de> Resolve<'de> for ConOp<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.lhs.resolve(resolver)?;
+        self.rhs.resolve(resolver)
+    }
+}
+
+impl<'de> Compile<'de> for ConOp<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        let code = match self.op {
+            Op::Equal => {
+                match *self.lhs {
+                    ExprTree::Atom(Atom::Ident(id, binding), lhs_span) => {
+                        compiler.operand(&self.rhs);
+                        compiler.set_var(id, binding, lhs_span);
+                    },
+                    ExprTree::Index(ref index) => {
+                        compiler.operand(&self.rhs);
+                        index.compile_assign(compiler);
+                    },
+                    _ => { compiler.emit(vm::Op::InvalidAssignment, self.lhs.span()); }
+                }
+                return
+            },
+            Op::And | Op::Or => {
+                compiler.operand(&self.lhs);
+                let jump = match self.op {
+                    Op::And => vm::Op::JumpIfFalse(0),
+                    _ => vm::Op::JumpIfTrue(0),
+                };
+                let jump = compiler.emit(jump, self.span);
+                compiler.emit(vm::Op::Pop, self.span);
+                compiler.operand(&self.rhs);
+                compiler.patch_jump(jump);
+                return
+            },
+            Op::Minus => vm::Op::Sub,
+            Op::Plus => vm::Op::Add,
+            Op::Star => vm::Op::Mul,
+            Op::Slash => vm::Op::Div,
+            Op::Div => vm::Op::IntDiv,
+            Op::Percent => vm::Op::Mod,
+            Op::StarStar => vm::Op::Pow,
+            Op::Ampersand => vm::Op::BitAnd,
+            Op::Pipe => vm::Op::BitOr,
+            Op::Caret => vm::Op::BitXor,
+            Op::LessLess => vm::Op::Shl,
+            Op::GreaterGreater => vm::Op::Shr,
+            Op::BangEqual => vm::Op::NotEqual,
+            Op::EqualEqual => vm::Op::Equal,
+            Op::LessEqual => vm::Op::LessEqual,
+            Op::GreaterEqual => vm::Op::GreaterEqual,
+            Op::Less => vm::Op::Less,
+            Op::Greater => vm::Op::Greater,
+            Op::Index | Op::Call | Op::Dot => unreachable!("postfix operators are parsed into their own nodes"),
+        };
+        compiler.operand(&self.lhs);
+        compiler.operand(&self.rhs);
+        compiler.emit(code, self.span);
+    }
+}
+
+impl<'de> Lower<'de> for ConOp<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let op = match self.op {
+            Op::Equal => {
+                match *self.lhs {
+                    ExprTree::Atom(Atom::Ident(id, binding), lhs_span) => {
+                        let src = builder.operand(&self.rhs);
+                        builder.emit(Inst::Store { var: Builder::var(id, binding), src }, lhs_span);
+                    },
+                    ExprTree::Index(ref index) => {
+                        let src = builder.operand(&self.rhs);
+                        index.lower_assign(builder, src);
+                    },
+                    _ => builder.emit(Inst::InvalidAssignment, self.lhs.span()),
+                }
+                return Some(builder.constant(Value::Nil, self.span))
+            },
+            Op::And | Op::Or => {
+                // result is the deciding operand
+                let res = builder.temp();
+                let lhs = builder.operand(&self.lhs);
+                builder.emit(Inst::Copy { dst: res, src: lhs }, self.span);
+                let (rhs_label, end) = (builder.label(), builder.label());
+                let (then, otherwise) = match self.op {
+                    Op::And => (rhs_label, end),
+                    _ => (end, rhs_label),
+                };
+                builder.emit(Inst::Branch { cond: res, then, otherwise }, self.span);
+                builder.emit(Inst::Label(rhs_label), self.span);
+                let rhs = builder.operand(&self.rhs);
+                builder.emit(Inst::Copy { dst: res, src: rhs }, self.span);
+                builder.emit(Inst::Label(end), self.span);
+                return Some(res)
+            },
+            Op::Minus => BinOp::Sub,
+            Op::Plus => BinOp::Add,
+            Op::Star => BinOp::Mul,
+            Op::Slash => BinOp::Div,
+            Op::Div => BinOp::IntDiv,
+            Op::Percent => BinOp::Mod,
+            Op::StarStar => BinOp::Pow,
+            Op::Ampersand => BinOp::BitAnd,
+            Op::Pipe => BinOp::BitOr,
+            Op::Caret => BinOp::BitXor,
+            Op::LessLess => BinOp::Shl,
+            Op::GreaterGreater => BinOp::Shr,
+            Op::BangEqual => BinOp::NotEqual,
+            Op::EqualEqual => BinOp::Equal,
+            Op::LessEqual => BinOp::LessEqual,
+            Op::GreaterEqual => BinOp::GreaterEqual,
+            Op::Less => BinOp::Less,
+            Op::Greater => BinOp::Greater,
+            Op::Index | Op::Call | Op::Dot => unreachable!("postfix operators are parsed into their own nodes"),
+        };
+        let lhs = builder.operand(&self.lhs);
+        let rhs = builder.operand(&self.rhs);
+        let dst = builder.temp();
+        builder.emit(Inst::Binary { dst, op, lhs, rhs }, self.span);
+        Some(dst)
+    }
+}
 
-            Op::Equal => unreachable!("already checked"),
+impl<'de> Outline<'de> for ConOp<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        self.lhs.outline(symbols);
+        self.rhs.outline(symbols);
+    }
+}
+
+impl<'de> Format<'de> for ConOp<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        self.lhs.format(f);
+        match self.op {
+            Op::And => f.write(" and "),
+            Op::Or => f.write(" or "),
+            op => f.write(&format!(" {op} ")),
         }
+        self.rhs.format(f);
     }
 }
 
 impl<'de> ConOp<'de> {
-    pub fn new(op: Op, lhs: Box<ExprTree<'de>>, rhs: Box<ExprTree<'de>>) -> Self {
-        Self { op, lhs, rhs }
+    pub fn new(op: Op, lhs: Box<ExprTree<'de>>, rhs: Box<ExprTree<'de>>, span: SourceSpan) -> Self {
+        Self { op, lhs, rhs, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
     }
 }
 
@@ -90,6 +270,14 @@ impl fmt::Display for Op {
             Self::Plus => write!(f, "+"),
             Self::Star => write!(f, "*"),
             Self::Slash => write!(f, "/"),
+            Self::Div => write!(f, "div"),
+            Self::Percent => write!(f, "%"),
+            Self::StarStar => write!(f, "**"),
+            Self::Ampersand => write!(f, "&"),
+            Self::Pipe => write!(f, "|"),
+            Self::Caret => write!(f, "^"),
+            Self::LessLess => write!(f, "<<"),
+            Self::GreaterGreater => write!(f, ">>"),
             Self::BangEqual => write!(f, "!="),
             Self::EqualEqual => write!(f, "=="),
             Self::LessEqual => write!(f, "<="),
@@ -99,6 +287,9 @@ impl fmt::Display for Op {
             Self::Equal => write!(f, "="),
             Self::And => write!(f, "&&"),
             Self::Or => write!(f, "||"),
+            Self::Index => write!(f, "[]"),
+            Self::Call => write!(f, "()"),
+            Self::Dot => write!(f, "."),
         }
     }
 }
\ No newline at end of file
@@ -1,46 +1,205 @@
-use std::fmt;
-use miette::Error;
+use std::{fmt, rc::Rc};
+use miette::{Error, SourceSpan};
 
-use crate::{context::CtxTree, error::DefaultRuntimeError, evaluator::{Eval, Event, Value}};
+use crate::{context::CtxTree, error::{ResolveError, RuntimeError}, evaluator::{Closure, Eval, Event, Value}, resolver::{Binding, Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol, SymbolKind}, formatter::{Format, Formatter}};
 
 use super::ExprTree;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FnBlock<'de> {
-    ident: &'de str,
-    args: Vec<&'de str>,
-    block: Box<ExprTree<'de>>
+    ident: Option<&'de str>,
+    binding: Binding,
+    args: Rc<[&'de str]>,
+    block: Rc<ExprTree<'de>>,
+    /// lines of the doc comment
+    doc: Rc<[&'de str]>,
+    span: SourceSpan
 }
 
 impl<'de: 'a, 'a> Eval<'a> for FnBlock<'de> {
-    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value, Error> {
-        // here we just add fn to context
-        ctx.insert_fn(self.ident, self.clone());
-        Ok(Value::Event(Event::NoVal))
+    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value<'a>, Error> {
+        // function captures the context it is declared in
+        let fun = Value::Function(Rc::new(Closure::new(self.clone(), ctx.clone())));
+        match self.ident {
+            Some(ident) => {
+                ctx.declare(ident, self.binding, fun);
+                Ok(Value::Event(Event::NoVal))
+            },
+            None => Ok(fun)
+        }
+    }
+}
+
+impl<'de> Resolve<'de> for FnBlock<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        // function is defined before its body so it can call itself
+        if let Some(ident) = self.ident {
+            self.binding = resolver.declare(ident);
+            resolver.define(ident);
+        }
+        self.resolve_body(resolver, false)
+    }
+}
+
+impl<'de> Compile<'de> for FnBlock<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        let fun = compiler.compile_function(self.name(), self.args.len(), &self.block);
+        let fun = compiler.function(fun, self.span);
+        compiler.emit(vm::Op::Closure(fun), self.span);
+        if let Some(ident) = self.ident {
+            compiler.define_var(ident, self.binding, self.span);
+        }
+    }
+}
+
+impl<'de> Lower<'de> for FnBlock<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let fun = Builder::lower_function(self.name(), self.args.len(), &self.block);
+        let fun = builder.function(fun);
+        let dst = builder.temp();
+        builder.emit(Inst::Closure { dst, fun }, self.span);
+        match self.ident {
+            Some(ident) => {
+                builder.emit(Inst::Define { var: Builder::var(ident, self.binding), src: dst }, self.span);
+                None
+            },
+            None => Some(dst),
+        }
+    }
+}
+
+impl<'de> Outline<'de> for FnBlock<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        match self.ident {
+            Some(name) => {
+                let mut children = Vec::new();
+                self.block.outline(&mut children);
+                symbols.push(Symbol { name, kind: SymbolKind::Function, span: self.span, params: self.args.to_vec(), doc: self.doc.clone(), children });
+            },
+            // declarations of anonymous function belong to the enclosing symbol
+            None => self.block.outline(symbols),
+        }
+    }
+}
+
+impl<'de> Format<'de> for FnBlock<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        match self.ident {
+            Some(name) => f.write(&format!("fun {name}(")),
+            None => f.write("fun ("),
+        }
+        self.format_rest(f);
     }
 }
 
 impl<'de> FnBlock<'de> {
-    pub fn new(ident: &'de str, args: Vec<&'de str>, block: Box<ExprTree<'de>>) -> Self {
-        Self { ident, args, block }
+    pub fn new(ident: Option<&'de str>, args: Vec<&'de str>, block: Box<ExprTree<'de>>, span: SourceSpan) -> Self {
+        Self { ident, binding: Binding::Global, args: args.into(), block: Rc::from(block), doc: Rc::new([]), span }
+    }
+
+    pub fn with_doc(self, doc: Vec<&'de str>) -> Self {
+        Self { doc: doc.into(), ..self }
+    }
+
+    /// ## Doc comment of the function
+    /// One item per `///` line
+    pub fn doc(&self) -> &[&'de str] {
+        &self.doc
+    }
+
+    /// ## Name of the named function
+    /// Returns `None` for anonymous functions
+    pub fn ident(&self) -> Option<&'de str> {
+        self.ident
+    }
+
+    /// ## Names of the parameters
+    pub fn args(&self) -> &[&'de str] {
+        &self.args
+    }
+
+    pub fn block(&self) -> &ExprTree<'de> {
+        &self.block
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    /// ## Resolves the function without declaring it
+    /// Used for methods, they are not variables
+    pub fn resolve_body(&mut self, resolver: &mut Resolver<'de>, initializer: bool) -> Result<(), Error> {
+        for (i, arg) in self.args.iter().enumerate() {
+            if self.args[..i].contains(arg) {
+                return Err(ResolveError::DuplicateParameter { name: arg.to_string(), span: self.span }.into())
+            }
+        }
+        // mirrors the scope forked for the arguments in `exec`
+        resolver.begin_function(initializer);
+        for arg in self.args.iter() {
+            resolver.declare(arg);
+            resolver.define(arg);
+        }
+        let res = Rc::make_mut(&mut self.block).resolve(resolver);
+        resolver.end_function();
+        res
+    }
+
+    /// ## Formats the function as a method
+    /// methods are written without the `fun` keyword
+    pub fn format_method(&self, f: &mut Formatter<'de>) {
+        f.write(&format!("{}(", self.name()));
+        self.format_rest(f);
+    }
+
+    /// Writes parameters and the body
+    fn format_rest(&self, f: &mut Formatter<'de>) {
+        f.write(&self.args.join(", "));
+        f.write(") ");
+        self.block.format(f);
+    }
+
+    /// ## Name of the function
+    /// Anonymous functions are called `anonymous`
+    pub fn name(&self) -> &'de str {
+        self.ident.unwrap_or("anonymous")
+    }
+
+    /// ## Same declaration
+    /// copies of the declaration share the body
+    pub fn same(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.block, &other.block)
     }
 
-    pub fn exec(&self, ctx: &crate::context::CtxTree<'de>, args: Vec<Value>) -> Result<Value, Error> {
+    pub fn exec(&self, ctx: &CtxTree<'de>, args: Vec<Value<'de>>) -> Result<Value<'de>, Error> {
         let fork = ctx.fork();
         if self.args.len() != args.len() {
-            return Err(DefaultRuntimeError {}.into()) // TODO change error
+            return Err(RuntimeError::ArityMismatch {
+                name: self.name().to_string(),
+                expected: self.args.len(),
+                found: args.len(),
+                span: None
+            }.into())
         }
-        for i in 0..args.len() {
-            fork.insert(self.args[i], args[i].clone());
+        // arguments take the first slots in the order they were declared
+        for (slot, v) in args.into_iter().enumerate() {
+            fork.define(slot, v);
         }
 
-        self.block.eval(&fork)
+        match self.block.eval(&fork)? {
+            // return unwinds up to the nearest function
+            Value::Event(Event::Return(v)) => Ok(*v),
+            v => Ok(v.no_event()?)
+        }
     }
 }
 
 impl fmt::Display for FnBlock<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let args = self.args.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(" ");
-        write!(f, "(fun {} ({}) {})", self.ident, args, self.block)
+        match self.ident {
+            Some(ident) => write!(f, "(fun {} ({}) {})", ident, args, self.block),
+            None => write!(f, "(fun ({}) {})", args, self.block),
+        }
     }
-}
\ No newline at end of file
+}
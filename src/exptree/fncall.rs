@@ -1,43 +1,134 @@
 use std::fmt;
-use miette::Error;
+use miette::{Error, SourceSpan};
 
-use crate::{error::DefaultRuntimeError, evaluator::{Eval, Value}};
+use crate::{error::{ResolveError, RuntimeError}, evaluator::{Eval, Value}, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
 
-use super::ExprTree;
+use super::{Atom, ExprTree};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FnCall<'de> {
-    ident: &'de str,
-    args: Vec<ExprTree<'de>>
+    callee: Box<ExprTree<'de>>,
+    args: Vec<ExprTree<'de>>,
+    span: SourceSpan
 }
 
 impl<'de: 'a, 'a> Eval<'a> for FnCall<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error> {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let callee = match *self.callee {
+            // calling by name reports missing function instead of missing variable
+            ExprTree::Atom(Atom::Ident(name, binding), span) => ctx.lookup(name, binding)
+                .ok_or(RuntimeError::UndefinedFunction { name: name.to_string(), span: Some(span) })?,
+            ref callee => operand!(callee, ctx)
+        };
         let mut v_args = Vec::new();
         for a in &self.args {
-            let v = a.eval(ctx)?;
-            if matches!(v, Value::Event(_)) {
-                return Err(DefaultRuntimeError {}.into()) // TODO change error
+            v_args.push(operand!(a, ctx));
+        }
+        call(callee, v_args).map_err(|e| RuntimeError::locate(e, self.span))
+    }
+}
+
+/// Calls the value, calling the class creates the instance and runs its initializer
+fn call<'a>(callee: Value<'a>, args: Vec<Value<'a>>) -> Result<Value<'a>, Error> {
+    match callee {
+        Value::Function(fun) => fun.call(args),
+        Value::NativeFunction(fun) => fun.call(args),
+        // functions of other backends run in their own interpreter
+        Value::CompiledFunction(fun) => fun.call(args),
+        Value::LoweredFunction(fun) => fun.call(args),
+        Value::Class(class) => {
+            let (instance, init) = class.instantiate(args.len())?;
+            if let Some(init) = init {
+                call(init, args)?;
             }
-            v_args.push(v);
+            Ok(instance)
+        },
+        v => Err(RuntimeError::NotCallable { target: v.type_name(), span: None }.into())
+    }
+}
+
+impl<'de> Resolve<'de> for FnCall<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        // calling by name reports missing function instead of missing variable
+        self.callee.resolve(resolver).map_err(|e| match e.downcast::<ResolveError>() {
+            Ok(ResolveError::UndeclaredVariable { name, span }) => ResolveError::UndeclaredFunction { name, span }.into(),
+            Ok(e) => e.into(),
+            Err(e) => e,
+        })?;
+        self.args.iter_mut().try_for_each(|a| a.resolve(resolver))
+    }
+}
+
+impl<'de> Compile<'de> for FnCall<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        match *self.callee {
+            ExprTree::Atom(Atom::Ident(name, binding), span) => compiler.get_fn(name, binding, span),
+            ref callee => compiler.operand(callee),
+        }
+        for a in &self.args {
+            compiler.operand(a);
+        }
+        let args = compiler.narrow(self.args.len(), "arguments", self.span);
+        compiler.emit(vm::Op::Call(args), self.span);
+    }
+}
+
+impl<'de> Lower<'de> for FnCall<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let callee = match *self.callee {
+            ExprTree::Atom(Atom::Ident(name, binding), span) => {
+                let dst = builder.temp();
+                builder.emit(Inst::LoadFn { dst, var: Builder::var(name, binding) }, span);
+                dst
+            },
+            ref callee => builder.operand(callee),
+        };
+        let args = self.args.iter().map(|a| builder.operand(a)).collect();
+        let dst = builder.temp();
+        builder.emit(Inst::Call { dst, callee, args }, self.span);
+        Some(dst)
+    }
+}
+
+impl<'de> Outline<'de> for FnCall<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        self.callee.outline(symbols);
+        for a in &self.args {
+            a.outline(symbols);
         }
-        ctx.exec_fn(self.ident, v_args).unwrap_or(Err(DefaultRuntimeError {}.into())) // TODO change error
+    }
+}
+
+impl<'de> Format<'de> for FnCall<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        self.callee.format(f);
+        f.write("(");
+        f.list(&self.args);
+        f.write(")");
     }
 }
 
 impl<'de> FnCall<'de> {
-    pub fn new(ident: &'de str, args: Vec<ExprTree<'de>>) -> Self {
-        Self { ident, args }
+    pub fn new(callee: Box<ExprTree<'de>>, args: Vec<ExprTree<'de>>, span: SourceSpan) -> Self {
+        Self { callee, args, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
     }
 }
 
 impl fmt::Display for FnCall<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let callee = match *self.callee {
+            ExprTree::Atom(Atom::Ident(name, _), _) => name.to_string(),
+            ref callee => callee.to_string()
+        };
         let args = self.args.iter().map(|e| e.to_string()).collect::<Vec<String>>();
         if args.is_empty() {
-            write!(f, "(call {})", self.ident)
+            write!(f, "(call {})", callee)
         } else {
-            write!(f, "(call {} ({}))", self.ident, args.join(" "))
+            write!(f, "(call {} ({}))", callee, args.join(" "))
         }
     }
-}
\ No newline at end of file
+}
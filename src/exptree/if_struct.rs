@@ -1,7 +1,7 @@
 use std::fmt;
-use miette::Error;
+use miette::{Error, SourceSpan};
 
-use crate::{evaluator::Value, evaluator::Eval};
+use crate::{evaluator::Value, evaluator::Eval, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
 
 use super::ExprTree;
 
@@ -10,12 +10,13 @@ use super::ExprTree;
 pub struct If<'de> {
     cond: Box<ExprTree<'de>>,
     if_block: Box<ExprTree<'de>>,
-    else_block: Option<Box<ExprTree<'de>>>
+    else_block: Option<Box<ExprTree<'de>>>,
+    span: SourceSpan
 }
 
 impl<'de: 'a, 'a> Eval<'a> for If<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error> {
-        if self.cond.eval(ctx)?.into() {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
+        if operand!(self.cond, ctx).into() {
             self.if_block.eval(ctx)
         } else {
             if let Some(ref else_block) = self.else_block {
@@ -28,9 +29,90 @@ impl<'de: 'a, 'a> Eval<'a> for If<'de> {
 }
 
 
+impl<'de> Resolve<'de> for If<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.cond.resolve(resolver)?;
+        self.if_block.resolve(resolver)?;
+        if let Some(ref mut else_block) = self.else_block {
+            else_block.resolve(resolver)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Compile<'de> for If<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        compiler.operand(&self.cond);
+        let else_jump = compiler.emit(vm::Op::JumpIfFalse(0), self.span);
+        let depth = compiler.depth();
+        compiler.emit(vm::Op::Pop, self.span);
+        self.if_block.compile(compiler);
+        let end_jump = compiler.emit(vm::Op::Jump(0), self.span);
+
+        compiler.patch_jump(else_jump);
+        compiler.set_depth(depth);
+        compiler.emit(vm::Op::Pop, self.span);
+        match self.else_block {
+            Some(ref else_block) => else_block.compile(compiler),
+            None => { compiler.emit(vm::Op::Nil, self.span); }
+        }
+        compiler.patch_jump(end_jump);
+    }
+}
+
+impl<'de> Lower<'de> for If<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let res = builder.temp();
+        let cond = builder.operand(&self.cond);
+        let (then, otherwise, end) = (builder.label(), builder.label(), builder.label());
+        builder.emit(Inst::Branch { cond, then, otherwise }, self.span);
+
+        builder.emit(Inst::Label(then), self.span);
+        let v = builder.operand(&self.if_block);
+        builder.emit(Inst::Copy { dst: res, src: v }, self.span);
+        builder.emit(Inst::Jump(end), self.span);
+
+        builder.emit(Inst::Label(otherwise), self.span);
+        let v = match self.else_block {
+            Some(ref else_block) => builder.operand(else_block),
+            None => builder.constant(Value::Nil, self.span),
+        };
+        builder.emit(Inst::Copy { dst: res, src: v }, self.span);
+        builder.emit(Inst::Label(end), self.span);
+        Some(res)
+    }
+}
+
+impl<'de> Outline<'de> for If<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        self.cond.outline(symbols);
+        self.if_block.outline(symbols);
+        if let Some(ref else_block) = self.else_block {
+            else_block.outline(symbols);
+        }
+    }
+}
+
+impl<'de> Format<'de> for If<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        f.write("if (");
+        self.cond.format(f);
+        f.write(") ");
+        self.if_block.format(f);
+        if let Some(ref else_block) = self.else_block {
+            f.write(" else ");
+            else_block.format(f);
+        }
+    }
+}
+
 impl<'de> If<'de> {
-    pub fn new(cond: Box<ExprTree<'de>>, if_block: Box<ExprTree<'de>>, else_block: Option<Box<ExprTree<'de>>>) -> Self {
-        Self { cond, if_block, else_block }
+    pub fn new(cond: Box<ExprTree<'de>>, if_block: Box<ExprTree<'de>>, else_block: Option<Box<ExprTree<'de>>>, span: SourceSpan) -> Self {
+        Self { cond, if_block, else_block, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
     }
 }
 
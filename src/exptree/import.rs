@@ -0,0 +1,88 @@
+use std::{fmt, rc::Rc};
+use miette::{Error, SourceSpan};
+
+use crate::{context::CtxTree, error::RuntimeError, evaluator::{Eval, Event, Value}, resolver::{Binding, Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol, SymbolKind}, formatter::{Format, Formatter}};
+
+/// ## Import of the module
+/// Declares the namespace of the module linked to the program's context
+/// under its path, the namespace is named after the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import<'de> {
+    /// path as written between the quotes
+    path: &'de str,
+    name: &'de str,
+    binding: Binding,
+    span: SourceSpan
+}
+
+impl<'de: 'a, 'a> Eval<'a> for Import<'de> {
+    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let module = ctx.module(self.path)
+            .ok_or(RuntimeError::ModuleNotLoaded { path: self.path.to_string(), span: Some(self.span) })?;
+        ctx.declare(self.name, self.binding, module);
+        Ok(Value::Event(Event::NoVal))
+    }
+}
+
+impl<'de> Resolve<'de> for Import<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.binding = resolver.declare(self.name);
+        resolver.define(self.name);
+        Ok(())
+    }
+}
+
+impl<'de> Compile<'de> for Import<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        let path = compiler.name(self.path, self.span);
+        compiler.emit(vm::Op::Import(path), self.span);
+        compiler.define_var(self.name, self.binding, self.span);
+    }
+}
+
+impl<'de> Lower<'de> for Import<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let dst = builder.temp();
+        builder.emit(Inst::Import { dst, path: self.path }, self.span);
+        builder.emit(Inst::Define { var: Builder::var(self.name, self.binding), src: dst }, self.span);
+        None
+    }
+}
+
+impl<'de> Outline<'de> for Import<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        symbols.push(Symbol { name: self.name, kind: SymbolKind::Module, span: self.span, params: Vec::new(), doc: Rc::new([]), children: Vec::new() });
+    }
+}
+
+impl<'de> Format<'de> for Import<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        f.write(&format!("import \"{}\"", self.path));
+    }
+}
+
+impl<'de> Import<'de> {
+    /// `name` is the stem of the `path`
+    pub fn new(path: &'de str, name: &'de str, span: SourceSpan) -> Self {
+        Self { path, name, binding: Binding::Global, span }
+    }
+
+    pub fn path(&self) -> &'de str {
+        self.path
+    }
+
+    /// ## Name of the namespace
+    pub fn name(&self) -> &'de str {
+        self.name
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+impl fmt::Display for Import<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(import \"{}\")", self.path)
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+use miette::{Error, SourceSpan};
+
+use crate::{context::CtxTree, evaluator::{Eval, Value}, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
+
+use super::{Atom, ExprTree};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index<'de> {
+    target: Box<ExprTree<'de>>,
+    index: Box<ExprTree<'de>>,
+    /// written as the field access `target.name`
+    field: bool,
+    span: SourceSpan
+}
+
+impl<'de: 'a, 'a> Eval<'a> for Index<'de> {
+    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let target = operand!(self.target, ctx);
+        let index = operand!(self.index, ctx);
+        target.get_index(&index).map_err(|e| e.at(self.span).into())
+    }
+}
+
+impl<'de> Resolve<'de> for Index<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.target.resolve(resolver)?;
+        self.index.resolve(resolver)
+    }
+}
+
+impl<'de> Compile<'de> for Index<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        compiler.operand(&self.target);
+        compiler.operand(&self.index);
+        compiler.emit(vm::Op::Index, self.span);
+    }
+}
+
+impl<'de> Lower<'de> for Index<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let target = builder.operand(&self.target);
+        let index = builder.operand(&self.index);
+        let dst = builder.temp();
+        builder.emit(Inst::Index { dst, target, index }, self.span);
+        Some(dst)
+    }
+}
+
+impl<'de> Outline<'de> for Index<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        self.target.outline(symbols);
+        self.index.outline(symbols);
+    }
+}
+
+impl<'de> Format<'de> for Index<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        self.target.format(f);
+        if self.field {
+            f.write(".");
+            f.write(f.source(self.index.span()));
+            return
+        }
+        f.write("[");
+        self.index.format(f);
+        f.write("]");
+    }
+}
+
+impl<'de> Index<'de> {
+    pub fn new(target: Box<ExprTree<'de>>, index: Box<ExprTree<'de>>, span: SourceSpan) -> Self {
+        Self { target, index, field: false, span }
+    }
+
+    /// ## Creates field access
+    /// `target.name` is the same as `target["name"]`
+    pub fn field(target: Box<ExprTree<'de>>, name: &'de str, name_span: SourceSpan, span: SourceSpan) -> Self {
+        let index = Box::new(ExprTree::Atom(Atom::String(name.into()), name_span));
+        Self { target, index, field: true, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    /// ## Compiles assignment to the indexed element
+    /// the assigned value is already on the stack
+    pub fn compile_assign(&self, compiler: &mut Compiler<'de>) {
+        compiler.operand(&self.target);
+        compiler.operand(&self.index);
+        compiler.emit(vm::Op::SetIndex, self.span);
+    }
+
+    /// ## Lowers assignment to the indexed element
+    /// `src` holds the already lowered value
+    pub fn lower_assign(&self, builder: &mut Builder<'de>, src: Temp) {
+        let target = builder.operand(&self.target);
+        let index = builder.operand(&self.index);
+        builder.emit(Inst::SetIndex { target, index, src }, self.span);
+    }
+
+    /// ## Assigns to the indexed element
+    /// Evaluates target and index and writes the value into the target,
+    /// evaluates to nil or the control flow event that interrupted it
+    pub fn assign<'a>(&self, ctx: &CtxTree<'a>, value: Value<'a>) -> Result<Value<'a>, Error> where 'de: 'a {
+        let target = operand!(self.target, ctx);
+        let index = operand!(self.index, ctx);
+        target.set_index(&index, value).map_err(|e| e.at(self.span))?;
+        Ok(Value::Nil)
+    }
+}
+
+impl fmt::Display for Index<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(index {} {})", self.target, self.index)
+    }
+}
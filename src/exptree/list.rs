@@ -0,0 +1,84 @@
+use std::fmt;
+use miette::{Error, SourceSpan};
+
+use crate::{evaluator::{Eval, Value}, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
+
+use super::ExprTree;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct List<'de> {
+    items: Vec<ExprTree<'de>>,
+    span: SourceSpan
+}
+
+impl<'de: 'a, 'a> Eval<'a> for List<'de> {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let mut items = Vec::with_capacity(self.items.len());
+        for item in &self.items {
+            items.push(operand!(item, ctx));
+        }
+        Ok(Value::list(items))
+    }
+}
+
+impl<'de> Resolve<'de> for List<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.items.iter_mut().try_for_each(|item| item.resolve(resolver))
+    }
+}
+
+impl<'de> Compile<'de> for List<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        for item in &self.items {
+            compiler.operand(item);
+        }
+        let items = compiler.narrow(self.items.len(), "list items", self.span);
+        compiler.emit(vm::Op::List(items), self.span);
+    }
+}
+
+impl<'de> Lower<'de> for List<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let items = self.items.iter().map(|item| builder.operand(item)).collect();
+        let dst = builder.temp();
+        builder.emit(Inst::List { dst, items }, self.span);
+        Some(dst)
+    }
+}
+
+impl<'de> Outline<'de> for List<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        for item in &self.items {
+            item.outline(symbols);
+        }
+    }
+}
+
+impl<'de> Format<'de> for List<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        f.write("[");
+        f.list(&self.items);
+        f.write("]");
+    }
+}
+
+impl<'de> List<'de> {
+    pub fn new(items: Vec<ExprTree<'de>>, span: SourceSpan) -> Self {
+        Self { items, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+impl fmt::Display for List<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = self.items.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+        if items.is_empty() {
+            write!(f, "(list)")
+        } else {
+            write!(f, "(list {})", items.join(" "))
+        }
+    }
+}
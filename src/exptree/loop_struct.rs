@@ -1,33 +1,49 @@
 use std::fmt;
-use miette::Error;
+use miette::{Error, SourceSpan};
 
-use crate::{error::DefaultRuntimeError, evaluator::{Eval, Value}, exptree::Atom};
+use crate::{error::RuntimeError, evaluator::{Eval, Value}, exptree::Atom, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
 
 use super::ExprTree;
 
+/// Keyword the loop was written with, both loops are parsed into the same node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoopKind {
+    While,
+    For,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Loop<'de> {
-    condition: Box<ExprTree<'de>>,
+    kind: LoopKind,
+    /// missing condition of the `for` loop is always true
+    condition: Option<Box<ExprTree<'de>>>,
     init: Option<Box<ExprTree<'de>>>,
     step: Option<Box<ExprTree<'de>>>,
-    block: Box<ExprTree<'de>>
+    block: Box<ExprTree<'de>>,
+    span: SourceSpan
 }
 
 impl<'de: 'a, 'a> Eval<'a> for Loop<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error> {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
         let outer_scope = ctx.fork();
         if let Some(ref init) = self.init {
             init.eval(&outer_scope)?;
         }
 
-        while self.condition.eval(&outer_scope)?.into() {
+        loop {
+            if let Some(ref condition) = self.condition {
+                if !bool::from(operand!(condition, &outer_scope)) {
+                    break
+                }
+            }
             let b_val = self.block.eval(&outer_scope)?;
             if let Value::Event(e) = b_val {
                 match e {
                     crate::evaluator::Event::Continue => (),
                     crate::evaluator::Event::Break(ret) => return Ok(*ret),
+                    // return unwinds further up to the function
                     crate::evaluator::Event::Return(_) => return Ok(Value::Event(e)),
-                    crate::evaluator::Event::NoVal => return Err(DefaultRuntimeError {}.into()), // TODO change error
+                    crate::evaluator::Event::NoVal => return Err(RuntimeError::NoValue { span: Some(self.block.span()) }.into()),
                 }
             }
             if let Some(ref step) = self.step {
@@ -40,17 +56,160 @@ impl<'de: 'a, 'a> Eval<'a> for Loop<'de> {
 }
 
 
+impl<'de> Resolve<'de> for Loop<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        // mirrors the scope forked for the loop's init
+        resolver.begin_scope();
+        let res = self.resolve_parts(resolver);
+        resolver.end_scope();
+        res
+    }
+}
+
+impl<'de> Compile<'de> for Loop<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        let declares = [self.init.as_deref(), self.condition.as_deref(), self.step.as_deref()]
+            .into_iter().flatten().any(|e| e.declared_name().is_some());
+        compiler.begin_scope(declares, self.span);
+        if let Some(ref init) = self.init {
+            init.compile(compiler);
+            compiler.emit(vm::Op::Pop, init.span());
+        }
+        let start = compiler.here();
+        match self.condition {
+            Some(ref condition) => compiler.operand(condition),
+            None => { compiler.emit(vm::Op::True, self.span); },
+        }
+        let exit_jump = compiler.emit(vm::Op::JumpIfFalse(0), self.condition_span());
+        let depth = compiler.depth();
+        compiler.emit(vm::Op::Pop, self.span);
+
+        compiler.begin_loop();
+        self.block.compile(compiler);
+        compiler.emit(vm::Op::Pop, self.block.span());
+        compiler.patch_continues();
+        if let Some(ref step) = self.step {
+            step.compile(compiler);
+            compiler.emit(vm::Op::Pop, step.span());
+        }
+        compiler.emit(vm::Op::Jump(start), self.span);
+
+        // condition is false, loop evaluates to nil
+        compiler.patch_jump(exit_jump);
+        compiler.set_depth(depth);
+        compiler.emit(vm::Op::Pop, self.span);
+        compiler.emit(vm::Op::Nil, self.span);
+        compiler.end_loop();
+        compiler.end_scope(self.span);
+    }
+}
+
+impl<'de> Lower<'de> for Loop<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        builder.begin_scope(self.span);
+        if let Some(ref init) = self.init {
+            init.lower(builder);
+        }
+        let res = builder.temp();
+        let (start, body, next, exit, end) = (builder.label(), builder.label(), builder.label(), builder.label(), builder.label());
+        builder.emit(Inst::Label(start), self.span);
+        let cond = match self.condition {
+            Some(ref condition) => builder.operand(condition),
+            None => builder.constant(Value::Bool(true), self.span),
+        };
+        builder.emit(Inst::Branch { cond, then: body, otherwise: exit }, self.condition_span());
+
+        builder.emit(Inst::Label(body), self.span);
+        builder.begin_loop(res, end, next);
+        self.block.lower(builder);
+        builder.end_loop();
+        builder.emit(Inst::Label(next), self.span);
+        if let Some(ref step) = self.step {
+            step.lower(builder);
+        }
+        builder.emit(Inst::Jump(start), self.span);
+
+        // condition is false, loop evaluates to nil
+        builder.emit(Inst::Label(exit), self.span);
+        builder.emit(Inst::Const { dst: res, value: Value::Nil }, self.span);
+        builder.emit(Inst::Label(end), self.span);
+        builder.end_scope(self.span);
+        Some(res)
+    }
+}
+
+impl<'de> Outline<'de> for Loop<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        for part in [self.init.as_deref(), self.condition.as_deref(), self.step.as_deref(), Some(&*self.block)].into_iter().flatten() {
+            part.outline(symbols);
+        }
+    }
+}
+
+impl<'de> Format<'de> for Loop<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        match self.kind {
+            LoopKind::While => {
+                f.write("while (");
+                if let Some(ref condition) = self.condition {
+                    condition.format(f);
+                }
+            },
+            LoopKind::For => {
+                f.write("for (");
+                if let Some(ref init) = self.init {
+                    init.format(f);
+                }
+                f.write(";");
+                if let Some(ref condition) = self.condition {
+                    f.write(" ");
+                    condition.format(f);
+                }
+                f.write(";");
+                if let Some(ref step) = self.step {
+                    f.write(" ");
+                    step.format(f);
+                }
+            },
+        }
+        f.write(") ");
+        self.block.format(f);
+    }
+}
+
 impl<'de> Loop<'de> {
-    pub fn new(condition: Box<ExprTree<'de>>, init: Option<Box<ExprTree<'de>>>, step: Option<Box<ExprTree<'de>>>, block: Box<ExprTree<'de>>) -> Self {
-        Self { condition, init, step, block }
+    pub fn new(kind: LoopKind, condition: Option<Box<ExprTree<'de>>>, init: Option<Box<ExprTree<'de>>>, step: Option<Box<ExprTree<'de>>>, block: Box<ExprTree<'de>>, span: SourceSpan) -> Self {
+        Self { kind, condition, init, step, block, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    /// Missing condition is reported at the loop itself
+    fn condition_span(&self) -> SourceSpan {
+        self.condition.as_ref().map_or(self.span, |c| c.span())
+    }
+
+    fn resolve_parts(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        if let Some(ref mut init) = self.init {
+            init.resolve(resolver)?;
+        }
+        if let Some(ref mut condition) = self.condition {
+            condition.resolve(resolver)?;
+        }
+        if let Some(ref mut step) = self.step {
+            step.resolve(resolver)?;
+        }
+        self.block.resolve(resolver)
     }
 }
 
 impl fmt::Display for Loop<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let step = self.step.clone().unwrap_or(Box::new(ExprTree::Atom(Atom::Nil))).to_string();
-        let var = self.init.clone().unwrap_or(Box::new(ExprTree::Atom(Atom::Nil))).to_string();
-        let condition = self.condition.to_string();
+        let step = self.step.as_ref().map_or(Atom::Nil.to_string(), |s| s.to_string());
+        let var = self.init.as_ref().map_or(Atom::Nil.to_string(), |s| s.to_string());
+        let condition = self.condition.as_ref().map_or(Atom::Bool(true).to_string(), |c| c.to_string());
         let block = self.block.to_string();
         write!(f, "(loop {var} {condition} {step} {block})")
     }
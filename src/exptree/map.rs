@@ -0,0 +1,105 @@
+use std::{collections::BTreeMap, fmt};
+use miette::{Error, SourceSpan};
+
+use crate::{evaluator::{Eval, Value}, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
+
+use super::ExprTree;
+
+/// ## Map literal
+/// Keys are string literals, later entry with the same key wins
+#[derive(Debug, Clone, PartialEq)]
+pub struct Map<'de> {
+    entries: Vec<(ExprTree<'de>, ExprTree<'de>)>,
+    span: SourceSpan
+}
+
+impl<'de: 'a, 'a> Eval<'a> for Map<'de> {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let map = Value::map(BTreeMap::new());
+        for (key, value) in &self.entries {
+            let key = operand!(key, ctx);
+            let value = operand!(value, ctx);
+            map.set_index(&key, value).map_err(|e| e.at(self.span))?;
+        }
+        Ok(map)
+    }
+}
+
+impl<'de> Resolve<'de> for Map<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.entries.iter_mut().try_for_each(|(key, value)| {
+            key.resolve(resolver)?;
+            value.resolve(resolver)
+        })
+    }
+}
+
+impl<'de> Compile<'de> for Map<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        for (key, value) in &self.entries {
+            compiler.operand(key);
+            compiler.operand(value);
+        }
+        let entries = compiler.narrow(self.entries.len(), "map entries", self.span);
+        compiler.emit(vm::Op::Map(entries), self.span);
+    }
+}
+
+impl<'de> Lower<'de> for Map<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let entries = self.entries.iter()
+            .map(|(key, value)| (builder.operand(key), builder.operand(value)))
+            .collect();
+        let dst = builder.temp();
+        builder.emit(Inst::Map { dst, entries }, self.span);
+        Some(dst)
+    }
+}
+
+impl<'de> Outline<'de> for Map<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        for (_, value) in &self.entries {
+            value.outline(symbols);
+        }
+    }
+}
+
+impl<'de> Format<'de> for Map<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        if self.entries.is_empty() {
+            f.write("{:}");
+            return
+        }
+        f.write("{");
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                f.write(", ");
+            }
+            key.format(f);
+            f.write(": ");
+            value.format(f);
+        }
+        f.write("}");
+    }
+}
+
+impl<'de> Map<'de> {
+    pub fn new(entries: Vec<(ExprTree<'de>, ExprTree<'de>)>, span: SourceSpan) -> Self {
+        Self { entries, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+impl fmt::Display for Map<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries.iter().map(|(k, v)| format!("({k} {v})")).collect::<Vec<String>>();
+        if entries.is_empty() {
+            write!(f, "(map)")
+        } else {
+            write!(f, "(map {})", entries.join(" "))
+        }
+    }
+}
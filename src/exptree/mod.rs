@@ -1,21 +1,48 @@
 use std::{borrow::Cow, fmt};
 
-use crate::{evaluator::{Event, Value}, error::DefaultRuntimeError, evaluator::Eval, token::format_num};
+use crate::{evaluator::{Event, Value}, error::RuntimeError, evaluator::Eval, resolver::{Binding, Resolve, Resolver}, token::format_num, vm::{self, Compile, Compiler}, ir::{self, Builder, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
 
+/// ## Evaluates an operand
+/// Control flow events (`return`, `break`, `continue`) are handed back 
+/// to the caller right away so they unwind up to the loop or function 
+/// that handles them, statements that produce no value are an error
+macro_rules! operand {
+    ($expr:expr, $ctx:expr) => {
+        match $expr.eval($ctx)? {
+            $crate::evaluator::Value::Event($crate::evaluator::Event::NoVal) => 
+                return Err($crate::error::RuntimeError::NoValue { span: Some($expr.span()) }.into()),
+            $crate::evaluator::Value::Event(e) => return Ok($crate::evaluator::Value::Event(e)),
+            v => v,
+        }
+    };
+}
+
+pub mod class;
 pub mod conop;
 pub mod fnblock;
 pub mod fncall;
 pub mod if_struct;
+pub mod import;
+pub mod index;
+pub mod list;
+pub mod map;
 pub mod loop_struct;
+pub mod super_access;
 pub mod uop;
 pub mod vardecl;
 
+pub use class::*;
 pub use conop::*;
 pub use fnblock::*;
 pub use fncall::*;
 pub use if_struct::*;
+pub use import::*;
+pub use index::*;
+pub use list::*;
+pub use map::*;
 pub use loop_struct::*;
-use miette::Error;
+use miette::{Error, SourceSpan};
+pub use super_access::*;
 pub use uop::*;
 pub use vardecl::*;
 
@@ -25,20 +52,26 @@ pub use vardecl::*;
 pub enum Atom<'de> {
     String(Cow<'de, str>),
     Number(f64),
+    Integer(i64),
     Nil,
     Bool(bool),
-    Ident(&'de str),
+    /// variable with its resolved binding
+    Ident(&'de str, Binding),
+    /// instance the method is bound to
+    This(Binding),
     Continue
 }
 
 impl<'de: 'a, 'a> Eval<'a> for Atom<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error>  {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error>  {
         match &self {
             Atom::String(s) => Ok(Value::String(s.to_string())),
-            Atom::Number(n) => Ok(Value::Number(n.clone())),
+            Atom::Number(n) => Ok(Value::Number(*n)),
+            Atom::Integer(i) => Ok(Value::Integer(*i)),
             Atom::Nil => Ok(Value::Nil),
-            Atom::Bool(b) => Ok(Value::Bool(b.clone())),
-            Atom::Ident(id) => Ok(ctx.search(id).ok_or(DefaultRuntimeError{})?), // TODO switch error
+            Atom::Bool(b) => Ok(Value::Bool(*b)),
+            Atom::Ident(id, binding) => Ok(ctx.lookup(id, *binding).ok_or(RuntimeError::UndefinedVariable { name: id.to_string(), span: None })?),
+            Atom::This(binding) => Ok(ctx.lookup("this", *binding).ok_or(RuntimeError::UndefinedVariable { name: "this".to_string(), span: None })?),
             Atom::Continue => Ok(Value::Event(Event::Continue)),
         }
     }
@@ -48,21 +81,27 @@ impl<'de: 'a, 'a> Eval<'a> for Atom<'de> {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExprTree<'de> {
-    Atom(Atom<'de>),
+    Atom(Atom<'de>, SourceSpan),
     ConOp(ConOp<'de>),
     UnaryOp(UnaryOp<'de>),
     FnCall(FnCall<'de>),
     FnBlock(FnBlock<'de>),
-    Block(Vec<ExprTree<'de>>, Option<Box<ExprTree<'de>>>),
+    Block(Vec<ExprTree<'de>>, Option<Box<ExprTree<'de>>>, SourceSpan),
     If(If<'de>),
     Loop(Loop<'de>),
-    Var(VarDecl<'de>)
+    Var(VarDecl<'de>),
+    List(List<'de>),
+    Map(Map<'de>),
+    Index(Index<'de>),
+    Class(ClassDecl<'de>),
+    Super(SuperAccess<'de>),
+    Import(Import<'de>)
 }
 
 impl<'de: 'a, 'a> Eval<'a> for ExprTree<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error> {
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
         match self {
-            ExprTree::Atom(a) => a.eval(ctx),
+            ExprTree::Atom(a, span) => a.eval(ctx).map_err(|e| RuntimeError::locate(e, *span)),
             ExprTree::ConOp(cop) => cop.eval(ctx),
             ExprTree::UnaryOp(uop) => uop.eval(ctx),
             ExprTree::FnCall(fnc) => fnc.eval(ctx),
@@ -70,43 +109,284 @@ impl<'de: 'a, 'a> Eval<'a> for ExprTree<'de> {
             ExprTree::If(iff) => iff.eval(ctx),
             ExprTree::Loop(lop) => lop.eval(ctx),
             ExprTree::Var(vd) => vd.eval(ctx),
-            ExprTree::Block(stmts, retexp) => {
+            ExprTree::List(l) => l.eval(ctx),
+            ExprTree::Map(m) => m.eval(ctx),
+            ExprTree::Index(i) => i.eval(ctx),
+            ExprTree::Class(c) => c.eval(ctx),
+            ExprTree::Super(s) => s.eval(ctx),
+            ExprTree::Import(i) => i.eval(ctx),
+            ExprTree::Block(stmts, retexp, _) => {
                 let fork = ctx.fork();
                 for s in stmts {
                     let v = s.eval(&fork)?;
-                    if !matches!(v, Value::Event(Event::NoVal)) {
-                        if let Value::Event(e) = v {
+                    if let Value::Event(e) = v {
+                        if e != Event::NoVal {
                             return Ok(Value::Event(e));
                         }
                     }
                 }
                 if let Some(ref retexp) = retexp {
-                    let v = retexp.eval(ctx)?;
-                    if let Value::Event(e) = v {
-                        if e == Event::NoVal { 
-                            return Err(DefaultRuntimeError {}.into()); // TODO change error
-                        } else {
-                            return Ok(Value::Event(e));
-                        }
+                    let v = retexp.eval(&fork)?;
+                    if let Value::Event(Event::NoVal) = v {
+                        return Err(RuntimeError::NoValue { span: Some(retexp.span()) }.into());
                     }
                     return Ok(v)
                 }
-                return Ok(Value::Nil);
+                Ok(Value::Nil)
+            }
+        }
+    }
+}
+
+
+impl<'de> Resolve<'de> for ExprTree<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        match self {
+            ExprTree::Atom(Atom::Ident(id, binding), span) => {
+                *binding = resolver.lookup(id, *span)?;
+                Ok(())
+            },
+            ExprTree::Atom(Atom::This(binding), span) => {
+                *binding = resolver.lookup("this", *span)?;
+                Ok(())
+            },
+            ExprTree::Atom(..) => Ok(()),
+            ExprTree::ConOp(cop) => cop.resolve(resolver),
+            ExprTree::UnaryOp(uop) => uop.resolve(resolver),
+            ExprTree::FnCall(fnc) => fnc.resolve(resolver),
+            ExprTree::FnBlock(fnb) => fnb.resolve(resolver),
+            ExprTree::If(iff) => iff.resolve(resolver),
+            ExprTree::Loop(lop) => lop.resolve(resolver),
+            ExprTree::Var(vd) => vd.resolve(resolver),
+            ExprTree::List(l) => l.resolve(resolver),
+            ExprTree::Map(m) => m.resolve(resolver),
+            ExprTree::Index(i) => i.resolve(resolver),
+            ExprTree::Class(c) => c.resolve(resolver),
+            ExprTree::Super(s) => s.resolve(resolver),
+            ExprTree::Import(i) => i.resolve(resolver),
+            ExprTree::Block(stmts, retexp, _) => {
+                resolver.begin_scope();
+                let res = stmts.iter_mut().chain(retexp.as_deref_mut())
+                    .try_for_each(|s| s.resolve(resolver));
+                resolver.end_scope();
+                res
+            }
+        }
+    }
+}
+
+impl<'de> Compile<'de> for ExprTree<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        match self {
+            ExprTree::Atom(a, span) => match a {
+                Atom::String(s) => compiler.constant(Value::String(s.to_string()), *span),
+                Atom::Number(n) => compiler.constant(Value::Number(*n), *span),
+                Atom::Integer(i) => compiler.constant(Value::Integer(*i), *span),
+                Atom::Nil => { compiler.emit(vm::Op::Nil, *span); },
+                Atom::Bool(true) => { compiler.emit(vm::Op::True, *span); },
+                Atom::Bool(false) => { compiler.emit(vm::Op::False, *span); },
+                Atom::Ident(id, binding) => compiler.get_var(id, *binding, *span),
+                Atom::This(binding) => compiler.get_var("this", *binding, *span),
+                Atom::Continue => compiler.emit_continue(*span),
+            },
+            ExprTree::ConOp(cop) => cop.compile(compiler),
+            ExprTree::UnaryOp(uop) => uop.compile(compiler),
+            ExprTree::FnCall(fnc) => fnc.compile(compiler),
+            ExprTree::FnBlock(fnb) => fnb.compile(compiler),
+            ExprTree::If(iff) => iff.compile(compiler),
+            ExprTree::Loop(lop) => lop.compile(compiler),
+            ExprTree::Var(vd) => vd.compile(compiler),
+            ExprTree::List(l) => l.compile(compiler),
+            ExprTree::Map(m) => m.compile(compiler),
+            ExprTree::Index(i) => i.compile(compiler),
+            ExprTree::Class(c) => c.compile(compiler),
+            ExprTree::Super(s) => s.compile(compiler),
+            ExprTree::Import(i) => i.compile(compiler),
+            ExprTree::Block(stmts, retexp, span) => {
+                let declares = stmts.iter().chain(retexp.as_deref()).any(|s| s.declared_name().is_some());
+                compiler.begin_scope(declares, *span);
+                for s in stmts {
+                    s.compile(compiler);
+                    compiler.emit(vm::Op::Pop, s.span());
+                }
+                match retexp {
+                    Some(retexp) => compiler.operand(retexp),
+                    None => { compiler.emit(vm::Op::Nil, *span); }
+                }
+                compiler.end_scope(*span);
+            }
+        }
+    }
+}
+
+impl<'de> Lower<'de> for ExprTree<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        match self {
+            ExprTree::Atom(a, span) => Some(match a {
+                Atom::String(s) => builder.constant(Value::String(s.to_string()), *span),
+                Atom::Number(n) => builder.constant(Value::Number(*n), *span),
+                Atom::Integer(i) => builder.constant(Value::Integer(*i), *span),
+                Atom::Nil => builder.constant(Value::Nil, *span),
+                Atom::Bool(b) => builder.constant(Value::Bool(*b), *span),
+                Atom::Ident(id, binding) => {
+                    let dst = builder.temp();
+                    builder.emit(ir::Inst::Load { dst, var: Builder::var(id, *binding) }, *span);
+                    dst
+                },
+                Atom::This(binding) => {
+                    let dst = builder.temp();
+                    builder.emit(ir::Inst::Load { dst, var: Builder::var("this", *binding) }, *span);
+                    dst
+                },
+                Atom::Continue => {
+                    builder.emit_continue(*span);
+                    builder.temp()
+                },
+            }),
+            ExprTree::ConOp(cop) => cop.lower(builder),
+            ExprTree::UnaryOp(uop) => uop.lower(builder),
+            ExprTree::FnCall(fnc) => fnc.lower(builder),
+            ExprTree::FnBlock(fnb) => fnb.lower(builder),
+            ExprTree::If(iff) => iff.lower(builder),
+            ExprTree::Loop(lop) => lop.lower(builder),
+            ExprTree::Var(vd) => vd.lower(builder),
+            ExprTree::List(l) => l.lower(builder),
+            ExprTree::Map(m) => m.lower(builder),
+            ExprTree::Index(i) => i.lower(builder),
+            ExprTree::Class(c) => c.lower(builder),
+            ExprTree::Super(s) => s.lower(builder),
+            ExprTree::Import(i) => i.lower(builder),
+            ExprTree::Block(stmts, retexp, span) => {
+                builder.begin_scope(*span);
+                for s in stmts {
+                    s.lower(builder);
+                }
+                let res = match retexp {
+                    Some(retexp) => builder.operand(retexp),
+                    None => builder.constant(Value::Nil, *span),
+                };
+                builder.end_scope(*span);
+                Some(res)
             }
         }
     }
 }
 
+impl<'de> Outline<'de> for ExprTree<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        match self {
+            ExprTree::Atom(..) => {},
+            ExprTree::ConOp(cop) => cop.outline(symbols),
+            ExprTree::UnaryOp(uop) => uop.outline(symbols),
+            ExprTree::FnCall(fnc) => fnc.outline(symbols),
+            ExprTree::FnBlock(fnb) => fnb.outline(symbols),
+            ExprTree::If(iff) => iff.outline(symbols),
+            ExprTree::Loop(lop) => lop.outline(symbols),
+            ExprTree::Var(vd) => vd.outline(symbols),
+            ExprTree::List(l) => l.outline(symbols),
+            ExprTree::Map(m) => m.outline(symbols),
+            ExprTree::Index(i) => i.outline(symbols),
+            ExprTree::Class(c) => c.outline(symbols),
+            ExprTree::Super(s) => s.outline(symbols),
+            ExprTree::Import(i) => i.outline(symbols),
+            ExprTree::Block(stmts, retexp, _) => {
+                for s in stmts.iter().chain(retexp.as_deref()) {
+                    s.outline(symbols);
+                }
+            }
+        }
+    }
+}
+
+impl<'de> Format<'de> for ExprTree<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        match self {
+            ExprTree::Atom(a, span) => {
+                f.comments_within(span.offset());
+                match a {
+                    // literals are kept as they were written
+                    Atom::String(_) | Atom::Number(_) | Atom::Integer(_) => f.write(f.source(*span)),
+                    Atom::Nil => f.write("nil"),
+                    Atom::Bool(b) => f.write(&b.to_string()),
+                    Atom::Ident(id, _) => f.write(id),
+                    Atom::This(_) => f.write("this"),
+                    Atom::Continue => f.write("continue"),
+                }
+            },
+            ExprTree::ConOp(cop) => cop.format(f),
+            ExprTree::UnaryOp(uop) => uop.format(f),
+            ExprTree::FnCall(fnc) => fnc.format(f),
+            ExprTree::FnBlock(fnb) => fnb.format(f),
+            ExprTree::If(iff) => iff.format(f),
+            ExprTree::Loop(lop) => lop.format(f),
+            ExprTree::Var(vd) => vd.format(f),
+            ExprTree::List(l) => l.format(f),
+            ExprTree::Map(m) => m.format(f),
+            ExprTree::Index(i) => i.format(f),
+            ExprTree::Class(c) => c.format(f),
+            ExprTree::Super(s) => s.format(f),
+            ExprTree::Import(i) => i.format(f),
+            ExprTree::Block(stmts, retexp, span) => f.block(stmts, retexp.as_deref(), *span),
+        }
+    }
+}
+
+impl<'de> ExprTree<'de> {
+    /// ## Checks if the node produces a value
+    /// Declarations and print are statements, using them as a value is an error
+    pub fn has_value(&self) -> bool {
+        match self {
+            ExprTree::Var(_) | ExprTree::Class(_) | ExprTree::Import(_) => false,
+            ExprTree::FnBlock(fnb) => fnb.ident().is_none(),
+            ExprTree::UnaryOp(uop) => uop.op() != UOp::Print,
+            _ => true
+        }
+    }
+
+    /// ## Name declared by the statement
+    /// Returns the name of the variable, named function, class or namespace declared by this statement
+    pub fn declared_name(&self) -> Option<&'de str> {
+        match self {
+            ExprTree::Var(vd) => Some(vd.name()),
+            ExprTree::Class(c) => Some(c.name()),
+            ExprTree::Import(i) => Some(i.name()),
+            ExprTree::FnBlock(fnb) => fnb.ident(),
+            _ => None
+        }
+    }
+
+    /// ## Span of the expression
+    /// Returns the part of the source code this expression was parsed from
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            ExprTree::Atom(_, span) => *span,
+            ExprTree::ConOp(cop) => cop.span(),
+            ExprTree::UnaryOp(uop) => uop.span(),
+            ExprTree::FnCall(fnc) => fnc.span(),
+            ExprTree::FnBlock(fnb) => fnb.span(),
+            ExprTree::Block(_, _, span) => *span,
+            ExprTree::If(iff) => iff.span(),
+            ExprTree::Loop(lop) => lop.span(),
+            ExprTree::Var(vd) => vd.span(),
+            ExprTree::List(l) => l.span(),
+            ExprTree::Map(m) => m.span(),
+            ExprTree::Index(i) => i.span(),
+            ExprTree::Class(c) => c.span(),
+            ExprTree::Super(s) => s.span(),
+            ExprTree::Import(i) => i.span(),
+        }
+    }
+}
 
 impl fmt::Display for ExprTree<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ExprTree::Atom(a) => write!(f, "{a}"),
+            ExprTree::Atom(a, _) => write!(f, "{a}"),
             ExprTree::ConOp(co) => write!(f, "{co}"),
             ExprTree::UnaryOp(uo) => write!(f, "{uo}"),
             ExprTree::FnCall(fnc) => write!(f, "{fnc}"),
             ExprTree::FnBlock(fnb) => write!(f, "{fnb}"),
-            ExprTree::Block(stmts, ret_exp) => {
+            ExprTree::Block(stmts, ret_exp, _) => {
                 let mut statements = stmts.iter().map(|e| e.to_string()).collect::<Vec<String>>();
                 if let Some(ref ret) = ret_exp {
                     statements.push(ret.to_string())
@@ -120,6 +400,12 @@ impl fmt::Display for ExprTree<'_> {
             ExprTree::If(i) => write!(f, "{i}"),
             ExprTree::Loop(l) => write!(f, "{l}"),
             ExprTree::Var(v) => write!(f, "{v}"),
+            ExprTree::List(l) => write!(f, "{l}"),
+            ExprTree::Map(m) => write!(f, "{m}"),
+            ExprTree::Index(i) => write!(f, "{i}"),
+            ExprTree::Class(c) => write!(f, "{c}"),
+            ExprTree::Super(s) => write!(f, "{s}"),
+            ExprTree::Import(i) => write!(f, "{i}"),
         }
     }
 }
@@ -128,9 +414,11 @@ impl fmt::Display for Atom<'_> {
         match self {
             Atom::String(s) => write!(f, "{}", s),
             Atom::Number(n) => write!(f, "{}", format_num(*n)),
+            Atom::Integer(i) => write!(f, "{i}"),
             Atom::Nil => write!(f, "nil"),
             Atom::Bool(b) => write!(f, "{b}"),
-            Atom::Ident(name) => write!(f, "id({name})"),
+            Atom::Ident(name, _) => write!(f, "id({name})"),
+            Atom::This(_) => write!(f, "this"),
             Atom::Continue => write!(f, "continue"),
         }
     }
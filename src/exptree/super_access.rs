@@ -0,0 +1,79 @@
+use std::fmt;
+use miette::{Error, SourceSpan};
+
+use crate::{context::CtxTree, error::RuntimeError, evaluator::{Eval, Value}, resolver::{Binding, Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
+
+/// ## Method of the superclass
+/// `super.name` reads the method from the superclass of the class
+/// the code is declared in and binds it to `this`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuperAccess<'de> {
+    method: &'de str,
+    /// bindings of the implicit `super` and `this` variables
+    binding: Binding,
+    this: Binding,
+    span: SourceSpan
+}
+
+impl<'de: 'a, 'a> Eval<'a> for SuperAccess<'de> {
+    fn eval(&self, ctx: &CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let lookup = |name: &str, binding| ctx.lookup(name, binding)
+            .ok_or(RuntimeError::UndefinedVariable { name: name.to_string(), span: Some(self.span) });
+        let superclass = lookup("super", self.binding)?;
+        let this = lookup("this", self.this)?;
+        superclass.get_super(self.method, this).map_err(|e| e.at(self.span).into())
+    }
+}
+
+impl<'de> Resolve<'de> for SuperAccess<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.binding = resolver.lookup("super", self.span)?;
+        self.this = resolver.lookup("this", self.span)?;
+        Ok(())
+    }
+}
+
+impl<'de> Compile<'de> for SuperAccess<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        compiler.get_var("super", self.binding, self.span);
+        compiler.get_var("this", self.this, self.span);
+        let name = compiler.name(self.method, self.span);
+        compiler.emit(vm::Op::GetSuper(name), self.span);
+    }
+}
+
+impl<'de> Lower<'de> for SuperAccess<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let (superclass, this, dst) = (builder.temp(), builder.temp(), builder.temp());
+        builder.emit(Inst::Load { dst: superclass, var: Builder::var("super", self.binding) }, self.span);
+        builder.emit(Inst::Load { dst: this, var: Builder::var("this", self.this) }, self.span);
+        builder.emit(Inst::Super { dst, superclass, this, name: self.method }, self.span);
+        Some(dst)
+    }
+}
+
+impl<'de> Outline<'de> for SuperAccess<'de> {
+    fn outline(&self, _symbols: &mut Vec<Symbol<'de>>) {}
+}
+
+impl<'de> Format<'de> for SuperAccess<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        f.write(&format!("super.{}", self.method));
+    }
+}
+
+impl<'de> SuperAccess<'de> {
+    pub fn new(method: &'de str, span: SourceSpan) -> Self {
+        Self { method, binding: Binding::Global, this: Binding::Global, span }
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+impl fmt::Display for SuperAccess<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(super {})", self.method)
+    }
+}
@@ -1,15 +1,17 @@
 use std::fmt;
-use miette::Error;
+use miette::{Error, SourceSpan};
 
-use crate::{error::DefaultRuntimeError, evaluator::{Eval, Event, Value}};
+use crate::{error::ResolveError, evaluator::{Eval, Event, Value}, resolver::{Resolve, Resolver}, vm::{self, Compile, Compiler}, ir::{Builder, Inst, Lower, Temp, UnOp}, lsp::{Outline, Symbol}, formatter::{Format, Formatter}};
 
-use super::ExprTree;
+use super::{Atom, ExprTree};
 
 // Unary operations
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UOp {
     Minus,
     Bang,
+    /// bitwise not
+    Tilde,
     Print,
     Return,
     Break,
@@ -20,17 +22,16 @@ pub enum UOp {
 pub struct UnaryOp<'de> {
     op: UOp,
     lhs: Box<ExprTree<'de>>,
+    span: SourceSpan
 }
 
 impl<'de: 'a, 'a> Eval<'a> for UnaryOp<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error> {
-        let v = self.lhs.eval(ctx)?;
-        if matches!(v, Value::Event(_)) {
-            return Err(DefaultRuntimeError {}.into()) // TODO change error
-        }
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let v = operand!(self.lhs, ctx);
         match self.op {
-            UOp::Minus => -v,
-            UOp::Bang => !v,
+            UOp::Minus => (-v).map_err(|e| e.at(self.span).into()),
+            UOp::Bang => (!v).map_err(|e| e.at(self.span).into()),
+            UOp::Tilde => v.bit_not().map_err(|e| e.at(self.span).into()),
             UOp::Print => {
                 print!("{}", v);
                 Ok(Value::Event(Event::NoVal))
@@ -43,9 +44,102 @@ impl<'de: 'a, 'a> Eval<'a> for UnaryOp<'de> {
 }
 
 
+impl<'de> Resolve<'de> for UnaryOp<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        if self.op == UOp::Return && !self.is_bare() && resolver.in_initializer() {
+            return Err(ResolveError::ReturnFromInitializer { span: self.span }.into())
+        }
+        self.lhs.resolve(resolver)
+    }
+}
+
+impl<'de> Compile<'de> for UnaryOp<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        compiler.operand(&self.lhs);
+        match self.op {
+            UOp::Minus => { compiler.emit(vm::Op::Neg, self.span); },
+            UOp::Bang => { compiler.emit(vm::Op::Not, self.span); },
+            UOp::Tilde => { compiler.emit(vm::Op::BitNot, self.span); },
+            UOp::Print => { compiler.emit(vm::Op::Print, self.span); },
+            UOp::Return => compiler.emit_return(self.span),
+            UOp::Break => compiler.emit_break(self.span),
+            UOp::Group => {},
+        }
+    }
+}
+
+impl<'de> Lower<'de> for UnaryOp<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let src = builder.operand(&self.lhs);
+        let op = match self.op {
+            UOp::Group => return Some(src),
+            UOp::Print => {
+                builder.emit(Inst::Print { src }, self.span);
+                return None
+            },
+            UOp::Return => {
+                builder.emit(Inst::Return(Some(src)), self.span);
+                return Some(builder.temp())
+            },
+            UOp::Break => {
+                builder.emit_break(src, self.span);
+                return Some(builder.temp())
+            },
+            UOp::Minus => UnOp::Neg,
+            UOp::Bang => UnOp::Not,
+            UOp::Tilde => UnOp::BitNot,
+        };
+        let dst = builder.temp();
+        builder.emit(Inst::Unary { dst, op, src }, self.span);
+        Some(dst)
+    }
+}
+
+impl<'de> Outline<'de> for UnaryOp<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        self.lhs.outline(symbols);
+    }
+}
+
+impl<'de> Format<'de> for UnaryOp<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        let bare = self.is_bare();
+        match self.op {
+            UOp::Minus => f.write("-"),
+            UOp::Bang => f.write("!"),
+            UOp::Tilde => f.write("~"),
+            UOp::Print => f.write("print "),
+            UOp::Return if bare => return f.write("return"),
+            UOp::Return => f.write("return "),
+            UOp::Break if bare => return f.write("break"),
+            UOp::Break => f.write("break "),
+            UOp::Group => {
+                f.write("(");
+                self.lhs.format(f);
+                return f.write(")")
+            },
+        }
+        self.lhs.format(f);
+    }
+}
+
 impl<'de> UnaryOp<'de> {
-    pub fn new(op: UOp, lhs: Box<ExprTree<'de>>) -> Self {
-        Self { op, lhs }
+    pub fn new(op: UOp, lhs: Box<ExprTree<'de>>, span: SourceSpan) -> Self {
+        Self { op, lhs, span }
+    }
+
+    /// ## Checks for `return;` and `break;`
+    /// without the value they have the nil spanning the whole statement
+    pub fn is_bare(&self) -> bool {
+        matches!(*self.lhs, ExprTree::Atom(Atom::Nil, span) if span == self.span)
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    pub fn op(&self) -> UOp {
+        self.op
     }
 }
 
@@ -60,6 +154,7 @@ impl fmt::Display for UOp {
         match self {
             UOp::Minus => write!(f, "-"),
             UOp::Bang => write!(f, "!"),
+            UOp::Tilde => write!(f, "~"),
             UOp::Print => write!(f, "print"),
             UOp::Return => write!(f, "return"),
             UOp::Break => write!(f, "break"),
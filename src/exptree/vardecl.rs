@@ -1,32 +1,100 @@
-use std::fmt;
-use miette::Error;
+use std::{fmt, rc::Rc};
+use miette::{Error, SourceSpan};
 
-use crate::{error::DefaultRuntimeError, evaluator::{Eval, Event, Value}, exptree::Atom};
+use crate::{evaluator::{Eval, Event, Value}, exptree::Atom, resolver::{Binding, Resolve, Resolver}, vm::{Compile, Compiler}, ir::{Builder, Inst, Lower, Temp}, lsp::{Outline, Symbol, SymbolKind}, formatter::{Format, Formatter}};
 
 use super::ExprTree;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct VarDecl<'de> {
     indent: &'de str,
-    exp: Box<ExprTree<'de>>
+    binding: Binding,
+    exp: Box<ExprTree<'de>>,
+    /// lines of the doc comment
+    doc: Rc<[&'de str]>,
+    span: SourceSpan
 }
 
 impl<'de: 'a, 'a> Eval<'a> for VarDecl<'de> {
-    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value, Error> {
-        let v = self.exp.eval(ctx)?;
-
-        if matches!(v, Value::Event(_)) {
-            return Err(DefaultRuntimeError {}.into()) // TODO change errors
-        }
-        ctx.insert(self.indent, v);
+    fn eval(&self, ctx: &crate::context::CtxTree<'a>) -> Result<Value<'a>, Error> {
+        let v = operand!(self.exp, ctx);
+        ctx.declare(self.indent, self.binding, v);
         Ok(Value::Event(Event::NoVal))
     }
 }
 
 
+impl<'de> Resolve<'de> for VarDecl<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error> {
+        self.binding = resolver.declare(self.indent);
+        self.exp.resolve(resolver)?;
+        resolver.define(self.indent);
+        Ok(())
+    }
+}
+
+impl<'de> Compile<'de> for VarDecl<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>) {
+        compiler.operand(&self.exp);
+        compiler.define_var(self.indent, self.binding, self.span);
+    }
+}
+
+impl<'de> Lower<'de> for VarDecl<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp> {
+        let src = builder.operand(&self.exp);
+        builder.emit(Inst::Define { var: Builder::var(self.indent, self.binding), src }, self.span);
+        None
+    }
+}
+
+impl<'de> Outline<'de> for VarDecl<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>) {
+        let mut children = Vec::new();
+        self.exp.outline(&mut children);
+        // variable holding anonymous function is shown as the function
+        let (kind, params) = match *self.exp {
+            ExprTree::FnBlock(ref fnb) if fnb.ident().is_none() => (SymbolKind::Function, fnb.args().to_vec()),
+            _ => (SymbolKind::Variable, Vec::new()),
+        };
+        symbols.push(Symbol { name: self.indent, kind, span: self.span, params, doc: self.doc.clone(), children });
+    }
+}
+
+impl<'de> Format<'de> for VarDecl<'de> {
+    fn format(&self, f: &mut Formatter<'de>) {
+        f.write("var ");
+        f.write(self.indent);
+        // `var a;` has the nil spanning the whole declaration
+        if !matches!(*self.exp, ExprTree::Atom(Atom::Nil, span) if span == self.span) {
+            f.write(" = ");
+            self.exp.format(f);
+        }
+    }
+}
+
 impl<'de> VarDecl<'de> {
-    pub fn new(indent: &'de str, exp: Option<Box<ExprTree<'de>>>) -> Self {
-        Self { indent, exp: exp.unwrap_or(Box::new(ExprTree::Atom(Atom::Nil))) }
+    pub fn new(indent: &'de str, exp: Option<Box<ExprTree<'de>>>, span: SourceSpan) -> Self {
+        Self { indent, binding: Binding::Global, exp: exp.unwrap_or_else(|| Box::new(ExprTree::Atom(Atom::Nil, span))), doc: Rc::new([]), span }
+    }
+
+    pub fn with_doc(self, doc: Vec<&'de str>) -> Self {
+        Self { doc: doc.into(), ..self }
+    }
+
+    /// ## Doc comment of the variable
+    /// One item per `///` line
+    pub fn doc(&self) -> &[&'de str] {
+        &self.doc
+    }
+
+    /// ## Name of the declared variable
+    pub fn name(&self) -> &'de str {
+        self.indent
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
     }
 }
 
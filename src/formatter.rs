@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+
+use miette::{Error, SourceSpan};
+
+use crate::{exptree::{ExprTree, FnBlock}, lexer::Lexer, parser::Parser, token::{Trivia, TriviaKind}};
+
+const INDENT: &str = "    ";
+
+/// ## Formattable node
+/// Implemented by every node of the expression tree, writes the node
+/// back as canonical source code
+pub trait Format<'de> {
+    fn format(&self, f: &mut Formatter<'de>);
+}
+
+/// ## Formats the program
+/// Source has to parse without errors, the comments are kept
+/// in front of the statement they precede, after the statement they end the line of
+/// or after the token they follow inside of the expression
+pub fn format(src: &str) -> Result<String, Error> {
+    let stmts = Parser::new(src).parse()?;
+    let mut f = Formatter::new(src);
+    f.statements(&stmts, None, false);
+    f.comments_before(src.len());
+    let mut out = f.out;
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// ## Comments of the source
+/// Returns all comments in the order they appear, they are taken
+/// from the trivia of the tokens
+pub fn comments(src: &str) -> Vec<Trivia<'_>> {
+    let mut lexer = Lexer::with_trivia(src);
+    let mut trivia: Vec<Trivia> = lexer.by_ref().flatten().flat_map(|t| t.trivia).collect();
+    trivia.extend_from_slice(lexer.trailing_trivia());
+    trivia.into_iter()
+        .filter(|t| t.kind == TriviaKind::Comment)
+        .map(|t| Trivia { text: t.text.trim_end(), ..t })
+        .collect()
+}
+
+/// ## Writer of the formatted code
+/// Nodes write themselves inline, blocks and statements
+/// take care of the lines, indentation and comments
+pub struct Formatter<'de> {
+    src: &'de str,
+    out: String,
+    indent: usize,
+    comments: VecDeque<Trivia<'de>>,
+    /// end of the last written statement or comment in the source
+    last_end: usize,
+}
+
+impl<'de> Formatter<'de> {
+    pub fn new(src: &'de str) -> Self {
+        Self { src, out: String::new(), indent: 0, comments: comments(src).into(), last_end: 0 }
+    }
+
+    pub fn write(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    /// ## Source of the span
+    /// literals are written as they were written in the source
+    pub fn source(&self, span: SourceSpan) -> &'de str {
+        &self.src[span.offset()..span.offset() + span.len()]
+    }
+
+    /// ## Writes list of expressions
+    /// expressions are separated by commas
+    pub fn list(&mut self, items: &[ExprTree<'de>]) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            item.format(self);
+        }
+    }
+
+    /// ## Writes the block
+    /// Every statement is written on its own line, empty block stays on one line
+    pub fn block(&mut self, stmts: &[ExprTree<'de>], retexp: Option<&ExprTree<'de>>, span: SourceSpan) {
+        let end = span.offset() + span.len();
+        let has_comments = self.comments.front().is_some_and(|c| c.offset < end);
+        if stmts.is_empty() && retexp.is_none() && !has_comments {
+            self.write("{}");
+            return
+        }
+        self.write("{");
+        self.indent += 1;
+        self.last_end = span.offset() + 1;
+        self.statements(stmts, retexp, true);
+        self.comments_before(end);
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    /// ## Writes the body of the class
+    /// Every method is written on its own line like the statements of the block
+    pub fn methods(&mut self, methods: &[FnBlock<'de>], span: SourceSpan) {
+        let end = span.offset() + span.len();
+        let has_comments = self.comments.front().is_some_and(|c| c.offset < end);
+        if methods.is_empty() && !has_comments {
+            self.write("{}");
+            return
+        }
+        self.write("{");
+        self.indent += 1;
+        self.last_end = span.offset();
+        for method in methods {
+            self.statement_start(method.span().offset());
+            method.format_method(self);
+            self.statement_end(method.span());
+        }
+        self.comments_before(end);
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    /// ## Writes the statements
+    /// Statements are terminated by semicolons except of the ones ending with a block,
+    /// those need it only when they are the last statement of the block
+    pub fn statements(&mut self, stmts: &[ExprTree<'de>], retexp: Option<&ExprTree<'de>>, in_block: bool) {
+        for (i, stmt) in stmts.iter().enumerate() {
+            self.statement_start(stmt.span().offset());
+            stmt.format(self);
+            let last = in_block && i + 1 == stmts.len() && retexp.is_none();
+            if !ends_with_block(stmt) || last {
+                self.write(";");
+            }
+            self.statement_end(stmt.span());
+        }
+        if let Some(retexp) = retexp {
+            self.statement_start(retexp.span().offset());
+            retexp.format(self);
+            self.statement_end(retexp.span());
+        }
+    }
+
+    /// ## Writes comments in front of the token
+    /// Comments between the tokens of the expression stay after the token they follow,
+    /// line comment continues the expression on the next line
+    pub fn comments_within(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.front().copied().filter(|c| c.offset < offset) {
+            self.comments.pop_front();
+            if !self.out.ends_with(' ') {
+                self.write(" ");
+            }
+            self.write(comment.text);
+            if comment.text.starts_with("//") {
+                self.indent += 1;
+                self.newline();
+                self.indent -= 1;
+            } else {
+                self.write(" ");
+            }
+        }
+    }
+
+    /// Writes comments before the offset, each on its own line
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.front().copied().filter(|c| c.offset < offset) {
+            self.comments.pop_front();
+            self.line_at(comment.offset);
+            self.write(comment.text);
+            self.last_end = comment.offset + comment.text.len();
+        }
+    }
+
+    fn statement_start(&mut self, offset: usize) {
+        self.comments_before(offset);
+        self.line_at(offset);
+    }
+
+    /// Comment on the same line as the end of the statement stays after it,
+    /// so does the comment left inside of the statement, doc comments
+    /// always stay in front of the statement they document
+    fn statement_end(&mut self, span: SourceSpan) {
+        let end = span.offset() + span.len();
+        self.last_end = end;
+        if let Some(comment) = self.comments.front().copied().filter(|c| !is_doc(c.text)) {
+            let between = self.src.get(end..comment.offset).unwrap_or_default();
+            if !between.contains(|c: char| c == '\n' || !(c.is_whitespace() || c == ';')) {
+                self.comments.pop_front();
+                self.write(" ");
+                self.write(comment.text);
+                self.last_end = end.max(comment.offset + comment.text.len());
+            }
+        }
+    }
+
+    /// Starts new line for the item at the offset, keeps single blank line
+    /// if there was at least one in the source, except right after the opening brace
+    fn line_at(&mut self, offset: usize) {
+        if self.out.is_empty() {
+            return
+        }
+        let gap = self.src.get(self.last_end..offset).unwrap_or_default();
+        if gap.matches('\n').count() > 1 && !self.out.ends_with('{') {
+            self.out.push('\n');
+        }
+        self.newline();
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+}
+
+/// whether the comment is `///` doc comment, `////` and longer are ordinary comments
+fn is_doc(comment: &str) -> bool {
+    comment.strip_prefix("///").is_some_and(|doc| !doc.starts_with('/'))
+}
+
+/// whether the statement ends with a block and so doesn't need a semicolon
+fn ends_with_block(stmt: &ExprTree<'_>) -> bool {
+    match stmt {
+        ExprTree::Block(..) | ExprTree::If(_) | ExprTree::Loop(_) | ExprTree::Class(_) => true,
+        ExprTree::FnBlock(fnb) => fnb.ident().is_some(),
+        _ => false,
+    }
+}
@@ -0,0 +1,196 @@
+use std::{collections::{BTreeMap, HashMap}, marker::PhantomData, rc::Rc};
+
+use miette::{Error, SourceSpan};
+
+use crate::{context::CtxTree, error::RuntimeError, evaluator::{Class, Event, Value}, exptree::ExprTree};
+
+use super::{BinOp, Builder, Closure, Function, Inst, UnOp, Var};
+
+/// ## Interpreter of the lowered code
+/// Every call gets its own frame of temporaries, variables
+/// live in the same context tree as in the tree-walking evaluator.
+///
+/// Like in the virtual machine, globals are searched from the environment
+/// of the running function
+#[derive(Default)]
+pub struct Interpreter<'de> {
+    marker: PhantomData<CtxTree<'de>>,
+}
+
+impl<'de> Interpreter<'de> {
+    pub fn new() -> Self {
+        Self { marker: PhantomData }
+    }
+
+    /// ## Runs the program
+    /// Lowers the resolved program and runs it in given context,
+    /// returns the value of the last statement
+    pub fn run(ctx: &CtxTree<'de>, stmts: &[ExprTree<'de>]) -> Result<Value<'de>, Error> {
+        let script = Builder::lower_script(stmts);
+        Interpreter::new().execute(&script, ctx.clone())
+    }
+
+    /// ## Executes lowered function
+    /// Arguments have to be already defined in the `env`
+    pub fn execute(&self, fun: &Function<'de>, mut env: CtxTree<'de>) -> Result<Value<'de>, Error> {
+        let mut temps = vec![Value::Nil; fun.temps];
+        let mut ip = 0;
+        loop {
+            let (inst, span) = (&fun.code[ip], fun.spans[ip]);
+            ip += 1;
+            match inst {
+                Inst::Const { dst, value } => temps[dst.0] = value.clone(),
+                Inst::Copy { dst, src } => temps[dst.0] = temps[src.0].clone(),
+                Inst::Unary { dst, op, src } => {
+                    let v = temps[src.0].clone();
+                    temps[dst.0] = match op {
+                        UnOp::Neg => -v,
+                        UnOp::Not => !v,
+                        UnOp::BitNot => v.bit_not(),
+                    }.map_err(|e| e.at(span))?;
+                },
+                Inst::Binary { dst, op, lhs, rhs } => {
+                    let (l, r) = (temps[lhs.0].clone(), temps[rhs.0].clone());
+                    temps[dst.0] = binary(*op, l, r).map_err(|e| e.at(span))?;
+                },
+
+                Inst::Load { dst, var } => {
+                    temps[dst.0] = self.load(&env, *var)
+                        .ok_or_else(|| RuntimeError::UndefinedVariable { name: name(var).to_string(), span: Some(span) })?;
+                },
+                Inst::LoadFn { dst, var } => {
+                    temps[dst.0] = self.load(&env, *var)
+                        .ok_or_else(|| RuntimeError::UndefinedFunction { name: name(var).to_string(), span: Some(span) })?;
+                },
+                Inst::Store { var, src } => {
+                    let v = temps[src.0].clone();
+                    match *var {
+                        Var::Global(name) => env.set(name, v),
+                        Var::Local { depth, slot, .. } => env.set_at(depth, slot, v),
+                    }.map_err(|_| RuntimeError::UndefinedVariable { name: name(var).to_string(), span: Some(span) })?;
+                },
+                Inst::Define { var, src } => {
+                    let v = temps[src.0].clone();
+                    match *var {
+                        Var::Global(name) => env.insert(name, v),
+                        Var::Local { slot, .. } => env.define(slot, v),
+                    }
+                },
+
+                Inst::List { dst, items } => {
+                    temps[dst.0] = Value::list(items.iter().map(|t| temps[t.0].clone()).collect());
+                },
+                Inst::Map { dst, entries } => {
+                    let map = Value::map(BTreeMap::new());
+                    for (key, value) in entries {
+                        map.set_index(&temps[key.0], temps[value.0].clone()).map_err(|e| e.at(span))?;
+                    }
+                    temps[dst.0] = map;
+                },
+                Inst::Index { dst, target, index } => {
+                    temps[dst.0] = temps[target.0].get_index(&temps[index.0]).map_err(|e| e.at(span))?;
+                },
+                Inst::SetIndex { target, index, src } => {
+                    temps[target.0].set_index(&temps[index.0], temps[src.0].clone()).map_err(|e| e.at(span))?;
+                },
+
+                Inst::Closure { dst, fun: f } => {
+                    let closure = Closure::new(fun.functions[*f].clone(), env.clone());
+                    temps[dst.0] = Value::LoweredFunction(Rc::new(closure));
+                },
+                Inst::Call { dst, callee, args } => {
+                    let args = args.iter().map(|t| temps[t.0].clone()).collect();
+                    temps[dst.0] = self.call(temps[callee.0].clone(), args, span)?;
+                },
+                Inst::Class { dst, name, superclass, methods } => {
+                    let superclass = superclass.map(|t| temps[t.0].clone());
+                    let methods: HashMap<_, _> = methods.iter().map(|(name, fun)| (*name, temps[fun.0].clone())).collect();
+                    let class = Class::new(name, superclass, methods).map_err(|e| e.at(span))?;
+                    temps[dst.0] = Value::Class(Rc::new(class));
+                },
+                Inst::Super { dst, superclass, this, name } => {
+                    let this = temps[this.0].clone();
+                    temps[dst.0] = temps[superclass.0].get_super(name, this).map_err(|e| e.at(span))?;
+                },
+                Inst::Import { dst, path } => {
+                    temps[dst.0] = env.module(path)
+                        .ok_or_else(|| RuntimeError::ModuleNotLoaded { path: path.to_string(), span: Some(span) })?;
+                },
+                Inst::Print { src } => print!("{}", temps[src.0]),
+
+                Inst::EnterScope => env = env.fork(),
+                Inst::ExitScope(n) => {
+                    for _ in 0..*n {
+                        env = env.parent().expect("scope was entered before");
+                    }
+                },
+
+                Inst::Label(_) => {},
+                Inst::Jump(label) => ip = fun.labels[label.0],
+                Inst::Branch { cond, then, otherwise } => {
+                    let label = if bool::from(temps[cond.0].clone()) { then } else { otherwise };
+                    ip = fun.labels[label.0];
+                },
+                Inst::Return(Some(src)) => return Ok(std::mem::replace(&mut temps[src.0], Value::Nil)),
+                Inst::Return(None) => return Ok(Value::Event(Event::NoVal)),
+
+                Inst::NoValue => return Err(RuntimeError::NoValue { span: Some(span) }.into()),
+                Inst::InvalidAssignment => return Err(RuntimeError::InvalidAssignmentTarget { span: Some(span) }.into()),
+            }
+        }
+    }
+
+    fn call(&self, callee: Value<'de>, args: Vec<Value<'de>>, span: SourceSpan) -> Result<Value<'de>, Error> {
+        match callee {
+            Value::LoweredFunction(fun) => fun.call(args).map_err(|e| RuntimeError::locate(e, span)),
+            Value::NativeFunction(fun) => fun.call(args).map_err(|e| RuntimeError::locate(e, span)),
+            // functions of other backends run in their own interpreter
+            Value::Function(fun) => fun.call(args).map_err(|e| RuntimeError::locate(e, span)),
+            Value::CompiledFunction(fun) => fun.call(args).map_err(|e| RuntimeError::locate(e, span)),
+            Value::Class(class) => {
+                let (instance, init) = class.instantiate(args.len()).map_err(|e| e.at(span))?;
+                if let Some(init) = init {
+                    self.call(init, args, span)?;
+                }
+                Ok(instance)
+            },
+            v => Err(RuntimeError::NotCallable { target: v.type_name(), span: Some(span) }.into()),
+        }
+    }
+
+    fn load(&self, env: &CtxTree<'de>, var: Var<'de>) -> Option<Value<'de>> {
+        match var {
+            Var::Global(name) => env.search(name),
+            Var::Local { depth, slot, .. } => env.get_at(depth, slot),
+        }
+    }
+}
+
+fn binary<'de>(op: BinOp, l: Value<'de>, r: Value<'de>) -> Result<Value<'de>, RuntimeError> {
+    match op {
+        BinOp::Add => l + r,
+        BinOp::Sub => l - r,
+        BinOp::Mul => l * r,
+        BinOp::Div => l / r,
+        BinOp::IntDiv => l.int_div(r),
+        BinOp::Mod => l % r,
+        BinOp::Pow => l.pow(r),
+        BinOp::BitAnd => l & r,
+        BinOp::BitOr => l | r,
+        BinOp::BitXor => l ^ r,
+        BinOp::Shl => l << r,
+        BinOp::Shr => l >> r,
+        BinOp::Equal => Ok(Value::Bool(l == r)),
+        BinOp::NotEqual => Ok(Value::Bool(l != r)),
+        BinOp::Less => Ok(Value::Bool(l < r)),
+        BinOp::LessEqual => Ok(Value::Bool(l <= r)),
+        BinOp::Greater => Ok(Value::Bool(l > r)),
+        BinOp::GreaterEqual => Ok(Value::Bool(l >= r)),
+    }
+}
+
+fn name<'de>(var: &Var<'de>) -> &'de str {
+    match var {
+        Var::Global(name) | Var::Local { name, .. } => name,
+    }
+}
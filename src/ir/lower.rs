@@ -0,0 +1,167 @@
+use std::rc::Rc;
+
+use miette::SourceSpan;
+
+use crate::{evaluator::Value, exptree::ExprTree, resolver::Binding};
+
+use super::{Function, Inst, Label, Temp, Var};
+
+/// ## Lowerable node
+/// Implemented by every node of the expression tree, emits the instructions
+/// computing the node and returns the temporary holding its value,
+/// statements without a value return `None`
+pub trait Lower<'de> {
+    fn lower(&self, builder: &mut Builder<'de>) -> Option<Temp>;
+}
+
+/// Loop that `break` and `continue` jump out of
+struct LoopFrame {
+    /// number of open scopes when the loop's block starts
+    scopes: usize,
+    /// temporary holding the value of the loop
+    result: Temp,
+    exit: Label,
+    next: Label,
+}
+
+/// ## Lowering of the expression tree
+/// Builds a single function, each nested function gets its own builder.
+/// Sugar of the tree (groups, for loop parts, block return expressions, ...)
+/// is turned into plain jumps, temporaries and explicit scopes
+#[derive(Default)]
+pub struct Builder<'de> {
+    fun: Function<'de>,
+    scopes: usize,
+    loops: Vec<LoopFrame>,
+}
+
+impl<'de> Builder<'de> {
+    /// ## Lowers the program
+    /// Program is lowered into the function that returns the value of the last statement
+    pub fn lower_script(stmts: &[ExprTree<'de>]) -> Function<'de> {
+        let mut builder = Builder::default();
+        let span = SourceSpan::from(0..0);
+        let res = match stmts.split_last() {
+            Some((last, stmts)) => {
+                for stmt in stmts {
+                    stmt.lower(&mut builder);
+                }
+                last.lower(&mut builder)
+            },
+            None => Some(builder.constant(Value::Nil, span)),
+        };
+        builder.emit(Inst::Return(res), span);
+        builder.finish("script", 0)
+    }
+
+    /// ## Lowers the function
+    /// Arguments are already defined by the call, body is lowered as usual
+    pub fn lower_function(name: &'de str, arity: usize, body: &ExprTree<'de>) -> Function<'de> {
+        let mut builder = Builder::default();
+        let res = builder.operand(body);
+        builder.emit(Inst::Return(Some(res)), body.span());
+        builder.finish(name, arity)
+    }
+
+    fn finish(mut self, name: &'de str, arity: usize) -> Function<'de> {
+        self.fun.name = name;
+        self.fun.arity = arity;
+        self.fun
+    }
+
+    pub fn emit(&mut self, inst: Inst<'de>, span: SourceSpan) {
+        if let Inst::Label(label) = inst {
+            self.fun.labels[label.0] = self.fun.code.len();
+        }
+        self.fun.code.push(inst);
+        self.fun.spans.push(span);
+    }
+
+    /// ## Lowers the operand
+    /// Statements can't be used as values, they report an error right after they run
+    pub fn operand(&mut self, expr: &ExprTree<'de>) -> Temp {
+        match expr.lower(self) {
+            Some(t) => t,
+            None => {
+                self.emit(Inst::NoValue, expr.span());
+                self.temp()
+            }
+        }
+    }
+
+    /// ## New temporary
+    pub fn temp(&mut self) -> Temp {
+        self.fun.temps += 1;
+        Temp(self.fun.temps - 1)
+    }
+
+    /// ## New label
+    /// has to be placed with `Inst::Label` before the function is finished
+    pub fn label(&mut self) -> Label {
+        self.fun.labels.push(usize::MAX);
+        Label(self.fun.labels.len() - 1)
+    }
+
+    pub fn constant(&mut self, value: Value<'de>, span: SourceSpan) -> Temp {
+        let dst = self.temp();
+        self.emit(Inst::Const { dst, value }, span);
+        dst
+    }
+
+    /// ## Adds nested function
+    /// returns its index for `Inst::Closure`
+    pub fn function(&mut self, function: Function<'de>) -> usize {
+        self.fun.functions.push(Rc::new(function));
+        self.fun.functions.len() - 1
+    }
+
+    pub fn var(name: &'de str, binding: Binding) -> Var<'de> {
+        match binding {
+            Binding::Global => Var::Global(name),
+            Binding::Local { depth, slot } => Var::Local { name, depth, slot },
+        }
+    }
+
+    /// ## Opens new scope
+    /// mirrors the scope opened by the resolver
+    pub fn begin_scope(&mut self, span: SourceSpan) {
+        self.scopes += 1;
+        self.emit(Inst::EnterScope, span);
+    }
+
+    pub fn end_scope(&mut self, span: SourceSpan) {
+        self.scopes -= 1;
+        self.emit(Inst::ExitScope(1), span);
+    }
+
+    /// ## Starts the loop's block
+    /// `break` writes into `result` and jumps to `exit`, `continue` jumps to `next`
+    pub fn begin_loop(&mut self, result: Temp, exit: Label, next: Label) {
+        self.loops.push(LoopFrame { scopes: self.scopes, result, exit, next });
+    }
+
+    pub fn end_loop(&mut self) {
+        self.loops.pop();
+    }
+
+    /// ## Breaks out of the loop with the value
+    pub fn emit_break(&mut self, value: Temp, span: SourceSpan) {
+        let frame = self.loops.last().expect("resolved break is inside of a loop");
+        let (result, exit, scopes) = (frame.result, frame.exit, self.scopes - frame.scopes);
+        self.emit(Inst::Copy { dst: result, src: value }, span);
+        if scopes > 0 {
+            self.emit(Inst::ExitScope(scopes), span);
+        }
+        self.emit(Inst::Jump(exit), span);
+    }
+
+    /// ## Continues with the next iteration of the loop
+    pub fn emit_continue(&mut self, span: SourceSpan) {
+        let frame = self.loops.last().expect("resolved continue is inside of a loop");
+        let (next, scopes) = (frame.next, self.scopes - frame.scopes);
+        if scopes > 0 {
+            self.emit(Inst::ExitScope(scopes), span);
+        }
+        self.emit(Inst::Jump(next), span);
+    }
+}
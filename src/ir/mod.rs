@@ -0,0 +1,339 @@
+use std::{fmt, rc::Rc};
+
+use miette::{Error, SourceSpan};
+
+use crate::{context::{Captured, Capturing, CtxTree}, error::RuntimeError, evaluator::Value};
+
+pub mod lower;
+pub mod interp;
+
+pub use lower::*;
+pub use interp::*;
+
+/// ## Temporary
+/// Register of the function's frame holding intermediate value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Temp(pub(crate) usize);
+
+impl fmt::Display for Temp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+/// ## Jump target
+/// Index into the labels of the function, placed by `Inst::Label`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Label(pub(crate) usize);
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L{}", self.0)
+    }
+}
+
+/// ## Variable
+/// Globals are accessed by the name, locals by the resolved slot
+/// of the scope `depth` levels above the current one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Var<'de> {
+    Global(&'de str),
+    Local { name: &'de str, depth: usize, slot: usize },
+}
+
+impl fmt::Display for Var<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Var::Global(name) => write!(f, "@{name}"),
+            Var::Local { name, depth, slot } => write!(f, "{name}[{depth}:{slot}]"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    IntDiv,
+    Mod,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::IntDiv => "div",
+            BinOp::Mod => "%",
+            BinOp::Pow => "**",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::Equal => "==",
+            BinOp::NotEqual => "!=",
+            BinOp::Less => "<",
+            BinOp::LessEqual => "<=",
+            BinOp::Greater => ">",
+            BinOp::GreaterEqual => ">=",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// ## Single instruction
+/// Instructions read their operands from temporaries and write the result into `dst`,
+/// the only control flow are jumps to the labels of the same function
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst<'de> {
+    Const { dst: Temp, value: Value<'de> },
+    Copy { dst: Temp, src: Temp },
+    Unary { dst: Temp, op: UnOp, src: Temp },
+    Binary { dst: Temp, op: BinOp, lhs: Temp, rhs: Temp },
+
+    Load { dst: Temp, var: Var<'de> },
+    /// same as `Load` but reports missing function
+    LoadFn { dst: Temp, var: Var<'de> },
+    Store { var: Var<'de>, src: Temp },
+    Define { var: Var<'de>, src: Temp },
+
+    List { dst: Temp, items: Vec<Temp> },
+    Map { dst: Temp, entries: Vec<(Temp, Temp)> },
+    Index { dst: Temp, target: Temp, index: Temp },
+    SetIndex { target: Temp, index: Temp, src: Temp },
+
+    /// creates closure of the nested function
+    Closure { dst: Temp, fun: usize },
+    Call { dst: Temp, callee: Temp, args: Vec<Temp> },
+    /// creates class from the closures of its methods
+    Class { dst: Temp, name: &'de str, superclass: Option<Temp>, methods: Vec<(&'de str, Temp)> },
+    /// method of the superclass bound to `this`
+    Super { dst: Temp, superclass: Temp, this: Temp, name: &'de str },
+    /// namespace of the module linked under the path
+    Import { dst: Temp, path: &'de str },
+    Print { src: Temp },
+
+    /// forks the environment
+    EnterScope,
+    /// drops n innermost environments
+    ExitScope(usize),
+
+    Label(Label),
+    Jump(Label),
+    Branch { cond: Temp, then: Label, otherwise: Label },
+    /// returns the value, `None` when the last statement of the program has no value
+    Return(Option<Temp>),
+
+    /// statement was used as a value
+    NoValue,
+    InvalidAssignment,
+}
+
+impl fmt::Display for Inst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inst::Const { dst, value: Value::String(s) } => write!(f, "{dst} = const {s:?}"),
+            Inst::Const { dst, value } => write!(f, "{dst} = const {value}"),
+            Inst::Copy { dst, src } => write!(f, "{dst} = {src}"),
+            Inst::Unary { dst, op: UnOp::Neg, src } => write!(f, "{dst} = -{src}"),
+            Inst::Unary { dst, op: UnOp::Not, src } => write!(f, "{dst} = !{src}"),
+            Inst::Unary { dst, op: UnOp::BitNot, src } => write!(f, "{dst} = ~{src}"),
+            Inst::Binary { dst, op, lhs, rhs } => write!(f, "{dst} = {lhs} {op} {rhs}"),
+            Inst::Load { dst, var } => write!(f, "{dst} = load {var}"),
+            Inst::LoadFn { dst, var } => write!(f, "{dst} = load fn {var}"),
+            Inst::Store { var, src } => write!(f, "store {var}, {src}"),
+            Inst::Define { var, src } => write!(f, "define {var}, {src}"),
+            Inst::List { dst, items } => write!(f, "{dst} = list [{}]", join(items)),
+            Inst::Map { dst, entries } => {
+                let entries: Vec<_> = entries.iter().map(|(k, v)| format!("{k}: {v}")).collect();
+                write!(f, "{dst} = map {{{}}}", entries.join(", "))
+            },
+            Inst::Index { dst, target, index } => write!(f, "{dst} = {target}[{index}]"),
+            Inst::SetIndex { target, index, src } => write!(f, "{target}[{index}] = {src}"),
+            Inst::Closure { dst, fun } => write!(f, "{dst} = closure #{fun}"),
+            Inst::Call { dst, callee, args } => write!(f, "{dst} = call {callee}({})", join(args)),
+            Inst::Class { dst, name, superclass, methods } => {
+                write!(f, "{dst} = class {name}")?;
+                if let Some(superclass) = superclass {
+                    write!(f, " < {superclass}")?;
+                }
+                let methods: Vec<_> = methods.iter().map(|(name, fun)| format!("{name}: {fun}")).collect();
+                write!(f, " {{{}}}", methods.join(", "))
+            },
+            Inst::Super { dst, superclass, this, name } => write!(f, "{dst} = super {superclass}.{name} for {this}"),
+            Inst::Import { dst, path } => write!(f, "{dst} = import {path:?}"),
+            Inst::Print { src } => write!(f, "print {src}"),
+            Inst::EnterScope => write!(f, "enter scope"),
+            Inst::ExitScope(n) => write!(f, "exit scope {n}"),
+            Inst::Label(label) => write!(f, "{label}:"),
+            Inst::Jump(label) => write!(f, "jump {label}"),
+            Inst::Branch { cond, then, otherwise } => write!(f, "branch {cond}, {then}, {otherwise}"),
+            Inst::Return(Some(src)) => write!(f, "return {src}"),
+            Inst::Return(None) => write!(f, "return"),
+            Inst::NoValue => write!(f, "error no value"),
+            Inst::InvalidAssignment => write!(f, "error invalid assignment"),
+        }
+    }
+}
+
+fn join(temps: &[Temp]) -> String {
+    temps.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// ## Lowered function
+/// Arguments are stored in the first slots of the function's scope,
+/// every instruction remembers the span of the expression it was lowered from
+#[derive(Debug, Default)]
+pub struct Function<'de> {
+    pub(crate) name: &'de str,
+    pub(crate) arity: usize,
+    /// number of temporaries used by the function
+    pub(crate) temps: usize,
+    pub(crate) code: Vec<Inst<'de>>,
+    pub(crate) spans: Vec<SourceSpan>,
+    /// position of each label in the code
+    pub(crate) labels: Vec<usize>,
+    pub(crate) functions: Vec<Rc<Function<'de>>>,
+}
+
+impl<'de> Function<'de> {
+    pub fn name(&self) -> &'de str {
+        self.name
+    }
+
+    pub fn code(&self) -> &[Inst<'de>] {
+        &self.code
+    }
+}
+
+impl fmt::Display for Function<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, "")
+    }
+}
+
+impl Function<'_> {
+    /// Writes the function and all nested functions,
+    /// `path` are the indexes of the nested functions leading to this one
+    fn write(&self, f: &mut fmt::Formatter<'_>, path: &str) -> fmt::Result {
+        write!(f, "fun {}", self.name)?;
+        if !path.is_empty() {
+            write!(f, " #{path}")?;
+        }
+        writeln!(f, " ({} args, {} temps)", self.arity, self.temps)?;
+        for (inst, span) in self.code.iter().zip(&self.spans) {
+            match inst {
+                Inst::Label(_) => writeln!(f, "{:>5} {inst}", span.offset())?,
+                _ => writeln!(f, "{:>5}     {inst}", span.offset())?,
+            }
+        }
+        for (i, fun) in self.functions.iter().enumerate() {
+            writeln!(f)?;
+            let path = if path.is_empty() { i.to_string() } else { format!("{path}.{i}") };
+            fun.write(f, &path)?;
+        }
+        Ok(())
+    }
+}
+
+/// ## Lowered function value
+/// Lowered function together with the environment it was declared in
+pub struct Closure<'de> {
+    fun: Rc<Function<'de>>,
+    env: Captured<'de>
+}
+
+impl<'de> Closure<'de> {
+    pub fn new(fun: Rc<Function<'de>>, env: CtxTree<'de>) -> Self {
+        Self { fun, env: Captured::new(env) }
+    }
+
+    pub fn name(&self) -> &'de str {
+        self.fun.name
+    }
+
+    /// ## Calls the function
+    /// Runs the body in a fork of its environment holding the arguments
+    pub fn call(&self, args: Vec<Value<'de>>) -> Result<Value<'de>, Error> {
+        if self.fun.arity != args.len() {
+            return Err(RuntimeError::ArityMismatch {
+                name: self.name().to_string(),
+                expected: self.fun.arity,
+                found: args.len(),
+                span: None
+            }.into())
+        }
+        let env = self.env.ctx().fork();
+        for (slot, v) in args.into_iter().enumerate() {
+            env.define(slot, v);
+        }
+        Interpreter::new().execute(&self.fun, env)
+    }
+
+    /// ## Binds the method
+    /// see `evaluator::Closure::bind`
+    pub fn bind(&self, this: Value<'de>) -> Self {
+        let env = self.env.ctx().fork();
+        env.define(0, this);
+        Self::new(self.fun.clone(), env)
+    }
+}
+
+impl<'de> Capturing<'de> for Closure<'de> {
+    fn env(&self) -> &Captured<'de> {
+        &self.env
+    }
+
+    fn with_env(&self, env: Captured<'de>) -> Self {
+        Self { fun: self.fun.clone(), env }
+    }
+}
+
+// see `evaluator::Closure`
+impl PartialEq for Closure<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.fun, &other.fun) && self.env.same(&other.env)
+    }
+}
+
+impl PartialOrd for Closure<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for Closure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Closure({})", self.name())
+    }
+}
+
+impl fmt::Display for Closure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
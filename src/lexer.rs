@@ -1,14 +1,22 @@
-use std::mem;
-
 use miette::{Error, LabeledSpan, SourceSpan};
 
-use crate::{error::{Eof, SingleTokenError, UnterminatedStringError}, token::{Token, TokenKind}};
+use crate::{error::{Eof, IntegerOutOfRange, SingleTokenError, UnterminatedCommentError, UnterminatedStringError}, token::{Token, TokenKind, Trivia, TriviaKind}};
 
 pub struct Lexer<'de> {
     whole: &'de str,
     rest: &'de str,
     byte: usize,
-    peeked: Option<Result<Token<'de>, Error>>
+    peeked: Option<Result<Token<'de>, Error>>,
+    consumed: (usize, usize),
+    consumed_kind: Option<TokenKind>,
+    /// line and column at the `located` byte, tokens are located from there
+    line: usize,
+    column: usize,
+    located: usize,
+    /// trivia waiting for the next token, `None` when trivia isn't collected
+    trivia: Option<Vec<Trivia<'de>>>,
+    /// lines of the doc comments waiting for the next token
+    doc: Vec<&'de str>,
 }
 
 impl<'de> Lexer<'de> {
@@ -17,21 +25,54 @@ impl<'de> Lexer<'de> {
             whole: input,
             rest: input,
             byte: 0,
-            peeked: None
+            peeked: None,
+            consumed: (0, 0),
+            consumed_kind: None,
+            line: 1,
+            column: 1,
+            located: 0,
+            trivia: None,
+            doc: Vec::new(),
         }
     }
+    /// ## Creates trivia-preserving lexer
+    /// Every token carries the whitespace and comments in front of it,
+    /// the ones after the last token are `trailing_trivia`
+    pub fn with_trivia(input: &'de str) -> Self {
+        Self { trivia: Some(Vec::new()), ..Self::new(input) }
+    }
+    /// ## Trivia after the last token
+    /// Complete once the lexer returned `None`
+    pub fn trailing_trivia(&self) -> &[Trivia<'de>] {
+        self.trivia.as_deref().unwrap_or_default()
+    }
     pub fn peek(&mut self) -> Option<&Result<Token<'de>, Error>> {
         if self.peeked.is_some() {
             return self.peeked.as_ref()
         }
-        self.peeked = self.next();
+        self.peeked = self.lex_located();
         self.peeked.as_ref()
     }
+    /// ## Start of the last consumed token
+    /// Returns byte offset at which the last token returned by `next` starts
+    pub fn consumed_start(&self) -> usize {
+        self.consumed.0
+    }
+    /// ## End of the last consumed token
+    /// Returns byte offset right after the last token returned by `next`
+    pub fn consumed_end(&self) -> usize {
+        self.consumed.1
+    }
+    /// ## Kind of the last consumed token
+    /// Returns `None` if no token was consumed yet
+    pub fn consumed_kind(&self) -> Option<TokenKind> {
+        self.consumed_kind
+    }
     pub fn expect_next(&mut self, next: TokenKind, unexpected: &str) -> Result<Token<'de>, miette::Error> {
         match self.next() {
             Some(Ok(token)) if token.kind == next => Ok(token),
             Some(Ok(token)) => {
-                return Err(miette::miette! {
+                Err(miette::miette! {
                     labels = vec![
                     LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
                     ],
@@ -39,8 +80,8 @@ impl<'de> Lexer<'de> {
                     "{unexpected}"
                 }.with_source_code(self.whole.to_string()))
             },
-            Some(Err(e)) => return Err(e),
-            None => return Err(Eof.into())
+            Some(Err(e)) => Err(e),
+            None => Err(Eof.into())
         }
     }
     pub fn next_or_error(&mut self) -> Result<Token<'de>, miette::Error> {
@@ -52,18 +93,97 @@ impl<'de> Lexer<'de> {
     }
 }
 
+/// Lexical error doesn't stop the lexer, the broken input is skipped
+/// and the lexing continues with the following token
 impl<'de> Iterator for Lexer<'de> 
 {
     type Item = Result<Token<'de>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(_) = self.peeked {
-            return mem::replace(&mut self.peeked, None);
+        let next = if self.peeked.is_some() {
+            self.peeked.take()
+        } else {
+            self.lex_located()
+        };
+        if let Some(Ok(ref token)) = next {
+            self.consumed = (token.offset, token.offset + token.origin.len());
+            self.consumed_kind = Some(token.kind);
+        }
+        next
+    }
+}
+
+impl<'de> Lexer<'de> {
+    /// ## Lexes next token with its trivia
+    fn lex_located(&mut self) -> Option<Result<Token<'de>, Error>> {
+        let start = self.byte;
+        let mut next = self.lex();
+        if let Some(Err(_)) = next {
+            // skipped input is kept as trivia so the source can still be reconstructed
+            let from = self.trailing_trivia().last()
+                .map(|t| t.offset + t.text.len())
+                .filter(|&end| end > start)
+                .unwrap_or(start);
+            self.push_trivia(TriviaKind::Invalid, from, self.byte);
+        }
+        if let Some(Ok(token)) = next.as_mut() {
+            token.doc = std::mem::take(&mut self.doc);
+            if let Some(trivia) = self.trivia.as_mut() {
+                token.trivia = std::mem::take(trivia);
+            }
+        }
+        next
+    }
+
+    /// ## Line and column of the offset
+    /// Offsets are located in order, so the line and column are counted
+    /// only over the input between the previously located offset and this one
+    fn locate(&mut self, offset: usize) -> (usize, usize) {
+        let between = &self.whole[self.located..offset];
+        match between.rsplit_once('\n') {
+            Some((before, last)) => {
+                self.line += before.matches('\n').count() + 1;
+                self.column = last.chars().count() + 1;
+            },
+            None => self.column += between.chars().count(),
+        }
+        self.located = offset;
+        (self.line, self.column)
+    }
+
+    /// Token made of the current character and the next one,
+    /// `from` is the input starting at the current character
+    fn pair(&mut self, kind: TokenKind, from: &'de str, at: usize) -> Option<Result<Token<'de>, Error>> {
+        let next = self.rest.chars().next()?.len_utf8();
+        self.rest = &self.rest[next..];
+        self.byte += next;
+        let (line, column) = self.locate(at);
+        Some(Ok(Token::new(kind, &from[..self.byte - at], at, line, column)))
+    }
+
+    /// Adds the input between `start` and `end` to the trivia of the next token,
+    /// whitespace right after whitespace extends it
+    fn push_trivia(&mut self, kind: TriviaKind, start: usize, end: usize) {
+        let Some(trivia) = self.trivia.as_mut() else { return };
+        if start == end {
+            return
+        }
+        match trivia.last_mut() {
+            Some(last) if kind == TriviaKind::Whitespace && last.kind == kind && last.offset + last.text.len() == start => {
+                last.text = &self.whole[last.offset..end];
+            },
+            _ => trivia.push(Trivia { kind, text: &self.whole[start..end], offset: start }),
         }
+    }
+
+    /// ## Lexes next token
+    /// Reads the next token from the input without looking at the peeked one
+    fn lex(&mut self) -> Option<Result<Token<'de>, Error>> {
         loop {
             let mut chars = self.rest.chars();
             let c = chars.next()?;
             let c_at = self.byte;
+            let (line, column) = self.locate(c_at);
             let c_str = &self.rest[..c.len_utf8()];
             let c_onwards = self.rest;
             self.rest = chars.as_str();
@@ -78,7 +198,7 @@ impl<'de> Iterator for Lexer<'de>
             }
 
             let just = move |kind: TokenKind| {
-                Some(Ok(Token::new(kind, c_str, c_at)))
+                Some(Ok(Token::new(kind, c_str, c_at, line, column)))
             };
 
             let started = match c {
@@ -93,7 +213,16 @@ impl<'de> Iterator for Lexer<'de>
                 '-' => return just(TokenKind::MINUS),
                 '+' => return just(TokenKind::PLUS),
                 ';' => return just(TokenKind::SEMICOLON), 
+                ':' => return just(TokenKind::COLON),
+                '*' if self.rest.starts_with('*') => return self.pair(TokenKind::STAR_STAR, c_onwards, c_at),
                 '*' => return just(TokenKind::STAR),
+                '%' => return just(TokenKind::PERCENT),
+                '&' => return just(TokenKind::AMPERSAND),
+                '|' => return just(TokenKind::PIPE),
+                '^' => return just(TokenKind::CARET),
+                '~' => return just(TokenKind::TILDE),
+                '<' if self.rest.starts_with('<') => return self.pair(TokenKind::LESS_LESS, c_onwards, c_at),
+                '>' if self.rest.starts_with('>') => return self.pair(TokenKind::GREATER_GREATER, c_onwards, c_at),
 
                 '/' => Started::Comment,
                 '<' => Started::IfEqualElse(TokenKind::LESS_EQUAL, TokenKind::LESS),
@@ -104,7 +233,10 @@ impl<'de> Iterator for Lexer<'de>
                 '0'..='9' => Started::Number,
                 'a'..='z' | 'A'..='Z' | '_' => Started::Ident,
 
-                c if c.is_whitespace() => continue,
+                c if c.is_whitespace() => {
+                    self.push_trivia(TriviaKind::Whitespace, c_at, self.byte);
+                    continue
+                },
                 _ => return Some(Err(SingleTokenError {
                     src: self.whole.to_string(),
                     token: c,
@@ -125,7 +257,7 @@ impl<'de> Iterator for Lexer<'de>
                     let mut state = State::Normal;
                     let mut i = 0;
 
-                    while let Some(c) = chars.next() {
+                    for c in chars.by_ref() {
                         i += c.len_utf8();
                         match c {
                             '\\' => match state {
@@ -139,10 +271,7 @@ impl<'de> Iterator for Lexer<'de>
                                     break
                                 }
                             },
-                            _ => match state {
-                                State::Escape => state = State::Normal,
-                                _ => {}
-                            }
+                            _ => if state == State::Escape { state = State::Normal }
                         }
                     }
                     let str_rep = &c_onwards[..i + c.len_utf8()];
@@ -155,7 +284,7 @@ impl<'de> Iterator for Lexer<'de>
                             err_span: SourceSpan::from(self.byte - c.len_utf8() - i..self.byte),
                         }.into()))
                     }
-                    return Some(Ok(Token::new(TokenKind::STRING, str_rep, c_at)));
+                    return Some(Ok(Token::new(TokenKind::STRING, str_rep, c_at, line, column)));
                 },
                 Started::Number => {
                     #[derive(PartialEq)]
@@ -167,13 +296,10 @@ impl<'de> Iterator for Lexer<'de>
                     let mut state = State::BeforeDot;
                     let mut i = 0;
                     
-                    while let Some(c) = chars.next() {
+                    for c in chars.by_ref() {
                         match c {
                             '0'..='9' | '_'  => {
-                                match state {
-                                    State::Dot => { state = State::AfterDot }
-                                    _ => {}
-                                }
+                                if state == State::Dot { state = State::AfterDot }
                             },
                             '.' => match state {
                                 State::BeforeDot => { state = State::Dot; },
@@ -191,24 +317,37 @@ impl<'de> Iterator for Lexer<'de>
                     let str_rep_normalized = str_rep.replace("_", "");
                     self.rest = &self.rest[i..];
                     self.byte += i;
-                    let num = match str_rep_normalized.parse() {
-                        Ok(n) => n,
+                    // without the decimal point it is an integer
+                    let parsed = match state {
+                        State::AfterDot => str_rep_normalized.parse().map(TokenKind::NUMBER).map_err(|e| e.to_string()),
+                        // 2^63 only fits negated, it is kept as i64::MIN for the parser to check for the minus
+                        _ => match str_rep_normalized.parse::<u64>() {
+                            Ok(n) if n <= i64::MIN.unsigned_abs() => Ok(TokenKind::INTEGER(n as i64)),
+                            _ => return Some(Err(IntegerOutOfRange {
+                                src: self.whole.to_string(),
+                                literal: str_rep.to_string(),
+                                err_span: SourceSpan::from(c_at..self.byte),
+                            }.into())),
+                        },
+                    };
+                    let kind = match parsed {
+                        Ok(kind) => kind,
                         Err(err) => {
                             return Some(Err(miette::miette! {
                                 labels = vec![
-                                    LabeledSpan::at(self.byte - i..self.byte, "here")
+                                    LabeledSpan::at(c_at..self.byte, "here")
                                 ],
-                                "Failed to parse number '{str_rep}' in input\n error: {err:?}"
+                                "Failed to parse number '{str_rep}' in input\n error: {err}"
                             }.with_source_code(self.whole.to_string())));
                         },
                     };
-                    return Some(Ok(Token::new(TokenKind::NUMBER(num), str_rep, c_at)))
+                    return Some(Ok(Token::new(kind, str_rep, c_at, line, column)))
 
                 },
                 Started::Ident =>  {
                     let first_non_ident = c_onwards
                         .find(|c| !matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_'))
-                        .unwrap_or_else(|| c_onwards.len());
+                        .unwrap_or(c_onwards.len());
                     let str_rep = &c_onwards[..first_non_ident];
                     let extra_bytes = str_rep.len() - c.len_utf8();
                     self.byte += extra_bytes;
@@ -230,22 +369,48 @@ impl<'de> Iterator for Lexer<'de>
                         "print" => TokenKind::PRINT,
                         "break" => TokenKind::BREAK,
                         "continue" => TokenKind::CONTINUE,
+                        "class" => TokenKind::CLASS,
+                        "this" => TokenKind::THIS,
+                        "super" => TokenKind::SUPER,
+                        "import" => TokenKind::IMPORT,
+                        "div" => TokenKind::DIV,
                         _ => TokenKind::IDENT
                     };
-                    return Some(Ok(Token::new(kind, str_rep, c_at)));
+                    return Some(Ok(Token::new(kind, str_rep, c_at, line, column)));
                 },
                 Started::Comment => {
-                    if let Some(this_c) = chars.next() {
-                        if this_c == '/' {
+                    match chars.next() {
+                        Some('/') => {
                             let n_line = c_onwards
-                                .find(|c| matches!(c, '\n'))
-                                .unwrap_or_else(|| c_onwards.len());
+                                .find('\n')
+                                .unwrap_or(c_onwards.len());
+                            let text = &c_onwards[..n_line];
                             self.rest = &c_onwards[n_line..];
                             self.byte += c_onwards.len() - self.rest.len() - c.len_utf8();
+                            self.push_trivia(TriviaKind::Comment, c_at, self.byte);
+                            // `////` and longer are ordinary comments
+                            if let Some(doc) = text.strip_prefix("///").filter(|d| !d.starts_with('/')) {
+                                let doc = doc.trim_end();
+                                self.doc.push(doc.strip_prefix(' ').unwrap_or(doc));
+                            }
                             continue;
-                        }
+                        },
+                        Some('*') => {
+                            let Some(len) = block_comment_len(c_onwards) else {
+                                self.rest = "";
+                                self.byte = self.whole.len();
+                                return Some(Err(UnterminatedCommentError {
+                                    src: self.whole.to_string(),
+                                    err_span: SourceSpan::from(c_at..c_at + 2),
+                                }.into()))
+                            };
+                            self.rest = &c_onwards[len..];
+                            self.byte = c_at + len;
+                            self.push_trivia(TriviaKind::Comment, c_at, self.byte);
+                            continue;
+                        },
+                        _ => return just(TokenKind::SLASH),
                     }
-                    return just(TokenKind::SLASH);
                 }
                 Started::IfEqualElse(yes, no) => {
                     self.rest = self.rest.trim_start();
@@ -257,13 +422,38 @@ impl<'de> Iterator for Lexer<'de>
                         let span = &c_onwards[..c.len_utf8() + trimmed + 1];
                         self.rest = &self.rest[1..];
                         self.byte += 1;
-                        return Some(Ok(Token::new(yes, span, c_at)));
+                        return Some(Ok(Token::new(yes, span, c_at, line, column)));
                     } else {
-                        return Some(Ok(Token::new(no, c_str, c_at)))
+                        // whitespace after the single character token isn't part of it
+                        self.rest = &c_onwards[c.len_utf8()..];
+                        self.byte = c_at + c.len_utf8();
+                        return Some(Ok(Token::new(no, c_str, c_at, line, column)))
                     }
                 }
             }
             // todo!() // unreachable
         }
     }
-}
\ No newline at end of file
+}
+/// ## Length of the block comment
+/// `src` starts with the opening `/*`, nested comments have to be closed
+/// before the outer one, returns `None` for unterminated comment
+fn block_comment_len(src: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < src.len() {
+        if src[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if src[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Some(i)
+            }
+        } else {
+            i += src[i..].chars().next()?.len_utf8();
+        }
+    }
+    None
+}
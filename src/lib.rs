@@ -1,7 +1,18 @@
 pub mod lexer;
 pub mod token;
+pub mod source_map;
 pub mod error;
 pub mod exptree;
 pub mod parser;
 pub mod context;
-pub mod evaluator;
\ No newline at end of file
+pub mod evaluator;
+pub mod resolver;
+pub mod vm;
+pub mod ir;
+pub mod backend;
+pub mod loader;
+pub mod formatter;
+pub mod docgen;
+pub mod lsp;
+pub mod repl;
+pub mod stdlib;
@@ -0,0 +1,104 @@
+use std::{collections::{BTreeMap, HashMap}, fs, path::{Path, PathBuf}};
+
+use miette::{Context, Error, IntoDiagnostic, NamedSource, SourceSpan};
+
+use crate::{backend::Backend, context::CtxTree, error::ImportError, evaluator::Value, exptree::ExprTree, parser::Parser, resolver::Resolver, stdlib};
+
+/// ## Module loader
+/// Runs the program stored in the file together with the modules it imports.
+/// Every module runs once in its own context no matter how many files import it,
+/// its top-level functions and variables become the entries of its namespace.
+///
+/// Imported paths are relative to the importing file, sources of all the loaded
+/// files live for the rest of the program
+pub struct Loader {
+    backend: Backend,
+    /// namespaces of the modules that already ran by their canonical path
+    modules: HashMap<PathBuf, Value<'static>>,
+    /// canonical and displayed paths of the files being loaded, the entry file first
+    loading: Vec<(PathBuf, String)>,
+}
+
+impl Loader {
+    pub fn new(backend: Backend) -> Self {
+        Self { backend, modules: HashMap::new(), loading: Vec::new() }
+    }
+
+    /// ## Runs the program from the file
+    /// Returns the value of its last statement, errors carry the source code
+    /// of the file they were found in
+    pub fn run(&mut self, path: &Path) -> Result<Value<'static>, Error> {
+        let source = fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("reading '{}' failed", path.display()))?;
+        let canonical = path.canonicalize()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("reading '{}' failed", path.display()))?;
+        let (_, _, value) = self.execute(path, canonical, source)?;
+        Ok(value)
+    }
+
+    /// Runs the file that is not loading yet
+    fn execute(&mut self, path: &Path, canonical: PathBuf, source: String) -> Result<(CtxTree<'static>, Vec<ExprTree<'static>>, Value<'static>), Error> {
+        self.loading.push((canonical, path.display().to_string()));
+        let res = self.execute_loading(path, source);
+        self.loading.pop();
+        res
+    }
+
+    fn execute_loading(&mut self, path: &Path, source: String) -> Result<(CtxTree<'static>, Vec<ExprTree<'static>>, Value<'static>), Error> {
+        // functions of the module borrow its source for the rest of the program
+        let source: &'static str = String::leak(source);
+        let named = || NamedSource::new(path.display().to_string(), source.to_string());
+
+        let mut stmts = Parser::new(source).parse()?;
+        let ctx = CtxTree::new();
+        stdlib::install(&ctx);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for stmt in &stmts {
+            if let ExprTree::Import(import) = stmt {
+                let namespace = self.import(&dir.join(import.path()), import.span(), named)?;
+                ctx.link(import.path(), namespace);
+            }
+        }
+        Resolver::with_globals(&ctx).resolve(&mut stmts)
+            .map_err(|e| e.with_source_code(named()))?;
+        let value = self.backend.run(&ctx, &stmts)
+            .map_err(|e| e.with_source_code(named()))?;
+        Ok((ctx, stmts, value))
+    }
+
+    /// ## Namespace of the imported module
+    /// Runs the module unless it already ran, errors of the import itself
+    /// are reported in the importing file with the `importer` source
+    fn import(&mut self, path: &Path, span: SourceSpan, importer: impl Fn() -> NamedSource<String>) -> Result<Value<'static>, Error> {
+        let unreadable = |e: std::io::Error| ImportError::Unreadable {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+            span
+        };
+        let canonical = path.canonicalize()
+            .map_err(|e| Error::from(unreadable(e)).with_source_code(importer()))?;
+        if let Some(namespace) = self.modules.get(&canonical) {
+            return Ok(namespace.clone())
+        }
+        if let Some(at) = self.loading.iter().position(|(loading, _)| *loading == canonical) {
+            let mut cycle: Vec<String> = self.loading[at..].iter().map(|(_, shown)| shown.clone()).collect();
+            cycle.push(path.display().to_string());
+            let cycle = cycle.join(" -> ");
+            return Err(Error::from(ImportError::Cycle { cycle, span }).with_source_code(importer()))
+        }
+        let source = fs::read_to_string(path)
+            .map_err(|e| Error::from(unreadable(e)).with_source_code(importer()))?;
+
+        let (ctx, stmts, _) = self.execute(path, canonical.clone(), source)?;
+        let entries: BTreeMap<_, _> = stmts.iter()
+            .filter(|stmt| !matches!(stmt, ExprTree::Import(_)))
+            .filter_map(|stmt| stmt.declared_name())
+            .filter_map(|name| Some((name.to_string(), ctx.search(name)?)))
+            .collect();
+        let namespace = Value::map(entries);
+        self.modules.insert(canonical, namespace.clone());
+        Ok(namespace)
+    }
+}
@@ -0,0 +1,149 @@
+use miette::{Diagnostic, SourceSpan};
+
+use crate::{context::CtxTree, evaluator::Value, parser::Parser, resolver::{Reference, Resolver}, stdlib};
+
+use super::{Outline, Symbol};
+
+/// ## Reported problem
+/// Message of the error together with all the contexts it was wrapped in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub span: SourceSpan,
+    pub message: String,
+}
+
+/// ## Analysis of the document
+/// Document is parsed and resolved against the standard library,
+/// declarations and resolved references are kept for the editor's requests
+pub struct Analysis<'de> {
+    src: &'de str,
+    pub problems: Vec<Problem>,
+    pub symbols: Vec<Symbol<'de>>,
+    references: Vec<Reference<'de>>,
+    globals: CtxTree<'de>,
+}
+
+impl<'de> Analysis<'de> {
+    pub fn new(src: &'de str) -> Self {
+        let globals = CtxTree::new();
+        stdlib::install(&globals);
+        let mut analysis = Self { src, problems: Vec::new(), symbols: Vec::new(), references: Vec::new(), globals };
+
+        let mut stmts = match Parser::new(src).parse() {
+            Ok(stmts) => stmts,
+            Err(e) => {
+                analysis.report(&*e);
+                return analysis
+            }
+        };
+        // references before the resolver's error are still usable
+        let mut resolver = Resolver::with_globals(&analysis.globals).with_references();
+        if let Err(e) = resolver.resolve(&mut stmts) {
+            analysis.report(&*e);
+        }
+        analysis.references = resolver.references().to_vec();
+        for stmt in &stmts {
+            stmt.outline(&mut analysis.symbols);
+        }
+        analysis
+    }
+
+    /// Reports the error, errors reported together are reported one by one
+    fn report(&mut self, e: &dyn Diagnostic) {
+        if let Some(related) = e.related() {
+            for e in related {
+                self.report(e);
+            }
+            return
+        }
+        let span = e.labels()
+            .and_then(|mut labels| labels.next())
+            .map_or(SourceSpan::from(self.src.len()..self.src.len()), |label| *label.inner());
+        // innermost error first, followed by the contexts it was found in
+        let mut chain: Vec<String> = std::iter::successors(Some(e as &dyn std::error::Error), |e| e.source())
+            .map(|e| e.to_string())
+            .collect();
+        chain.reverse();
+        let mut message = chain.join("\n");
+        if let Some(help) = e.help() {
+            message.push_str(&format!("\nhelp: {help}"));
+        }
+        self.problems.push(Problem { span, message });
+    }
+
+    /// ## Declaration of the name at the offset
+    /// Works for both uses of the variable and the names in declarations,
+    /// returns the span of the name in the declaration
+    pub fn definition(&self, offset: usize) -> Option<SourceSpan> {
+        self.declaration_at(offset).map(|name| self.span_of(name))
+    }
+
+    /// ## Description of the name at the offset
+    /// Functions are described by their parameters
+    pub fn hover(&self, offset: usize) -> Option<String> {
+        let Some(name) = self.declaration_at(offset) else {
+            // globals declared outside of the program, like the standard library
+            let reference = self.references.iter().find(|r| contains(r.span, offset))?;
+            let name = &self.src[reference.span.offset()..reference.span.offset() + reference.span.len()];
+            return match self.globals.search(name)? {
+                Value::NativeFunction(fun) => Some(match fun.arity() {
+                    Some(arity) => format!("fun {name}({})", vec!["_"; arity].join(", ")),
+                    None => format!("fun {name}(...)"),
+                }),
+                v => Some(format!("var {name}: {}", v.type_name())),
+            }
+        };
+        Some(match find(&self.symbols, name) {
+            Some(symbol) => symbol.signature(),
+            // only parameters are declared without a symbol
+            None => format!("parameter {name}"),
+        })
+    }
+
+    /// ## Doc comment of the name at the offset
+    /// Lines of the comment are joined, `None` when the declaration isn't documented
+    pub fn doc(&self, offset: usize) -> Option<String> {
+        let symbol = find(&self.symbols, self.declaration_at(offset)?)?;
+        (!symbol.doc.is_empty()).then(|| symbol.doc.join("\n"))
+    }
+
+    /// Name in the declaration of the variable at the offset
+    fn declaration_at(&self, offset: usize) -> Option<&'de str> {
+        if let Some(reference) = self.references.iter().find(|r| contains(r.span, offset)) {
+            return reference.declaration
+        }
+        declarations(&self.symbols)
+            .into_iter()
+            .find(|name| contains(self.span_of(name), offset))
+    }
+
+    /// ## Span of the name
+    /// `name` has to be a slice of the analysed source
+    pub fn span_of(&self, name: &str) -> SourceSpan {
+        let offset = name.as_ptr() as usize - self.src.as_ptr() as usize;
+        debug_assert!(offset + name.len() <= self.src.len(), "name is a slice of the source");
+        SourceSpan::from(offset..offset + name.len())
+    }
+}
+
+/// whether the offset is within the span or right at its end
+fn contains(span: SourceSpan, offset: usize) -> bool {
+    span.offset() <= offset && offset <= span.offset() + span.len()
+}
+
+/// Symbol declared by the name, names are compared as slices of the source
+fn find<'a, 'de>(symbols: &'a [Symbol<'de>], name: &str) -> Option<&'a Symbol<'de>> {
+    symbols.iter().find_map(|s| {
+        if std::ptr::eq(s.name, name) {
+            return Some(s)
+        }
+        find(&s.children, name)
+    })
+}
+
+/// All declared names, including parameters
+fn declarations<'de>(symbols: &[Symbol<'de>]) -> Vec<&'de str> {
+    symbols.iter()
+        .flat_map(|s| std::iter::once(s.name).chain(s.params.iter().copied()).chain(declarations(&s.children)))
+        .collect()
+}
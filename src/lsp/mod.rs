@@ -0,0 +1,211 @@
+use std::{collections::HashMap, io::{self, BufRead, Write}};
+
+use miette::SourceSpan;
+use serde_json::{json, Value};
+
+use crate::source_map::SourceMap;
+
+pub mod analysis;
+pub mod outline;
+
+pub use analysis::*;
+pub use outline::*;
+
+/// ## Language server
+/// Speaks the Language Server Protocol over the given streams, documents are
+/// synchronized as a whole and analysed again on every request
+#[derive(Default)]
+pub struct Server {
+    /// text of the open documents by their uri
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ## Serves the client
+    /// Runs until the client sends `exit` or closes the input,
+    /// returns whether the server was shut down properly before
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<bool> {
+        while let Some(message) = read_message(&mut input)? {
+            if message["method"] == "exit" {
+                break
+            }
+            for reply in self.handle(&message) {
+                write_message(&mut output, &reply)?;
+            }
+        }
+        Ok(self.shutdown)
+    }
+
+    /// ## Handles single message
+    /// Returns the response to the request and notifications for the client
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notify(method, params)
+        };
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                },
+                "serverInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            },
+            "textDocument/documentSymbol" => self.with_document(params, |map, analysis, _| {
+                Some(analysis.symbols.iter().map(|s| symbol(map, analysis, s)).collect())
+            }),
+            "textDocument/definition" => self.with_document(params, |map, analysis, offset| {
+                let span = analysis.definition(offset?)?;
+                Some(json!({ "uri": params["textDocument"]["uri"], "range": range(map, span) }))
+            }),
+            "textDocument/hover" => self.with_document(params, |_, analysis, offset| {
+                let signature = analysis.hover(offset?)?;
+                let mut value = format!("```\n{signature}\n```");
+                if let Some(doc) = analysis.doc(offset?) {
+                    value.push_str(&format!("\n\n{doc}"));
+                }
+                Some(json!({ "contents": { "kind": "markdown", "value": value } }))
+            }),
+            _ => return vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Unknown method '{method}'") },
+            })],
+        };
+        vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+            },
+            "textDocument/didChange" => {
+                // whole document is sent with every change
+                let Some(text) = params["contentChanges"].as_array().and_then(|c| c.last()) else {
+                    return Vec::new()
+                };
+                self.documents.insert(uri.clone(), text["text"].as_str().unwrap_or_default().to_string());
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![diagnostics(&uri, Vec::new())]
+            },
+            _ => return Vec::new(),
+        }
+        let src = &self.documents[&uri];
+        let (map, analysis) = (SourceMap::new(src), Analysis::new(src));
+        let problems = analysis.problems.iter()
+            .map(|p| json!({ "range": range(&map, p.span), "severity": 1, "source": "lang", "message": p.message }))
+            .collect();
+        vec![diagnostics(&uri, problems)]
+    }
+
+    /// Analyses the document of the request, position of the request is given as offset,
+    /// missing result is `null`
+    fn with_document(&self, params: &Value, f: impl FnOnce(&SourceMap, &Analysis, Option<usize>) -> Option<Value>) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(src) = self.documents.get(uri) else {
+            return Value::Null
+        };
+        let (map, analysis) = (SourceMap::new(src), Analysis::new(src));
+        f(&map, &analysis, offset(&map, &params["position"])).unwrap_or_default()
+    }
+}
+
+fn diagnostics(uri: &str, problems: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": problems },
+    })
+}
+
+fn symbol(map: &SourceMap, analysis: &Analysis, symbol: &Symbol) -> Value {
+    let (kind, detail) = match symbol.kind {
+        SymbolKind::Function => (12, symbol.signature()),
+        SymbolKind::Variable => (13, String::new()),
+        SymbolKind::Class => (5, String::new()),
+        SymbolKind::Method => (6, symbol.signature()),
+        SymbolKind::Module => (2, String::new()),
+    };
+    json!({
+        "name": symbol.name,
+        "detail": detail,
+        "kind": kind,
+        "range": range(map, symbol.span),
+        "selectionRange": range(map, analysis.span_of(symbol.name)),
+        "children": symbol.children.iter().map(|s| self::symbol(map, analysis, s)).collect::<Vec<_>>(),
+    })
+}
+
+/// ## Position of the offset
+/// Lines start at 0 and characters are counted in UTF-16 code units
+fn position(map: &SourceMap, offset: usize) -> Value {
+    let (line, column) = map.line_col(offset);
+    let text = map.line(line).unwrap_or_default();
+    let character: usize = text.chars().take(column - 1).map(char::len_utf16).sum();
+    json!({ "line": line - 1, "character": character })
+}
+
+fn range(map: &SourceMap, span: SourceSpan) -> Value {
+    json!({ "start": position(map, span.offset()), "end": position(map, span.offset() + span.len()) })
+}
+
+/// Offset of the position, see `position`
+fn offset(map: &SourceMap, position: &Value) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize + 1;
+    let character = position["character"].as_u64()? as usize;
+    let mut units = 0;
+    let column = map.line(line)?.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= character
+        })
+        .count();
+    map.offset(line, column + 1)
+}
+
+/// ## Reads the message
+/// Messages are json preceded by the `Content-Length` header,
+/// returns `None` at the end of the input
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None)
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(io::Error::from)
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
@@ -0,0 +1,49 @@
+use std::rc::Rc;
+use miette::SourceSpan;
+
+/// ## Outlined node
+/// Implemented by every node of the expression tree, collects
+/// the declarations made within the node
+pub trait Outline<'de> {
+    fn outline(&self, symbols: &mut Vec<Symbol<'de>>);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+    Class,
+    Method,
+    /// namespace of the imported module
+    Module,
+}
+
+/// ## Declared symbol
+/// `name` and `params` are slices of the outlined source,
+/// declarations nested in the symbol are its children
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol<'de> {
+    pub name: &'de str,
+    pub kind: SymbolKind,
+    /// whole declaration
+    pub span: SourceSpan,
+    /// parameters of the function
+    pub params: Vec<&'de str>,
+    /// lines of the doc comment
+    pub doc: Rc<[&'de str]>,
+    pub children: Vec<Symbol<'de>>,
+}
+
+impl<'de> Symbol<'de> {
+    /// ## Signature of the symbol
+    /// Shown when hovering over the symbol
+    pub fn signature(&self) -> String {
+        match self.kind {
+            SymbolKind::Function => format!("fun {}({})", self.name, self.params.join(", ")),
+            SymbolKind::Variable => format!("var {}", self.name),
+            SymbolKind::Class => format!("class {}", self.name),
+            SymbolKind::Method => format!("{}({})", self.name, self.params.join(", ")),
+            SymbolKind::Module => format!("module {}", self.name),
+        }
+    }
+}
@@ -1,8 +1,8 @@
-use std::{fs, path::PathBuf};
+use std::{fs, io::{self, BufRead, Write}, path::PathBuf};
 
 use clap::{Parser, Subcommand};
-use lang_interpreter::{context::CtxTree, evaluator::Eval, lexer::Lexer, parser::Parser as MyParser};
-use miette::{Context, IntoDiagnostic};
+use lang_interpreter::{backend::Backend, context::CtxTree, docgen::{self, DocFormat, Module}, error::SyntaxErrors, formatter, ir::Builder, lexer::Lexer, loader::Loader, lsp::Server, parser::Parser as MyParser, repl::{Repl, Sources}, resolver::Resolver, stdlib};
+use miette::{Context, IntoDiagnostic, NamedSource};
 
 #[derive(Debug, Subcommand)]
 enum Commands {
@@ -16,16 +16,48 @@ enum Commands {
         /// Path to a file that u want to parse
         filename: PathBuf
     },
+    /// Formats an input file in place
+    Fmt {
+        /// Path to a file that u want to format
+        filename: PathBuf,
+        /// Only checks whether the file is formatted, exits with an error if it isn't
+        #[arg(long)]
+        check: bool
+    },
+    /// Prints the documentation of the functions declared in the input files
+    Doc {
+        /// Paths to the files that u want to document
+        #[arg(required = true)]
+        filenames: Vec<PathBuf>,
+        /// Format of the generated page
+        #[arg(long, value_enum, default_value_t)]
+        format: DocFormat
+    },
+    /// Prints the lowered intermediate representation of an input file
+    Ir {
+        /// Path to a file that u want to lower
+        filename: PathBuf
+    },
     /// Evaluates single expression
     Eval {
         /// Path to a file that u want to evaluate
-        filename: PathBuf
+        filename: PathBuf,
+        /// Backend that executes the code
+        #[arg(long, value_enum, default_value_t)]
+        backend: Backend
     },
     /// Runs the code
     Run {
         /// Path to a file that u want to run
-        filename: PathBuf
-    }
+        filename: PathBuf,
+        /// Backend that executes the code
+        #[arg(long, value_enum, default_value_t)]
+        backend: Backend
+    },
+    /// Starts an interactive session
+    Repl,
+    /// Starts the language server on stdin and stdout
+    Lsp
 }
 
 #[derive(Parser, Debug)]
@@ -45,11 +77,15 @@ fn main() -> miette::Result<()> {
                 .wrap_err_with(|| format!("reading '{}' failed", filename.display()))?;
 
             let lexer = Lexer::new(&file_contents);
+            let mut errors = Vec::new();
             for token in lexer {
-                let token = token?;
-                println!("{token}");
+                match token {
+                    Ok(token) => println!("{token}"),
+                    Err(e) => errors.push(e),
+                }
             }
             println!("EOF");
+            SyntaxErrors::check(&file_contents, errors)?;
         },
         Commands::Parse { filename } => {
             let file_contents = fs::read_to_string(&filename)
@@ -64,27 +100,97 @@ fn main() -> miette::Result<()> {
                 .join("\n");
             println!("{}", parsed_str)
         },
-        Commands::Eval { filename} => {
+        Commands::Fmt { filename, check } => {
             let file_contents = fs::read_to_string(&filename)
                 .into_diagnostic()
                 .wrap_err_with(|| format!("reading '{}' failed", filename.display()))?;
-            let mut parser = MyParser::new(&file_contents);
-            let expr_tree = parser.parse_expression_within(0)?;
-            let ctx = CtxTree::new();
-            let val = expr_tree.eval(&ctx)?;
-            println!("evaluation completed");
-            println!("result: {val}");
+            let formatted = formatter::format(&file_contents)
+                .map_err(|e| e.with_source_code(NamedSource::new(filename.display().to_string(), file_contents.clone())))?;
+            if check {
+                if formatted != file_contents {
+                    return Err(miette::miette!("'{}' is not formatted", filename.display()))
+                }
+            } else if formatted != file_contents {
+                fs::write(&filename, formatted)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("writing '{}' failed", filename.display()))?;
+            }
+        },
+        Commands::Doc { filenames, format } => {
+            let mut sources = Vec::new();
+            for filename in &filenames {
+                let file_contents = fs::read_to_string(filename)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("reading '{}' failed", filename.display()))?;
+                sources.push(file_contents);
+            }
+            let mut modules = Vec::new();
+            for (filename, file_contents) in filenames.iter().zip(&sources) {
+                let module = Module::new(filename.display().to_string(), file_contents)
+                    .map_err(|e| e.with_source_code(NamedSource::new(filename.display().to_string(), file_contents.clone())))?;
+                modules.push(module);
+            }
+            match format {
+                DocFormat::Markdown => print!("{}", docgen::markdown(&modules)),
+                DocFormat::Html => print!("{}", docgen::html(&modules)),
+            }
         },
-        Commands::Run { filename } => {
+        Commands::Ir { filename } => {
             let file_contents = fs::read_to_string(&filename)
                 .into_diagnostic()
                 .wrap_err_with(|| format!("reading '{}' failed", filename.display()))?;
             let parser = MyParser::new(&file_contents);
-            let expr_list = parser.parse()?;
+            let mut expr_list = parser.parse()?;
+            let ctx = CtxTree::new();
+            stdlib::install(&ctx);
+            Resolver::with_globals(&ctx).resolve(&mut expr_list)
+                .map_err(|e| e.with_source_code(NamedSource::new(filename.display().to_string(), file_contents.clone())))?;
+            print!("{}", Builder::lower_script(&expr_list));
+        },
+        Commands::Eval { filename, backend } => {
+            let file_contents = fs::read_to_string(&filename)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("reading '{}' failed", filename.display()))?;
+            let mut parser = MyParser::new(&file_contents);
+            let mut expr_tree = parser.parse_expression_within(0)?;
             let ctx = CtxTree::new();
-            for expr in expr_list {
-                expr.eval(&ctx)?;
+            stdlib::install(&ctx);
+            Resolver::with_globals(&ctx).resolve(std::slice::from_mut(&mut expr_tree))
+                .map_err(|e| e.with_source_code(NamedSource::new(filename.display().to_string(), file_contents.clone())))?;
+            let val = backend.run(&ctx, std::slice::from_ref(&expr_tree))
+                .map_err(|e| e.with_source_code(NamedSource::new(filename.display().to_string(), file_contents.clone())))?;
+            println!("evaluation completed");
+            println!("result: {val}");
+        },
+        Commands::Run { filename, backend } => {
+            Loader::new(backend).run(&filename)?;
+        },
+        Commands::Lsp => {
+            let shutdown = Server::new().run(io::stdin().lock(), io::stdout().lock())
+                .into_diagnostic()
+                .wrap_err("language server failed")?;
+            // exit without shutdown request is an error
+            if !shutdown {
+                std::process::exit(1);
+            }
+        },
+        Commands::Repl => {
+            let sources = Sources::new();
+            let mut repl = Repl::new(&sources);
+            let mut lines = io::stdin().lock().lines();
+            loop {
+                print!("{}", if repl.is_pending() { "... " } else { "> " });
+                io::stdout().flush().into_diagnostic()?;
+                let Some(line) = lines.next() else { break };
+                let line = line.into_diagnostic().wrap_err("reading input failed")?;
+                match repl.feed(&line) {
+                    None => {},
+                    Some(Ok(Some(val))) => println!("{val}"),
+                    Some(Ok(None)) => {},
+                    Some(Err(e)) => eprintln!("{e:?}"),
+                }
             }
+            println!();
         },
     }
 
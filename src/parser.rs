@@ -1,31 +1,112 @@
-use miette::{Context, Error, LabeledSpan};
+use std::path::Path;
 
-use crate::{error::Eof, exptree::{Atom, ConOp, ExprTree, FnBlock, FnCall, If, Loop, Op, UOp, UnaryOp, VarDecl}, lexer::Lexer, token::{unescape, Token, TokenKind}};
+use miette::{Context, Error, LabeledSpan, SourceSpan};
+
+use crate::{error::{ControlFlowError, Eof, IntegerOutOfRange, SyntaxErrors}, exptree::{Atom, ClassDecl, ConOp, ExprTree, FnBlock, FnCall, If, Import, Index, List, Loop, LoopKind, Map, Op, SuperAccess, UOp, UnaryOp, VarDecl}, lexer::Lexer, resolver::Binding, token::{unescape, Token, TokenKind}};
 
 pub struct Parser<'de> {
     whole: &'de str,
-    lexer: Lexer<'de>
+    lexer: Lexer<'de>,
+    /// number of loops around currently parsed code (within the function)
+    loop_depth: usize,
+    /// number of functions around currently parsed code
+    fn_depth: usize,
+    /// classes around currently parsed code, `true` for the ones with a superclass
+    classes: Vec<bool>,
+    /// whether blocks recover from errors, only the whole program is parsed with recovery
+    recovering: bool,
+    /// errors the parser recovered from
+    errors: Vec<Error>,
 }
 
 impl<'de> Parser<'de> {
     pub fn new(input: &'de str) -> Self {
-        Self { whole: input, lexer: Lexer::new(input) }
+        Self { whole: input, lexer: Lexer::new(input), loop_depth: 0, fn_depth: 0, classes: Vec::new(), recovering: false, errors: Vec::new() }
     }
 
+    /// ## Parses the whole program
+    /// Parser recovers from syntax errors and continues with the next statement,
+    /// single error is returned as is, more of them are reported together.
+    ///
+    /// Imports are statements of the top level only
     pub fn parse(mut self) -> Result<Vec<ExprTree<'de>>, Error> {
+        self.recovering = true;
         let mut stmts: Vec<ExprTree<'de>> = Vec::new();
         loop {
             match self.lexer.peek() {
                 Some(Ok(Token { kind: TokenKind::SEMICOLON, ..})) => { self.lexer.next(); },
-                Some(_) => stmts.push(self.parse_statement_within()?),
+                Some(Ok(Token { kind: TokenKind::IMPORT, ..})) => match self.parse_import() {
+                    Ok(stmt) => stmts.push(stmt),
+                    Err(e) => {
+                        self.errors.push(e);
+                        self.synchronize(false);
+                    }
+                },
+                Some(_) => match self.parse_statement_within() {
+                    Ok(stmt) => stmts.push(stmt),
+                    Err(e) => {
+                        self.errors.push(e);
+                        self.synchronize(false);
+                    }
+                },
                 None => break
             }
         }
+        SyntaxErrors::check(self.whole, self.errors)?;
         Ok(stmts)
     }
 
+    /// ## Synchronizes after syntax error
+    /// Skips tokens until the end of the broken statement, that is after `;`,
+    /// after the block the statement ends with or before the keyword starting
+    /// the next statement. Within a block it stops before the closing `}`,
+    /// elsewhere the stray `}` is skipped too.
+    ///
+    /// Lexer errors found on the way are collected as well
+    fn synchronize(&mut self, in_block: bool) {
+        // the error was at the `;` ending the statement
+        if self.lexer.consumed_kind() == Some(TokenKind::SEMICOLON) {
+            return
+        }
+        let mut depth = 0;
+        loop {
+            match self.lexer.peek() {
+                None => return,
+                Some(Ok(Token { kind: TokenKind::LEFT_BRACE, ..})) => {
+                    depth += 1;
+                    self.lexer.next();
+                },
+                Some(Ok(Token { kind: TokenKind::RIGHT_BRACE, ..})) if depth > 0 => {
+                    depth -= 1;
+                    self.lexer.next();
+                    if depth == 0 {
+                        return
+                    }
+                },
+                // everything within the skipped block belongs to the broken statement
+                Some(Ok(_)) if depth > 0 => { self.lexer.next(); },
+                Some(Ok(Token { kind: TokenKind::SEMICOLON, ..})) => {
+                    self.lexer.next();
+                    return
+                },
+                Some(Ok(Token { kind: TokenKind::RIGHT_BRACE, ..})) if in_block => return,
+                Some(Ok(Token {
+                    kind: TokenKind::VAR | TokenKind::FUN | TokenKind::CLASS | TokenKind::IF | TokenKind::WHILE | TokenKind::FOR |
+                        TokenKind::RETURN | TokenKind::PRINT | TokenKind::BREAK | TokenKind::CONTINUE | TokenKind::IMPORT,
+                    ..
+                })) => return,
+                Some(Ok(_)) => { self.lexer.next(); },
+                Some(Err(_)) => {
+                    let err = self.lexer.next().expect("peeked above").expect_err("peeked above");
+                    self.errors.push(err);
+                },
+            }
+        }
+    }
+
     /// ## Parses statement
-    /// Parses: `return`, `print`, `break`, `continue`, `var`, `fun`, `if`, `while`, `for` and `block` as statements.
+    /// Parses: `return`, `print`, `break`, `continue`, `var`, `fun`, `class`, `if`, `while`, `for` and `block` as statements,
+    /// `import` is an error here as only `parse` accepts it on the top level.
     /// 
     /// Everything else is parsed as an expression
     /// 
@@ -34,35 +115,55 @@ impl<'de> Parser<'de> {
         let stmt_exp = match self.lexer.peek() {
             Some(Ok(Token { kind: TokenKind::RETURN, ..})) => {
                 self.lexer.next(); // we advance lexer
+                let start = self.lexer.consumed_start();
+                if self.fn_depth == 0 {
+                    return Err(self.control_flow_error("return", "function"))
+                }
                 if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::SEMICOLON, ..}))) {
-                    ExprTree::UnaryOp(UnaryOp::new(UOp::Return, Box::new(ExprTree::Atom(Atom::Nil))))
+                    let span = self.span_from(start);
+                    ExprTree::UnaryOp(UnaryOp::new(UOp::Return, Box::new(ExprTree::Atom(Atom::Nil, span)), span))
                 } else {
-                    ExprTree::UnaryOp(UnaryOp::new(UOp::Return, 
-                        Box::new(self.parse_expression_within(0).wrap_err("in statement")?)))
+                    let rhs = self.parse_expression_within(0).wrap_err("in statement")?;
+                    ExprTree::UnaryOp(UnaryOp::new(UOp::Return, Box::new(rhs), self.span_from(start)))
                 }
             },
             Some(Ok(Token { kind: TokenKind::PRINT, ..})) => {
                 self.lexer.next(); // we advance lexer
-                ExprTree::UnaryOp(UnaryOp::new(UOp::Print, 
-                    Box::new(self.parse_expression_within(0).wrap_err("in statement")?)))
+                let start = self.lexer.consumed_start();
+                let rhs = self.parse_expression_within(0).wrap_err("in statement")?;
+                ExprTree::UnaryOp(UnaryOp::new(UOp::Print, Box::new(rhs), self.span_from(start)))
             },
             Some(Ok(Token { kind: TokenKind::BREAK, ..})) => {
                 self.lexer.next(); // we advance lexer
+                let start = self.lexer.consumed_start();
+                if self.loop_depth == 0 {
+                    return Err(self.control_flow_error("break", "loop"))
+                }
                 if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::SEMICOLON, ..}))) {
-                    ExprTree::UnaryOp(UnaryOp::new(UOp::Break, Box::new(ExprTree::Atom(Atom::Nil))))
+                    let span = self.span_from(start);
+                    ExprTree::UnaryOp(UnaryOp::new(UOp::Break, Box::new(ExprTree::Atom(Atom::Nil, span)), span))
                 } else {
-                    ExprTree::UnaryOp(UnaryOp::new(UOp::Break, 
-                        Box::new(self.parse_expression_within(0).wrap_err("in statement")?)))
+                    let rhs = self.parse_expression_within(0).wrap_err("in statement")?;
+                    ExprTree::UnaryOp(UnaryOp::new(UOp::Break, Box::new(rhs), self.span_from(start)))
                 }
             },
             Some(Ok(Token { kind: TokenKind::CONTINUE, ..})) => {
                 self.lexer.next(); // we advance lexer
-                ExprTree::Atom(Atom::Continue)
+                if self.loop_depth == 0 {
+                    return Err(self.control_flow_error("continue", "loop"))
+                }
+                ExprTree::Atom(Atom::Continue, self.span_from(self.lexer.consumed_start()))
+            },
+            Some(Ok(Token { kind: TokenKind::IMPORT, ..})) => {
+                self.lexer.next(); // we advance lexer
+                return Err(self.control_flow_error("import", "the top level"))
             },
             Some(Ok(Token { kind: TokenKind::VAR, ..})) => 
                 self.parse_var(false).wrap_err("in statement")?,
             Some(Ok(Token { kind: TokenKind::FUN, ..})) => 
-                self.parse_fun(false).wrap_err("in statement")?,
+                self.parse_fun(false, true).wrap_err("in statement")?,
+            Some(Ok(Token { kind: TokenKind::CLASS, ..})) => 
+                self.parse_class().wrap_err("in statement")?,
             Some(Ok(Token { kind: TokenKind::IF, ..})) => 
                 self.parse_if(false).wrap_err("in statement")?,
             Some(Ok(Token { kind: TokenKind::WHILE, ..})) => 
@@ -70,9 +171,9 @@ impl<'de> Parser<'de> {
             Some(Ok(Token { kind: TokenKind::FOR, ..})) => 
                 self.parse_for(false).wrap_err("in statement")?,
             Some(Ok(Token { kind: TokenKind::LEFT_BRACE, ..})) => 
-                self.parse_block(false).wrap_err("in statement")?,
-            Some(Ok(Token { kind: TokenKind::SEMICOLON, ..})) => { // empty semicolon is statement
-                ExprTree::Atom(Atom::Nil)
+                self.parse_block(false, true).wrap_err("in statement")?,
+            Some(Ok(Token { kind: TokenKind::SEMICOLON, offset, ..})) => { // empty semicolon is statement
+                ExprTree::Atom(Atom::Nil, SourceSpan::from(*offset..*offset + 1))
             },
             Some(Err(_)) => return Err(self.lexer.next().unwrap().err().unwrap()),
             None => return Err(Eof.into()),
@@ -90,35 +191,63 @@ impl<'de> Parser<'de> {
     /// 
     /// ## Arguments
     /// - `min_bp` - minimal binding power if an operator has 
-    /// left binding power lower than `min_bp` it will be aplied later
+    ///   left binding power lower than `min_bp` it will be aplied later
     /// 
     /// ## Returns
     /// - ExpressionTree
     /// - miette error if any occured durring parsing
-    /// **(returning an error stops parsing right away)**
+    ///   **(returning an error stops parsing right away)**
     pub fn parse_expression_within(&mut self, min_bp: u8) -> Result<ExprTree<'de>, Error> {
         let lhs = self.lexer.next_or_error().wrap_err("on left-hand side")?;
+        let start = lhs.offset;
+        let lhs_span = SourceSpan::from(lhs.offset..lhs.offset + lhs.origin.len());
         let mut lhs = match lhs {
             // Atoms
-            Token { kind: TokenKind::STRING, origin, ..} => ExprTree::Atom(Atom::String(unescape(origin))),
-            Token { kind: TokenKind::NUMBER(n), .. } => ExprTree::Atom(Atom::Number(n)),
-            Token { kind: TokenKind::NIL, .. } => ExprTree::Atom(Atom::Nil),
-            Token { kind: TokenKind::TRUE, .. } => ExprTree::Atom(Atom::Bool(true)),
-            Token { kind: TokenKind::FALSE, .. } => ExprTree::Atom(Atom::Bool(false)),
-            Token { kind: TokenKind::CONTINUE, .. } => ExprTree::Atom(Atom::Continue),
-            Token { kind: TokenKind::IDENT, origin, .. } => self.parse_ident(origin)?,
+            Token { kind: TokenKind::STRING, origin, ..} => ExprTree::Atom(Atom::String(unescape(origin)), lhs_span),
+            Token { kind: TokenKind::NUMBER(n), .. } => ExprTree::Atom(Atom::Number(n), lhs_span),
+            // 2^63 is lexed as i64::MIN and is only valid negated
+            Token { kind: TokenKind::INTEGER(i64::MIN), origin, .. } => return Err(IntegerOutOfRange {
+                src: self.whole.to_string(),
+                literal: origin.to_string(),
+                err_span: lhs_span,
+            }.into()),
+            Token { kind: TokenKind::INTEGER(i), .. } => ExprTree::Atom(Atom::Integer(i), lhs_span),
+            Token { kind: TokenKind::NIL, .. } => ExprTree::Atom(Atom::Nil, lhs_span),
+            Token { kind: TokenKind::TRUE, .. } => ExprTree::Atom(Atom::Bool(true), lhs_span),
+            Token { kind: TokenKind::FALSE, .. } => ExprTree::Atom(Atom::Bool(false), lhs_span),
+            Token { kind: TokenKind::CONTINUE, .. } if self.loop_depth == 0 => 
+                return Err(self.control_flow_error("continue", "loop")),
+            Token { kind: TokenKind::CONTINUE, .. } => ExprTree::Atom(Atom::Continue, lhs_span),
+            Token { kind: TokenKind::IDENT, origin, .. } => ExprTree::Atom(Atom::Ident(origin, Binding::Global), lhs_span),
+            Token { kind: TokenKind::THIS, .. } if self.classes.is_empty() => 
+                return Err(self.control_flow_error("this", "class")),
+            Token { kind: TokenKind::THIS, .. } => ExprTree::Atom(Atom::This(Binding::Global), lhs_span),
+            Token { kind: TokenKind::SUPER, .. } if self.classes.last() != Some(&true) => 
+                return Err(self.control_flow_error("super", "subclass")),
+            Token { kind: TokenKind::SUPER, .. } => {
+                self.lexer.expect_next(TokenKind::DOT, "expected .").wrap_err("after super")?;
+                let method = self.lexer.expect_next(TokenKind::IDENT, "expected method name").wrap_err("after super")?;
+                ExprTree::Super(SuperAccess::new(method.origin, self.span_from(start)))
+            },
+            // the smallest integer has no positive counterpart so it is negated as a literal
+            Token { kind: TokenKind::MINUS, .. } 
+                if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::INTEGER(i64::MIN), .. }))) => {
+                self.lexer.next();
+                ExprTree::Atom(Atom::Integer(i64::MIN), self.span_from(start))
+            },
             // prefix/unary
-            Token { kind: TokenKind::BANG | TokenKind::MINUS, ..} => {
+            Token { kind: TokenKind::BANG | TokenKind::MINUS | TokenKind::TILDE, ..} => {
                 let uop = match lhs.kind {
                     TokenKind::BANG => UOp::Bang,
                     TokenKind::MINUS => UOp::Minus,
+                    TokenKind::TILDE => UOp::Tilde,
                     _ => unreachable!("checked above")
                 };
                 let (_, r_bp) = Self::prefix_binding_power(uop);
                 let rhs = self
                     .parse_expression_within(r_bp)
                     .wrap_err("in unary expression rhs")?;
-                ExprTree::UnaryOp(UnaryOp::new(uop, Box::new(rhs)))
+                ExprTree::UnaryOp(UnaryOp::new(uop, Box::new(rhs), self.span_from(start)))
             },
             Token { kind: TokenKind::LEFT_PAREN, .. } => {
                 let inner = self
@@ -127,19 +256,21 @@ impl<'de> Parser<'de> {
                 self.lexer
                     .expect_next(TokenKind::RIGHT_PAREN, "Unexpected bracketed expression terminator")
                     .wrap_err("after bracketed expression")?;
-                ExprTree::UnaryOp(UnaryOp::new(UOp::Group, Box::new(inner)))
+                ExprTree::UnaryOp(UnaryOp::new(UOp::Group, Box::new(inner), self.span_from(start)))
             },
+            Token { kind: TokenKind::LEFT_SQUARE_BRACKET, .. } => self.parse_list()?,
             // special
-            Token { kind: TokenKind::LEFT_BRACE, .. } => self.parse_block(true)?,
+            Token { kind: TokenKind::LEFT_BRACE, .. } => self.parse_block(true, true)?,
             Token { kind: TokenKind::FOR, .. } => self.parse_for(true)?,
             Token { kind: TokenKind::IF, .. } => self.parse_if(true)?,
             Token { kind: TokenKind::WHILE, .. } => self.parse_while(true)?,
+            Token { kind: TokenKind::FUN, .. } => self.parse_fun(true, false)?,
 
             token => return Err(miette::miette! {
                 labels = vec![
                     LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
                 ],
-                help = format!("Unexpected {token:?}"),
+                help = format!("Unexpected {:?}", token.kind),
                 "Expected the left-hand side of an expression"
             }.with_source_code(self.whole.to_string()))
         };
@@ -148,7 +279,7 @@ impl<'de> Parser<'de> {
         // those operators have proper binding power
         loop {
             let op = self.lexer.peek();
-            if op.map_or(false, |op| op.is_err()) {
+            if op.is_some_and(|op| op.is_err()) {
                 return Err(self.lexer.next().expect("checked Some above").unwrap_err()).wrap_err("after left-hand side");
             }
             let op = match op {
@@ -158,6 +289,16 @@ impl<'de> Parser<'de> {
                 Some(Ok(Token { kind: TokenKind::PLUS, ..})) => Op::Plus,
                 Some(Ok(Token { kind: TokenKind::STAR, ..})) => Op::Star,
                 Some(Ok(Token { kind: TokenKind::SLASH, ..})) => Op::Slash,
+                Some(Ok(Token { kind: TokenKind::DIV, ..})) => Op::Div,
+                Some(Ok(Token { kind: TokenKind::PERCENT, ..})) => Op::Percent,
+                Some(Ok(Token { kind: TokenKind::STAR_STAR, ..})) => Op::StarStar,
+
+                // bitwise
+                Some(Ok(Token { kind: TokenKind::AMPERSAND, ..})) => Op::Ampersand,
+                Some(Ok(Token { kind: TokenKind::PIPE, ..})) => Op::Pipe,
+                Some(Ok(Token { kind: TokenKind::CARET, ..})) => Op::Caret,
+                Some(Ok(Token { kind: TokenKind::LESS_LESS, ..})) => Op::LessLess,
+                Some(Ok(Token { kind: TokenKind::GREATER_GREATER, ..})) => Op::GreaterGreater,
 
                 // binary
                 Some(Ok(Token { kind: TokenKind::BANG_EQUAL, ..})) => Op::BangEqual,
@@ -172,29 +313,57 @@ impl<'de> Parser<'de> {
                 // assigment
                 Some(Ok(Token { kind: TokenKind::EQUAL, ..})) => Op::Equal,
 
+                // postfix
+                Some(Ok(Token { kind: TokenKind::LEFT_SQUARE_BRACKET, ..})) => Op::Index,
+                Some(Ok(Token { kind: TokenKind::LEFT_PAREN, ..})) => Op::Call,
+                Some(Ok(Token { kind: TokenKind::DOT, ..})) => Op::Dot,
+
                 // ending
                 Some(Ok(Token { kind: TokenKind::RIGHT_PAREN | TokenKind::RIGHT_BRACE | 
-                    TokenKind::SEMICOLON | TokenKind::COMMA, .. })) => return Ok(lhs),
+                    TokenKind::RIGHT_SQUARE_BRACKET | TokenKind::SEMICOLON | TokenKind::COMMA | TokenKind::COLON, .. })) => return Ok(lhs),
                 
                 // unexpected
                 Some(Ok(token)) => return Err(miette::miette! {
                     labels = vec![
                         LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
                     ],
-                    help = format!("Unexpected {token:?}"),
+                    help = format!("Unexpected {:?}", token.kind),
                     "Expected an expression operator"
                 }.with_source_code(self.whole.to_string())),
                 Some(Err(_)) => unreachable!("checked above")
             };
-            if let Some((_l_bp, ())) = Self::postfix_binding_power(op) {
-                unimplemented!("Not yet needed")
+            if let Some((l_bp, ())) = Self::postfix_binding_power(op) {
+                if l_bp < min_bp { break; }
+                self.lexer.next(); // consume the op token
+                lhs = match op {
+                    Op::Index => {
+                        let index = self.parse_expression_within(0)
+                            .wrap_err("in index")?;
+                        self.lexer.expect_next(TokenKind::RIGHT_SQUARE_BRACKET, "expected ]")
+                            .wrap_err("after index")?;
+                        ExprTree::Index(Index::new(Box::new(lhs), Box::new(index), self.span_from(start)))
+                    },
+                    Op::Call => {
+                        let args = self.parse_call_args()
+                            .wrap_err("in function call")?;
+                        ExprTree::FnCall(FnCall::new(Box::new(lhs), args, self.span_from(start)))
+                    },
+                    Op::Dot => {
+                        let name = self.lexer.expect_next(TokenKind::IDENT, "expected field name")
+                            .wrap_err("after .")?;
+                        let name_span = SourceSpan::from(name.offset..name.offset + name.origin.len());
+                        ExprTree::Index(Index::field(Box::new(lhs), name.origin, name_span, self.span_from(start)))
+                    },
+                    _ => unreachable!("only postfix operators have postfix binding power")
+                };
+                continue;
             }
             if let Some((l_bp, r_bp)) = Self::infix_binding_power(op) {
                 if l_bp < min_bp { break; }
                 self.lexer.next(); // consume the op token
                 let rhs = self.parse_expression_within(r_bp)
                     .wrap_err("on the right-hand side")?;
-                lhs = ExprTree::ConOp(ConOp::new(op, Box::new(lhs), Box::new(rhs)));
+                lhs = ExprTree::ConOp(ConOp::new(op, Box::new(lhs), Box::new(rhs), self.span_from(start)));
                 continue;
             }
             break;
@@ -202,42 +371,104 @@ impl<'de> Parser<'de> {
         Ok(lhs)
     }
 
-    /// ## Parses something starting from an ident
-    /// something starting from ident can be either:
-    /// - ident itself (variable reference)
-    /// - function call
-    fn parse_ident(&mut self, name: &'de str) -> Result<ExprTree<'de>, Error> {
-        if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::LEFT_PAREN, .. }))) {
-            self.lexer.next(); // we advance lexer
-            let mut arg_list = Vec::new();
-            if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::RIGHT_PAREN, ..}))) {
-                self.lexer.next(); // we advance the right paren
-                return Ok(ExprTree::FnCall(FnCall::new(name, arg_list)));
+    /// ## Parses call arguments
+    /// Parses comma separated expressions up to the closing `)`, 
+    /// the opening paren should already be consumed
+    fn parse_call_args(&mut self) -> Result<Vec<ExprTree<'de>>, Error> {
+        let mut arg_list = Vec::new();
+        if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::RIGHT_PAREN, ..}))) {
+            self.lexer.next(); // we advance the right paren
+            return Ok(arg_list);
+        }
+        loop {
+            let expr = self.parse_expression_within(0)?;
+            arg_list.push(expr);
+            match self.lexer.peek() {
+                Some(Ok(Token { kind: TokenKind::COMMA, .. })) => { self.lexer.next(); continue; },
+                Some(Ok(Token { kind: TokenKind::RIGHT_PAREN, .. })) => {
+                    self.lexer.next(); // we advance the right paren
+                    break;
+                },
+                None => return Err(Eof.into()),
+                Some(Ok(token)) => return Err(miette::miette! {
+                    labels = vec![
+                        LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
+                    ],
+                    help = format!("Unexpected {:?}", token.kind),
+                    "Expected end of argument list or comma"
+                }.with_source_code(self.whole.to_string())),
+                Some(Err(_)) => return Err(self.lexer.next().unwrap().err().unwrap())
             }
-            loop {
-                let expr = self.parse_expression_within(0)?;
-                arg_list.push(expr);
-                match self.lexer.peek() {
-                    Some(Ok(Token { kind: TokenKind::COMMA, .. })) => { self.lexer.next(); continue; },
-                    Some(Ok(Token { kind: TokenKind::RIGHT_PAREN, .. })) => {
-                        self.lexer.next(); // we advance the right paren
-                        break;
-                    },
-                    None => return Err(Eof.into()),
-                    Some(Ok(token)) => return Err(miette::miette! {
-                        labels = vec![
-                            LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
-                        ],
-                        help = format!("Unexpected {token:?}"),
-                        "Expected end of argument list or comma"
-                    }.with_source_code(self.whole.to_string())),
-                    Some(Err(_)) => return Err(self.lexer.next().unwrap().err().unwrap())
-                }
+        }
+        Ok(arg_list)
+    }
+
+    /// ## Parses list literal
+    /// Parses comma separated expressions up to the closing `]`, 
+    /// the opening bracket should already be consumed
+    fn parse_list(&mut self) -> Result<ExprTree<'de>, Error> {
+        let start = self.lexer.consumed_start();
+        let mut items = Vec::new();
+        if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::RIGHT_SQUARE_BRACKET, ..}))) {
+            self.lexer.next(); // we advance the right bracket
+            return Ok(ExprTree::List(List::new(items, self.span_from(start))));
+        }
+        loop {
+            items.push(self.parse_expression_within(0).wrap_err("in list")?);
+            match self.lexer.peek() {
+                Some(Ok(Token { kind: TokenKind::COMMA, .. })) => { self.lexer.next(); continue; },
+                Some(Ok(Token { kind: TokenKind::RIGHT_SQUARE_BRACKET, .. })) => {
+                    self.lexer.next(); // we advance the right bracket
+                    break;
+                },
+                None => return Err(Eof.into()),
+                Some(Ok(token)) => return Err(miette::miette! {
+                    labels = vec![
+                        LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
+                    ],
+                    help = format!("Unexpected {:?}", token.kind),
+                    "Expected end of list or comma"
+                }.with_source_code(self.whole.to_string())),
+                Some(Err(_)) => return Err(self.lexer.next().unwrap().err().unwrap())
             }
-            Ok(ExprTree::FnCall(FnCall::new(name, arg_list)))
-        } else {
-            Ok(ExprTree::Atom(Atom::Ident(name)))
         }
+        Ok(ExprTree::List(List::new(items, self.span_from(start))))
+    }
+
+    /// ## Parses map literal
+    /// Parses comma separated `key: value` entries up to the closing `}`, optionally followed by a comma,
+    /// everything up to the first key (included) should already be consumed
+    fn parse_map(&mut self, start: usize, first_key: ExprTree<'de>) -> Result<ExprTree<'de>, Error> {
+        let mut entries = Vec::new();
+        let mut key = first_key;
+        loop {
+            self.lexer.expect_next(TokenKind::COLON, "expected :")?;
+            let value = self.parse_expression_within(0)?;
+            entries.push((key, value));
+            match self.lexer.next() {
+                // trailing comma is allowed
+                Some(Ok(Token { kind: TokenKind::COMMA, .. })) 
+                    if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::RIGHT_BRACE, .. }))) => {
+                    self.lexer.next();
+                    break
+                },
+                Some(Ok(Token { kind: TokenKind::COMMA, .. })) => {},
+                Some(Ok(Token { kind: TokenKind::RIGHT_BRACE, .. })) => break,
+                None => return Err(Eof.into()),
+                Some(Ok(token)) => return Err(miette::miette! {
+                    labels = vec![
+                        LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
+                    ],
+                    help = format!("Unexpected {:?}", token.kind),
+                    "Expected end of map or comma"
+                }.with_source_code(self.whole.to_string())),
+                Some(Err(e)) => return Err(e)
+            }
+            let token = self.lexer.expect_next(TokenKind::STRING, "expected string key")?;
+            let span = SourceSpan::from(token.offset..token.offset + token.origin.len());
+            key = ExprTree::Atom(Atom::String(unescape(token.origin)), span);
+        }
+        Ok(ExprTree::Map(Map::new(entries, self.span_from(start))))
     }
 
     /// ## Parses for loop
@@ -245,6 +476,7 @@ impl<'de> Parser<'de> {
         if !skip_first_keyword {
             self.lexer.expect_next(TokenKind::FOR, "expected for loop")?;
         }
+        let start = self.lexer.consumed_start();
         self.lexer.expect_next(TokenKind::LEFT_PAREN, "expected (")
             .wrap_err("in for loop")?;
         let init = 
@@ -258,10 +490,10 @@ impl<'de> Parser<'de> {
             .wrap_err("in for loop")?;
         let cond = 
         if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::SEMICOLON, ..}))) {
-            ExprTree::Atom(Atom::Bool(true))
+            None
         } else {
-            self.parse_statement_within()
-                .wrap_err("in for loop's cond")?
+            Some(Box::new(self.parse_statement_within()
+                .wrap_err("in for loop's cond")?))
         };
         self.lexer.expect_next(TokenKind::SEMICOLON, "expected ;")
             .wrap_err("in for loop")?;
@@ -274,14 +506,16 @@ impl<'de> Parser<'de> {
         };
         self.lexer.expect_next(TokenKind::RIGHT_PAREN, "expected )")
             .wrap_err("in for loop")?;
-        let block = self.parse_block(false)
+        let block = self.parse_loop_block()
             .wrap_err("in for loop's block")?;
 
         let loop_strc = Loop::new(
-            Box::new(cond), 
+            LoopKind::For,
+            cond, 
             init, 
             step, 
-            Box::new(block));
+            Box::new(block),
+            self.span_from(start));
         Ok(ExprTree::Loop(loop_strc))
     }
 
@@ -290,20 +524,23 @@ impl<'de> Parser<'de> {
         if !skip_first_keyword {
             self.lexer.expect_next(TokenKind::WHILE, "expected while loop")?;
         }
+        let start = self.lexer.consumed_start();
         self.lexer.expect_next(TokenKind::LEFT_PAREN, "expected (")
             .wrap_err("in while loop")?;
         let cond = self.parse_statement_within()
             .wrap_err("in while loop's cond")?;
         self.lexer.expect_next(TokenKind::RIGHT_PAREN, "expected )")
             .wrap_err("in while loop")?;
-        let block = self.parse_block(false)
+        let block = self.parse_loop_block()
             .wrap_err("in while loop's block")?;
 
         let loop_strc = Loop::new(
-            Box::new(cond), 
+            LoopKind::While,
+            Some(Box::new(cond)), 
             None, 
             None, 
-            Box::new(block));
+            Box::new(block),
+            self.span_from(start));
         Ok(ExprTree::Loop(loop_strc))
     }
 
@@ -312,25 +549,34 @@ impl<'de> Parser<'de> {
         if !skip_first_keyword {
             self.lexer.expect_next(TokenKind::IF, "expected if")?;
         }
+        let start = self.lexer.consumed_start();
         self.lexer.expect_next(TokenKind::LEFT_PAREN, "expected (").wrap_err("in if condition")?;
         let condition = Box::new(self.parse_expression_within(0).wrap_err("in if condition")?);
         self.lexer.expect_next(TokenKind::RIGHT_PAREN, "expected )").wrap_err("in if condition")?;
-        let yes_stmt = Box::new(self.parse_block(false).wrap_err("in if expression")?);
+        let yes_stmt = Box::new(self.parse_block(false, false).wrap_err("in if expression")?);
         let no_stmt = if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::ELSE, .. }))) {
             self.lexer.next(); // we advance lexer, checked above
-            Some(Box::new(self.parse_block(false)?))
+            Some(Box::new(self.parse_block(false, false)?))
         } else {
             None
         };
 
-        Ok(ExprTree::If(If::new(condition, yes_stmt, no_stmt)))
+        Ok(ExprTree::If(If::new(condition, yes_stmt, no_stmt, self.span_from(start))))
     }
 
     /// ## Parses block
-    fn parse_block(&mut self, skip_first_keyword: bool) -> Result<ExprTree<'de>, Error> {
+    /// With `allow_map` the braces can hold a map literal instead, it is recognized
+    /// by a string followed by `:` as the first thing in the braces, `{:}` is an empty map
+    fn parse_block(&mut self, skip_first_keyword: bool, allow_map: bool) -> Result<ExprTree<'de>, Error> {
         if !skip_first_keyword {
             self.lexer.expect_next(TokenKind::LEFT_BRACE, "expected block")?;
         }
+        let start = self.lexer.consumed_start();
+        if allow_map && matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::COLON, ..}))) {
+            self.lexer.next();
+            self.lexer.expect_next(TokenKind::RIGHT_BRACE, "expected }").wrap_err("in empty map")?;
+            return Ok(ExprTree::Map(Map::new(Vec::new(), self.span_from(start))))
+        }
         let mut stmts: Vec<ExprTree<'de>> = Vec::new();
         let mut ret_expr: Option<ExprTree<'de>> = None;
         
@@ -346,20 +592,59 @@ impl<'de> Parser<'de> {
                         break;
                     }
                 },
-                _ => stmts.push(self.parse_statement_within().wrap_err("in block")?),
+                None => break,
+                _ => match self.parse_statement_within() {
+                    Ok(key @ ExprTree::Atom(Atom::String(_), _)) if allow_map && stmts.is_empty() &&
+                        matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::COLON, ..}))) => {
+                        return self.parse_map(start, key).wrap_err("in map")
+                    },
+                    Ok(stmt) => stmts.push(stmt),
+                    // nothing to recover at the end of the input, the error ends the parsing
+                    Err(e) if !self.recovering || self.lexer.peek().is_none() => return Err(e.wrap_err("in block")),
+                    Err(e) => {
+                        self.errors.push(e.wrap_err("in block"));
+                        self.synchronize(true);
+                    }
+                },
             }
         };
         self.lexer.expect_next(TokenKind::RIGHT_BRACE, "expected }")?;
 
-        Ok(ExprTree::Block(stmts, ret_expr.map(|e| Box::new(e))))
+        Ok(ExprTree::Block(stmts, ret_expr.map(Box::new), self.span_from(start)))
+    }
+
+    /// ## Parses loop's block
+    /// `break` and `continue` are allowed only within it
+    fn parse_loop_block(&mut self) -> Result<ExprTree<'de>, Error> {
+        self.loop_depth += 1;
+        let block = self.parse_block(false, false);
+        self.loop_depth -= 1;
+        block
     }
 
     /// ## Parses function declaration
-    fn parse_fun(&mut self, skip_first_keyword: bool) -> Result<ExprTree<'de>, Error> {
+    /// Named functions are declarations (statements) 
+    /// while anonymous ones are expressions evaluating to the function,
+    /// doc comments in front of the `fun` keyword belong to the function
+    fn parse_fun(&mut self, skip_first_keyword: bool, named: bool) -> Result<ExprTree<'de>, Error> {
+        let mut doc = Vec::new();
         if !skip_first_keyword {
-            self.lexer.expect_next(TokenKind::FUN, "expected fun")?;
+            doc = self.lexer.expect_next(TokenKind::FUN, "expected fun")?.doc;
         }
-        let ident = self.lexer.expect_next(TokenKind::IDENT, "expected function name")?.origin;
+        let start = self.lexer.consumed_start();
+        let name = if named {
+            Some(self.lexer.expect_next(TokenKind::IDENT, "expected function name")?.origin)
+        } else {
+            None
+        };
+        let fnb = self.parse_function(name, start)?.with_doc(doc);
+        Ok(ExprTree::FnBlock(fnb))
+    }
+
+    /// ## Parses parameters and body of the function
+    /// Everything up to the name (included) should already be consumed
+    fn parse_function(&mut self, name: Option<&'de str>, start: usize) -> Result<FnBlock<'de>, Error> {
+        let ident = name.unwrap_or("anonymous");
         self.lexer.expect_next(TokenKind::LEFT_PAREN, "expected (")
             .wrap_err(format!("in function {} params", ident))?;
 
@@ -380,7 +665,7 @@ impl<'de> Parser<'de> {
                         labels = vec![
                             LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
                         ],
-                        help = format!("Unexpected {token:?} in function {ident} parameters"),
+                        help = format!("Unexpected {:?} in function {ident} parameters", token.kind),
                         "Expected end of parameters list"
                     }.with_source_code(self.whole.to_string())),
                     Some(Err(_)) => return Err(self.lexer.next().unwrap().err().unwrap())
@@ -389,34 +674,128 @@ impl<'de> Parser<'de> {
         }
         self.lexer.expect_next(TokenKind::RIGHT_PAREN, "expected )")
             .wrap_err(format!("in function {} params", ident))?;
-        let block = self.parse_block(false)
-            .wrap_err(format!("in function {} block", ident))?;
-        Ok(ExprTree::FnBlock(FnBlock::new(ident, args, Box::new(block))))
+        // loops outside of the function can't be broken from within it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.fn_depth += 1;
+        let block = self.parse_block(false, false);
+        self.fn_depth -= 1;
+        self.loop_depth = loop_depth;
+        let block = block.wrap_err(format!("in function {} block", ident))?;
+        Ok(FnBlock::new(name, args, Box::new(block), self.span_from(start)))
+    }
+
+    /// ## Parses class declaration
+    /// Methods are written like named functions without the `fun` keyword,
+    /// doc comments in front of the `class` keyword belong to the class
+    /// and the ones in front of the method to the method
+    fn parse_class(&mut self) -> Result<ExprTree<'de>, Error> {
+        let doc = self.lexer.expect_next(TokenKind::CLASS, "expected class")?.doc;
+        let start = self.lexer.consumed_start();
+        let name = self.lexer.expect_next(TokenKind::IDENT, "expected class name")?.origin;
+        let superclass = if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::LESS, ..}))) {
+            self.lexer.next();
+            let superclass = self.lexer.expect_next(TokenKind::IDENT, "expected superclass name")
+                .wrap_err(format!("in class {name}"))?;
+            let span = SourceSpan::from(superclass.offset..superclass.offset + superclass.origin.len());
+            Some(Box::new(ExprTree::Atom(Atom::Ident(superclass.origin, Binding::Global), span)))
+        } else {
+            None
+        };
+        self.lexer.expect_next(TokenKind::LEFT_BRACE, "expected {").wrap_err(format!("in class {name}"))?;
+
+        self.classes.push(superclass.is_some());
+        let methods = self.parse_methods();
+        self.classes.pop();
+        let methods = methods.wrap_err(format!("in class {name}"))?;
+        Ok(ExprTree::Class(ClassDecl::new(name, superclass, methods, self.span_from(start)).with_doc(doc)))
+    }
+
+    /// ## Parses methods of the class
+    /// Parses methods up to the closing `}`, the opening brace should already be consumed
+    fn parse_methods(&mut self) -> Result<Vec<FnBlock<'de>>, Error> {
+        let mut methods = Vec::new();
+        loop {
+            match self.lexer.peek() {
+                Some(Ok(Token { kind: TokenKind::RIGHT_BRACE, ..})) => {
+                    self.lexer.next(); // we advance the right brace
+                    return Ok(methods)
+                },
+                None => return Err(Eof.into()),
+                _ => {
+                    let name = self.lexer.expect_next(TokenKind::IDENT, "expected method name")?;
+                    let method = self.parse_function(Some(name.origin), name.offset)?.with_doc(name.doc);
+                    methods.push(method);
+                },
+            }
+        }
+    }
+
+    /// ## Parses import
+    /// The namespace is named after the file the path points to,
+    /// so the name of the file has to be an identifier
+    fn parse_import(&mut self) -> Result<ExprTree<'de>, Error> {
+        self.lexer.expect_next(TokenKind::IMPORT, "expected import")?;
+        let start = self.lexer.consumed_start();
+        let token = self.lexer.expect_next(TokenKind::STRING, "expected path of the module")
+            .wrap_err("in import")?;
+        let path = &token.origin[1..token.origin.len() - 1];
+        let name = Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        let is_ident = matches!(Lexer::new(name).collect::<Vec<_>>()[..], [Ok(Token { kind: TokenKind::IDENT, origin, .. })] if origin == name);
+        if !is_ident {
+            return Err(miette::miette! {
+                labels = vec![
+                    LabeledSpan::at(token.offset..token.offset + token.origin.len(), "here"),
+                ],
+                help = "the namespace is named after the file, rename the file so its name is an identifier",
+                "Module name '{name}' is not an identifier"
+            }.with_source_code(self.whole.to_string()))
+        }
+        Ok(ExprTree::Import(Import::new(path, name, self.span_from(start))))
     }
 
     /// ## Parses var declaration
+    /// Doc comments in front of the `var` keyword belong to the declaration
     fn parse_var(&mut self, skip_first_keyword: bool) -> Result<ExprTree<'de>, Error> {
+        let mut doc = Vec::new();
         if !skip_first_keyword {
-            self.lexer.expect_next(TokenKind::VAR, "expected var")?;
+            doc = self.lexer.expect_next(TokenKind::VAR, "expected var")?.doc;
         }
+        let start = self.lexer.consumed_start();
         let ident = self.lexer.expect_next(TokenKind::IDENT, "expected ident")
             .wrap_err("in variable declaration")?.origin;
         if matches!(self.lexer.peek(), Some(Ok(Token { kind: TokenKind::SEMICOLON, ..}))) {
-            return Ok(ExprTree::Var(VarDecl::new(ident, None)))
+            return Ok(ExprTree::Var(VarDecl::new(ident, None, self.span_from(start)).with_doc(doc)))
         }
         self.lexer.expect_next(TokenKind::EQUAL, "expected =")
             .wrap_err("in variable declaration")?;
         let expr = self.parse_expression_within(0)
             .wrap_err("in variable declaration")?;
-        let var_decl = VarDecl::new(ident, Some(Box::new(expr)));
+        let var_decl = VarDecl::new(ident, Some(Box::new(expr)), self.span_from(start)).with_doc(doc);
         Ok(ExprTree::Var(var_decl))
     }
 
+    /// ## Span from the offset
+    /// Returns span starting at `start` and ending with the last consumed token
+    fn span_from(&self, start: usize) -> SourceSpan {
+        SourceSpan::from(start..self.lexer.consumed_end())
+    }
+
+    /// ## Control flow error
+    /// Points at the just consumed keyword that is used outside of its scope
+    fn control_flow_error(&self, keyword: &'static str, scope: &'static str) -> Error {
+        ControlFlowError {
+            src: self.whole.to_string(),
+            keyword,
+            scope,
+            err_span: self.span_from(self.lexer.consumed_start()),
+        }.into()
+    }
+
     /// ## Prefix bp
     /// Returns prefix binding power for given unary operator
     fn prefix_binding_power(uop: UOp) -> ((), u8) {
         match uop {
-            UOp::Minus | UOp::Bang => ((), 20),
+            UOp::Minus | UOp::Bang | UOp::Tilde => ((), 20),
             UOp::Group | UOp::Break | UOp::Return | UOp::Print => panic!("Groups and statements should be handled independently"),
             // _ => panic!("Should never be called on non prefix operator")
         }
@@ -426,26 +805,34 @@ impl<'de> Parser<'de> {
     /// Returns infix binding power for given infix operator
     fn infix_binding_power(op: Op) -> Option<(u8, u8)> {
         let res = match op {
-            Op::Plus | Op::Minus => (7, 8),
-            Op::Star | Op::Slash => (9, 10),
+            Op::Pipe => (7, 8),
+            Op::Caret => (9, 10),
+            Op::Ampersand => (11, 12),
+            Op::LessLess | Op::GreaterGreater => (13, 14),
+            Op::Plus | Op::Minus => (15, 16),
+            Op::Star | Op::Slash | Op::Div | Op::Percent => (17, 18),
+            // right associative and binds tighter than prefix operators,
+            // `-2 ** 2` is `-(2 ** 2)`
+            Op::StarStar => (22, 21),
             Op::Less | Op::LessEqual | 
             Op::EqualEqual | Op::GreaterEqual | 
             Op::Greater | Op::BangEqual => (5, 6),
             Op::Equal => (1, 2),
             Op::And | Op::Or => (3, 4),
-            // _ => return None,
+            Op::Index | Op::Call | Op::Dot => return None,
         };
         Some(res)
     }
     
     /// ## Postfix bp
     /// Returns postfix binding power for given postfix operator
-    /// 
-    /// *Not yet required*
-    fn postfix_binding_power(_op: Op) -> Option<(u8, ())> {
-        // it can be used later for example for array field access 
-        None
-    } 
+    fn postfix_binding_power(op: Op) -> Option<(u8, ())> {
+        match op {
+            // binds tighter than prefix operators so `-xs[0]` is `-(xs[0])`
+            Op::Index | Op::Call | Op::Dot => Some((23, ())),
+            _ => None
+        }
+    }
 
     #[allow(dead_code)]
     /// ## Skips semicolon
@@ -0,0 +1,174 @@
+use std::cell::RefCell;
+
+use miette::{Error, NamedSource};
+
+use crate::{context::CtxTree, error::{Eof, UnterminatedStringError}, evaluator::{Eval, Event, Value}, lexer::Lexer, parser::Parser, resolver::Resolver, stdlib, token::TokenKind};
+
+/// ## Sources of the session
+/// Every input is kept until the sources are dropped, functions
+/// declared in one input borrow it for the rest of the session
+#[derive(Default)]
+pub struct Sources {
+    inputs: RefCell<Vec<Box<str>>>,
+}
+
+impl Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ## Stores the input
+    /// returns the stored copy borrowed for as long as the sources live
+    pub fn add(&self, input: String) -> &str {
+        let input = input.into_boxed_str();
+        let stored: *const str = &*input;
+        self.inputs.borrow_mut().push(input);
+        // SAFETY: inputs are never removed or changed, moving the box into
+        // the vector doesn't move the string it points to
+        unsafe { &*stored }
+    }
+}
+
+/// ## Interactive session
+/// Keeps one context alive across all the inputs so variables and
+/// functions declared in one input are visible in the following ones
+pub struct Repl<'src> {
+    sources: &'src Sources,
+    ctx: CtxTree<'src>,
+    resolver: Resolver<'src>,
+    buffer: String,
+}
+
+impl<'src> Repl<'src> {
+    pub fn new(sources: &'src Sources) -> Self {
+        let ctx = CtxTree::new();
+        stdlib::install(&ctx);
+        let resolver = Resolver::with_globals(&ctx);
+        Self { sources, ctx, resolver, buffer: String::new() }
+    }
+
+    /// ## Checks for unfinished input
+    /// Returns true if some lines are buffered waiting for the rest of the input
+    pub fn is_pending(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// ## Feeds single line
+    /// Buffers the line and evaluates everything buffered so far once the input is complete.
+    ///
+    /// ## Returns
+    /// - `None` if the input is not complete yet (unclosed block, paren, string, ...)
+    /// - value of the last statement if it produced a printable value
+    /// - miette error with source code attached if parsing or evaluation failed
+    pub fn feed(&mut self, line: &str) -> Option<Result<Option<Value<'src>>, Error>> {
+        let force = line.trim().is_empty() && self.is_pending();
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        if !force && self.needs_more_input() {
+            return None;
+        }
+
+        let source = self.sources.add(std::mem::take(&mut self.buffer));
+        let mut stmts = match Parser::new(source).parse() {
+            Ok(stmts) => stmts,
+            Err(e) => return Some(Err(e)),
+        };
+        if let Err(e) = self.resolver.resolve(&mut stmts) {
+            return Some(Err(e.with_source_code(NamedSource::new("repl", source.to_string()))))
+        }
+
+        let mut last = Value::Event(Event::NoVal);
+        for stmt in stmts {
+            match stmt.eval(&self.ctx) {
+                Ok(v) => last = v,
+                Err(e) => return Some(Err(e.with_source_code(NamedSource::new("repl", source.to_string())))),
+            }
+        }
+        match last {
+            Value::Event(_) | Value::Nil => Some(Ok(None)),
+            v => Some(Ok(Some(v))),
+        }
+    }
+
+    /// ## Checks for unfinished input
+    /// Input is unfinished if it has more opening than closing brackets,
+    /// ends inside of a string or the parser hits the end of input too early
+    fn needs_more_input(&self) -> bool {
+        let mut depth = 0i32;
+        for token in Lexer::new(&self.buffer) {
+            match token {
+                Ok(token) => match token.kind {
+                    TokenKind::LEFT_PAREN | TokenKind::LEFT_BRACE | TokenKind::LEFT_SQUARE_BRACKET => depth += 1,
+                    TokenKind::RIGHT_PAREN | TokenKind::RIGHT_BRACE | TokenKind::RIGHT_SQUARE_BRACKET => depth -= 1,
+                    _ => {}
+                },
+                Err(e) if e.downcast_ref::<UnterminatedStringError>().is_some() => return true,
+                Err(_) => {}
+            }
+        }
+        depth > 0 || Parser::new(&self.buffer).parse()
+            .is_err_and(|e| e.downcast_ref::<Eof>().is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_ok<'src>(repl: &mut Repl<'src>, line: &str) -> Option<Value<'src>> {
+        repl.feed(line).expect("input should be complete").unwrap()
+    }
+
+    #[test]
+    fn test_sources_keep_inputs() {
+        let sources = Sources::new();
+        let first = sources.add("first".to_string());
+        for i in 0..100 {
+            sources.add(i.to_string());
+        }
+        assert_eq!(first, "first");
+        assert_eq!(sources.inputs.borrow().len(), 101);
+    }
+
+    #[test]
+    fn test_state_is_kept_between_lines() {
+        let sources = Sources::new();
+        let mut repl = Repl::new(&sources);
+        assert_eq!(feed_ok(&mut repl, "var a = 2;"), None);
+        assert_eq!(feed_ok(&mut repl, "fun double(x) { x * 2 }"), None);
+        assert_eq!(feed_ok(&mut repl, "double(a)"), Some(Value::Number(4f64)));
+    }
+
+    #[test]
+    fn test_multiline_input() {
+        let sources = Sources::new();
+        let mut repl = Repl::new(&sources);
+        assert!(repl.feed("fun add(a, b) {").is_none());
+        assert!(repl.is_pending());
+        assert!(repl.feed("    return a + b;").is_none());
+        assert_eq!(feed_ok(&mut repl, "}"), None);
+        assert!(!repl.is_pending());
+        assert!(repl.feed("add(1,").is_none());
+        assert_eq!(feed_ok(&mut repl, "2)"), Some(Value::Number(3f64)));
+        assert!(repl.feed("\"multi").is_none());
+        assert_eq!(feed_ok(&mut repl, "line\""), Some(Value::String("multi\nline".to_string())));
+    }
+
+    #[test]
+    fn test_errors_do_not_end_session() {
+        let sources = Sources::new();
+        let mut repl = Repl::new(&sources);
+        assert!(repl.feed("undefined + 1").unwrap().is_err());
+        assert!(repl.feed("1 +* 2").unwrap().is_err());
+        assert_eq!(feed_ok(&mut repl, "1 + 2"), Some(Value::Number(3f64)));
+    }
+
+    #[test]
+    fn test_empty_line_forces_pending_input() {
+        let sources = Sources::new();
+        let mut repl = Repl::new(&sources);
+        assert!(repl.feed("1 +").is_none());
+        assert!(repl.feed("").unwrap().is_err());
+        assert!(!repl.is_pending());
+    }
+}
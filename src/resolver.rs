@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use miette::{Error, SourceSpan};
+
+use crate::{context::CtxTree, error::ResolveError, exptree::ExprTree};
+
+/// ## Where the variable lives
+/// Globals are looked up by name in the context tree,
+/// locals are read straight from the slot of the scope `depth` levels up
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Binding {
+    /// not resolved variables are treated as globals
+    #[default]
+    Global,
+    Local { depth: usize, slot: usize },
+}
+
+/// ## Resolvable node
+/// Implemented by every node of the expression tree, resolves all
+/// the variables used within the node
+pub trait Resolve<'de> {
+    fn resolve(&mut self, resolver: &mut Resolver<'de>) -> Result<(), Error>;
+}
+
+/// Single block scope, maps variable to its slot, whether it's initializer has finished
+/// and the name in its latest declaration
+#[derive(Default)]
+struct Scope<'de> {
+    vars: HashMap<&'de str, (usize, bool, &'de str)>,
+}
+
+/// ## Resolved use of the variable
+/// `declaration` is the name in the declaration the use refers to, it's a slice
+/// of the same source, `None` for globals declared outside of the program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reference<'de> {
+    pub span: SourceSpan,
+    pub declaration: Option<&'de str>,
+}
+
+/// ## Static resolver
+/// Semantic analysis pass run between parsing and evaluation.
+/// Every scope the evaluator forks the context for (block, loop, function call)
+/// is mirrored here so each variable can be given its (depth, slot) pair.
+///
+/// Reports use of undeclared variables and variables used in their own initializers
+/// before any code runs. Resolver remembers declared globals, so it can be reused
+/// across many inputs evaluated in the same context (like in repl)
+#[derive(Default)]
+pub struct Resolver<'de> {
+    scopes: Vec<Scope<'de>>,
+    /// known globals and whether they were already declared in the program order
+    globals: HashMap<String, bool>,
+    /// global variable that is being initialized right now
+    initializing: Option<&'de str>,
+    /// function bodies being resolved and whether they are class initializers,
+    /// code outside of them runs in program order and can't use globals declared later
+    functions: Vec<bool>,
+    /// first declaration of each global of the program
+    declarations: HashMap<&'de str, &'de str>,
+    /// resolved uses of variables, recorded only when requested
+    references: Option<Vec<Reference<'de>>>,
+}
+
+impl<'de> Resolver<'de> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ## Creates resolver for the context
+    /// all variables already present in the context (natives, previous inputs, ...) are known globals
+    pub fn with_globals(ctx: &CtxTree<'_>) -> Self {
+        let globals = ctx.names().into_iter().map(|name| (name, true)).collect();
+        Self { globals, ..Self::default() }
+    }
+
+    /// ## Records references
+    /// Every resolved use of a variable is remembered together with its declaration
+    pub fn with_references(mut self) -> Self {
+        self.references = Some(Vec::new());
+        self
+    }
+
+    /// ## Recorded references
+    /// Empty unless the resolver was created `with_references`
+    pub fn references(&self) -> &[Reference<'de>] {
+        self.references.as_deref().unwrap_or_default()
+    }
+
+    /// ## Resolves the program
+    /// Globals declared anywhere on top level are visible in all functions,
+    /// so functions can call functions declared after them
+    pub fn resolve(&mut self, stmts: &mut [ExprTree<'de>]) -> Result<(), Error> {
+        for stmt in stmts.iter() {
+            if let Some(name) = stmt.declared_name() {
+                self.globals.entry(name.to_string()).or_insert(false);
+                self.declarations.entry(name).or_insert(name);
+            }
+        }
+        for stmt in stmts.iter_mut() {
+            stmt.resolve(self)?;
+        }
+        Ok(())
+    }
+
+    /// ## Opens new scope
+    /// has to be paired with `end_scope` and match the fork done by the evaluator
+    pub fn begin_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    pub fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// ## Opens scope of the function body
+    /// has to be paired with `end_function`, globals declared later
+    /// in the program are visible in the body
+    pub fn begin_function(&mut self, initializer: bool) {
+        self.functions.push(initializer);
+        self.begin_scope();
+    }
+
+    pub fn end_function(&mut self) {
+        self.end_scope();
+        self.functions.pop();
+    }
+
+    /// ## Checks for initializer
+    /// Returns true if the innermost function being resolved is the class initializer
+    pub fn in_initializer(&self) -> bool {
+        self.functions.last() == Some(&true)
+    }
+
+    /// ## Declares the variable
+    /// Variable can't be read until it's defined, redeclaring
+    /// the variable in the same scope reuses its slot
+    pub fn declare(&mut self, name: &'de str) -> Binding {
+        let Some(scope) = self.scopes.last_mut() else {
+            if self.globals.get(name) != Some(&true) {
+                self.initializing = Some(name);
+            }
+            return Binding::Global
+        };
+        let next = scope.vars.len();
+        let (slot, _, _) = *scope.vars.entry(name).or_insert((next, false, name));
+        Binding::Local { depth: 0, slot }
+    }
+
+    /// ## Defines the variable
+    /// Marks the declared variable as ready to be read,
+    /// initializer of redeclared variable still refers to the previous declaration
+    pub fn define(&mut self, name: &'de str) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                if let Some((_, defined, declaration)) = scope.vars.get_mut(name) {
+                    *defined = true;
+                    *declaration = name;
+                }
+            },
+            None => {
+                self.initializing = None;
+                self.globals.insert(name.to_string(), true);
+            }
+        }
+    }
+
+    /// ## Defines implicit variable
+    /// Variables like `this` are not declared in the source,
+    /// their uses refer to the `declaration` instead
+    pub fn define_implicit(&mut self, name: &'de str, declaration: &'de str) {
+        let scope = self.scopes.last_mut().expect("implicit variables are local");
+        let next = scope.vars.len();
+        scope.vars.insert(name, (next, true, declaration));
+    }
+
+    /// ## Resolves use of the variable
+    /// Searches scopes from the innermost one, variables not found
+    /// in any of them have to be known globals
+    pub fn lookup(&mut self, name: &'de str, span: SourceSpan) -> Result<Binding, ResolveError> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(&(slot, defined, declaration)) = scope.vars.get(name) {
+                if !defined {
+                    return Err(ResolveError::SelfReferencingInitializer { name: name.to_string(), span })
+                }
+                self.record(span, Some(declaration));
+                return Ok(Binding::Local { depth, slot })
+            }
+        }
+        if self.initializing == Some(name) {
+            return Err(ResolveError::SelfReferencingInitializer { name: name.to_string(), span })
+        }
+        match self.globals.get(name) {
+            Some(false) if self.functions.is_empty() => {
+                Err(ResolveError::UsedBeforeDeclaration { name: name.to_string(), span })
+            },
+            Some(_) => {
+                self.record(span, self.declarations.get(name).copied());
+                Ok(Binding::Global)
+            },
+            None => Err(ResolveError::UndeclaredVariable { name: name.to_string(), span }),
+        }
+    }
+
+    fn record(&mut self, span: SourceSpan, declaration: Option<&'de str>) {
+        if let Some(references) = self.references.as_mut() {
+            references.push(Reference { span, declaration });
+        }
+    }
+}
@@ -0,0 +1,59 @@
+/// ## Map of the source code
+/// Converts byte offsets into lines and columns and back,
+/// lines and columns start at 1 and columns count characters, not bytes
+#[derive(Debug, Clone)]
+pub struct SourceMap<'de> {
+    src: &'de str,
+    /// byte offset at which each line starts
+    lines: Vec<usize>,
+}
+
+impl<'de> SourceMap<'de> {
+    pub fn new(src: &'de str) -> Self {
+        let lines = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { src, lines }
+    }
+
+    /// ## Line and column of the offset
+    /// Offset past the end of the source is clamped to the end,
+    /// offset inside of a multi-byte character points to that character,
+    /// offset inside of the line break points right after the last character of the line
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.src.len());
+        let line = self.lines.partition_point(|&start| start <= offset) - 1;
+        let start = self.lines[line];
+        let column = self.line(line + 1).unwrap_or_default().char_indices()
+            .take_while(|(i, c)| start + i + c.len_utf8() <= offset)
+            .count();
+        (line + 1, column + 1)
+    }
+
+    /// ## Offset of the line and column
+    /// Returns `None` if the position is outside of the source,
+    /// column right after the last character of the line is valid
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        let text = self.line(line)?;
+        let start = self.lines[line - 1];
+        match text.char_indices().nth(column.checked_sub(1)?) {
+            Some((i, _)) => Some(start + i),
+            None if column == text.chars().count() + 1 => Some(start + text.len()),
+            None => None,
+        }
+    }
+
+    /// ## Text of the line
+    /// Returns the line without its line break
+    pub fn line(&self, line: usize) -> Option<&'de str> {
+        let start = *self.lines.get(line.checked_sub(1)?)?;
+        let end = self.lines.get(line).map_or(self.src.len(), |next| next - 1);
+        let text = &self.src[start..end];
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// ## Number of lines
+    pub fn lines(&self) -> usize {
+        self.lines.len()
+    }
+}
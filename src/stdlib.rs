@@ -0,0 +1,237 @@
+use std::{cell::Cell, cmp::Ordering, collections::BTreeMap, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+
+use miette::Error;
+
+use crate::{context::CtxTree, error::RuntimeError, evaluator::Value};
+
+/// ## Installs the standard library
+/// Registers all builtin functions in given context node,
+/// scripts can shadow them with their own declarations
+pub fn install(ctx: &CtxTree<'_>) {
+    install_strings(ctx);
+    install_math(ctx);
+    install_types(ctx);
+    install_maps(ctx);
+
+    ctx.register_fn("clock", Some(0), |_| Ok(now().into()));
+}
+
+fn install_strings(ctx: &CtxTree<'_>) {
+    ctx.register_fn("len", Some(1), |args| {
+        match &args[0] {
+            Value::String(s) => Ok((s.chars().count() as i64).into()),
+            Value::List(items) => Ok((items.borrow().len() as i64).into()),
+            Value::Map(entries) => Ok((entries.borrow().len() as i64).into()),
+            v => Err(RuntimeError::UnexpectedType { expected: "string, list or map", found: v.type_name(), span: None }.into())
+        }
+    });
+    ctx.register_fn("substring", Some(3), |args| {
+        let mut args = args.into_iter();
+        let s = string(args.next())?;
+        let len = s.chars().count();
+        let start = bound(args.next(), len)?;
+        let end = bound(args.next(), len)?;
+        if start > end {
+            return Err(invalid("substring", format!("start {start} is greater than end {end}")))
+        }
+        Ok(s.chars().skip(start).take(end - start).collect::<String>().into())
+    });
+    ctx.register_fn("split", Some(2), |args| {
+        let mut args = args.into_iter();
+        let s = string(args.next())?;
+        let sep = string(args.next())?;
+        // empty separator splits into single characters
+        let parts: Vec<String> = if sep.is_empty() {
+            s.chars().map(String::from).collect()
+        } else {
+            s.split(sep.as_str()).map(String::from).collect()
+        };
+        Ok(parts.into())
+    });
+    ctx.register_fn("upper", Some(1), |args| {
+        Ok(string(args.into_iter().next())?.to_uppercase().into())
+    });
+    ctx.register_fn("lower", Some(1), |args| {
+        Ok(string(args.into_iter().next())?.to_lowercase().into())
+    });
+    ctx.register_fn("contains", Some(2), |args| {
+        match (&args[0], &args[1]) {
+            (Value::String(s), Value::String(sub)) => Ok(s.contains(sub.as_str()).into()),
+            (Value::String(_), v) => Err(RuntimeError::UnexpectedType { expected: "string", found: v.type_name(), span: None }.into()),
+            (Value::List(items), v) => Ok(items.borrow().contains(v).into()),
+            // maps contain their keys
+            (Value::Map(entries), Value::String(key)) => Ok(entries.borrow().contains_key(key).into()),
+            (Value::Map(_), v) => Err(RuntimeError::InvalidKey { found: v.type_name(), span: None }.into()),
+            (v, _) => Err(RuntimeError::UnexpectedType { expected: "string, list or map", found: v.type_name(), span: None }.into())
+        }
+    });
+}
+
+fn install_math(ctx: &CtxTree<'_>) {
+    ctx.register_fn("sqrt", Some(1), |args| Ok(number(args.into_iter().next())?.sqrt().into()));
+    ctx.register_fn("floor", Some(1), |args| Ok(number(args.into_iter().next())?.floor().into()));
+    ctx.register_fn("abs", Some(1), |args| {
+        match args.into_iter().next() {
+            Some(Value::Integer(i)) => i.checked_abs()
+                .map(Value::Integer)
+                .ok_or(RuntimeError::IntegerOverflow { op: "abs".to_string(), span: None }.into()),
+            arg => Ok(number(arg)?.abs().into())
+        }
+    });
+    ctx.register_fn("pow", Some(2), |args| {
+        let mut args = args.into_iter();
+        let base = number(args.next())?;
+        let exp = number(args.next())?;
+        Ok(base.powf(exp).into())
+    });
+    ctx.register_fn("min", None, |args| fold_numbers("min", args, Ordering::Less));
+    ctx.register_fn("max", None, |args| fold_numbers("max", args, Ordering::Greater));
+
+    // xorshift generator, every context gets its own state
+    let state = Rc::new(Cell::new(seed_state(now().to_bits())));
+    let random_state = state.clone();
+    ctx.register_fn("random", Some(0), move |_| {
+        let mut x = random_state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        random_state.set(x);
+        // 53 random bits give uniformly distributed float in [0, 1)
+        Ok(((x >> 11) as f64 / (1u64 << 53) as f64).into())
+    });
+    ctx.register_fn("seed", Some(1), move |args| {
+        let seed = number(args.into_iter().next())?;
+        state.set(seed_state(seed.to_bits()));
+        Ok(().into())
+    });
+}
+
+fn install_types(ctx: &CtxTree<'_>) {
+    ctx.register_fn("type_of", Some(1), |args| Ok(args[0].type_name().into()));
+    ctx.register_fn("to_string", Some(1), |args| Ok(args[0].to_string().into()));
+    ctx.register_fn("to_number", Some(1), |args| {
+        match &args[0] {
+            Value::Number(_) | Value::Integer(_) => Ok(args[0].clone()),
+            Value::Bool(b) => Ok((*b as i64).into()),
+            // strings that are not numbers give nil, whole numbers give integers
+            Value::String(s) => match s.trim().parse::<i64>() {
+                Ok(i) => Ok(i.into()),
+                Err(_) => Ok(s.trim().parse::<f64>().ok().into()),
+            },
+            v => Err(RuntimeError::UnexpectedType { expected: "number, string or bool", found: v.type_name(), span: None }.into())
+        }
+    });    ctx.register_fn("to_integer", Some(1), |args| {
+        match &args[0] {
+            Value::Integer(_) => Ok(args[0].clone()),
+            Value::Bool(b) => Ok((*b as i64).into()),
+            Value::Number(n) => to_integer(*n).map(Value::Integer),
+            v => Err(RuntimeError::UnexpectedType { expected: "number or bool", found: v.type_name(), span: None }.into())
+        }
+    });
+}
+
+fn install_maps(ctx: &CtxTree<'_>) {
+    ctx.register_fn("keys", Some(1), |args| {
+        let entries = map(args.into_iter().next())?;
+        Ok(entries.into_keys().collect::<Vec<_>>().into())
+    });
+    ctx.register_fn("values", Some(1), |args| {
+        let entries = map(args.into_iter().next())?;
+        Ok(entries.into_values().collect::<Vec<_>>().into())
+    });
+    ctx.register_fn("entries", Some(1), |args| {
+        let entries = map(args.into_iter().next())?;
+        let pairs: Vec<Value> = entries.into_iter()
+            .map(|(k, v)| Value::list(vec![k.into(), v]))
+            .collect();
+        Ok(pairs.into())
+    });
+    ctx.register_fn("remove", Some(2), |args| {
+        match (&args[0], &args[1]) {
+            // removed value is returned, missing key gives nil
+            (Value::Map(entries), Value::String(key)) => Ok(entries.borrow_mut().remove(key).into()),
+            (Value::Map(_), v) => Err(RuntimeError::InvalidKey { found: v.type_name(), span: None }.into()),
+            (v, _) => Err(RuntimeError::UnexpectedType { expected: "map", found: v.type_name(), span: None }.into())
+        }
+    });
+}
+
+/// Seconds since unix epoch
+fn now() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or_default()
+}
+
+/// Mixes the seed so similar seeds give different sequences, state can't be zero
+fn seed_state(seed: u64) -> u64 {
+    let mut x = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (x ^ (x >> 31)).max(1)
+}
+
+fn number(arg: Option<Value<'_>>) -> Result<f64, RuntimeError> {
+    f64::try_from(arg.unwrap_or(Value::Nil))
+}
+
+fn string(arg: Option<Value<'_>>) -> Result<String, RuntimeError> {
+    String::try_from(arg.unwrap_or(Value::Nil))
+}
+
+/// Copy of the entries, the map itself stays shared
+fn map<'de>(arg: Option<Value<'de>>) -> Result<BTreeMap<String, Value<'de>>, RuntimeError> {
+    BTreeMap::try_from(arg.unwrap_or(Value::Nil))
+}
+
+/// Reads the position within the string, `len` itself is still valid
+fn bound(arg: Option<Value<'_>>, len: usize) -> Result<usize, RuntimeError> {
+    match arg.unwrap_or(Value::Nil) {
+        Value::Integer(i) => usize::try_from(i).ok()
+            .filter(|i| *i <= len)
+            .ok_or(RuntimeError::IndexOutOfBounds { index: i, len, span: None }),
+        Value::Number(n) if n.fract() == 0.0 => {
+            if n < 0.0 || n > len as f64 {
+                Err(RuntimeError::IndexOutOfBounds { index: n as i64, len, span: None })
+            } else {
+                Ok(n as usize)
+            }
+        },
+        v => Err(RuntimeError::InvalidIndex { found: v.to_string(), span: None })
+    }
+}
+
+/// Picks the number that is `pick` compared to all the others,
+/// integers stay integers
+fn fold_numbers<'de>(name: &str, args: Vec<Value<'de>>, pick: Ordering) -> Result<Value<'de>, Error> {
+    let numeric = |v: Value<'de>| match v {
+        Value::Number(_) | Value::Integer(_) => Ok(v),
+        v => Err(RuntimeError::UnexpectedType { expected: "number", found: v.type_name(), span: None })
+    };
+    let mut args = args.into_iter();
+    let Some(first) = args.next() else {
+        return Err(invalid(name, "expected at least one number".to_string()))
+    };
+    let mut acc = numeric(first)?;
+    for a in args {
+        let a = numeric(a)?;
+        if a.partial_cmp(&acc) == Some(pick) {
+            acc = a;
+        }
+    }
+    Ok(acc)
+}
+
+/// Drops the fraction, the result has to fit into the integer
+fn to_integer(n: f64) -> Result<i64, Error> {
+    // 2^63, the smallest float above i64::MAX
+    const LIMIT: f64 = 9223372036854775808.0;
+    let whole = n.trunc();
+    if (-LIMIT..LIMIT).contains(&whole) {
+        Ok(whole as i64)
+    } else {
+        Err(invalid("to_integer", format!("{n} doesn't fit into an integer")))
+    }
+}
+
+fn invalid(name: &str, reason: String) -> Error {
+    RuntimeError::InvalidArgument { name: name.to_string(), reason, span: None }.into()
+}
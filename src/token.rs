@@ -5,12 +5,38 @@ pub struct Token<'de> {
     pub kind: TokenKind,
     pub offset: usize,
     pub origin: &'de str,
+    /// line of the token's start, starting at 1
+    pub line: usize,
+    /// column of the token's start in characters, starting at 1
+    pub column: usize,
+    /// whitespace and comments in front of the token, collected only by the lexer `with_trivia`
+    pub trivia: Vec<Trivia<'de>>,
+    /// lines of the `///` doc comments in front of the token, without the slashes
+    pub doc: Vec<&'de str>,
 }
 impl<'de> Token<'de> {
-    pub fn new(kind: TokenKind, origin: &'de str, offset: usize) -> Self {
-        Self { kind, origin, offset } 
+    pub fn new(kind: TokenKind, origin: &'de str, offset: usize, line: usize, column: usize) -> Self {
+        Self { kind, origin, offset, line, column, trivia: Vec::new(), doc: Vec::new() }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+    /// input skipped because of a lexical error
+    Invalid,
+}
+
+/// ## Trivia
+/// Part of the source that isn't a token, together with the tokens
+/// it reconstructs the source exactly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trivia<'de> {
+    pub kind: TriviaKind,
+    pub text: &'de str,
+    pub offset: usize,
+}
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenKind {
@@ -25,7 +51,13 @@ pub enum TokenKind {
     MINUS,
     PLUS,
     SEMICOLON,
+    COLON,
     STAR,
+    PERCENT,
+    AMPERSAND,
+    PIPE,
+    CARET,
+    TILDE,
 
     EQUAL,
     BANG_EQUAL,
@@ -36,10 +68,14 @@ pub enum TokenKind {
     GREATER,
     SLASH,
     BANG,
+    STAR_STAR,
+    LESS_LESS,
+    GREATER_GREATER,
 
     STRING,
     IDENT,
     NUMBER(f64),
+    INTEGER(i64),
 
     // keywords
     PRINT,
@@ -57,6 +93,11 @@ pub enum TokenKind {
     WHILE,
     BREAK,
     CONTINUE,
+    CLASS,
+    THIS,
+    SUPER,
+    IMPORT,
+    DIV,
 }
 impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -74,7 +115,13 @@ impl fmt::Display for Token<'_> {
             TokenKind::MINUS => write!(f, "MINUS {i} null"),
             TokenKind::PLUS => write!(f, "PLUS {i} null"),
             TokenKind::SEMICOLON => write!(f, "SEMICOLON {i} null"),
+            TokenKind::COLON => write!(f, "COLON {i} null"),
             TokenKind::STAR => write!(f, "STAR {i} null"),
+            TokenKind::PERCENT => write!(f, "PERCENT {i} null"),
+            TokenKind::AMPERSAND => write!(f, "AMPERSAND {i} null"),
+            TokenKind::PIPE => write!(f, "PIPE {i} null"),
+            TokenKind::CARET => write!(f, "CARET {i} null"),
+            TokenKind::TILDE => write!(f, "TILDE {i} null"),
             TokenKind::EQUAL => write!(f, "EQUAL {i} null"),
             TokenKind::BANG_EQUAL => write!(f, "BANG_EQUAL {i} null"),
             TokenKind::EQUAL_EQUAL => write!(f, "EQUAL_EQUAL {i} null"),
@@ -84,9 +131,14 @@ impl fmt::Display for Token<'_> {
             TokenKind::GREATER => write!(f, "GREATER {i} null"),
             TokenKind::SLASH => write!(f, "SLASH {i} null"),
             TokenKind::BANG => write!(f, "BANG {i} null"),
+            TokenKind::STAR_STAR => write!(f, "STAR_STAR {i} null"),
+            TokenKind::LESS_LESS => write!(f, "LESS_LESS {i} null"),
+            TokenKind::GREATER_GREATER => write!(f, "GREATER_GREATER {i} null"),
             TokenKind::STRING => write!(f, "STRING \"{}\" {}", unescape(i), unescape(i)),
             TokenKind::IDENT => write!(f, "IDENTIFIER {i} null"),
             TokenKind::NUMBER(n) => write!(f, "NUMBER {i} {}", format_num(n)),
+            // integers are printed like whole numbers for the `tokenize` output
+            TokenKind::INTEGER(n) => write!(f, "NUMBER {i} {}.0", n.unsigned_abs()),
             TokenKind::PRINT => write!(f, "PRINT {i} null"),
             TokenKind::AND => write!(f, "AND {i} null"),
             TokenKind::ELSE => write!(f, "ELSE {i} null"),
@@ -102,6 +154,11 @@ impl fmt::Display for Token<'_> {
             TokenKind::WHILE => write!(f, "WHILE {i} null"),
             TokenKind::BREAK => write!(f, "BREAK {i} null"),
             TokenKind::CONTINUE => write!(f, "CONTINUE {i} null"),
+            TokenKind::CLASS => write!(f, "CLASS {i} null"),
+            TokenKind::THIS => write!(f, "THIS {i} null"),
+            TokenKind::SUPER => write!(f, "SUPER {i} null"),
+            TokenKind::IMPORT => write!(f, "IMPORT {i} null"),
+            TokenKind::DIV => write!(f, "DIV {i} null"),
         }
     }
 }
@@ -112,7 +169,6 @@ pub fn format_num(n: f64) -> String {
 
 pub fn unescape<'de>(s: &'de str) -> Cow<'de, str> {
     // TODO change this latter with better escapings
-    let mut chars = s.chars();
     let mut ret = String::new();
     enum State {
         Starting,
@@ -120,7 +176,7 @@ pub fn unescape<'de>(s: &'de str) -> Cow<'de, str> {
         Escaping,
     }
     let mut state = State::Starting;
-    while let Some(c) = chars.next() {
+    for c in s.chars() {
         match c {
             '\\' => match state {
                 State::Escaping => {
@@ -154,4 +210,23 @@ pub fn unescape<'de>(s: &'de str) -> Cow<'de, str> {
         }
     }
     Cow::Owned(ret)
-}
\ No newline at end of file
+}
+
+/// ## Quotes the string
+/// Writes the string as a literal that `unescape` reads back as the same string
+pub fn quote(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
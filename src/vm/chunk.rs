@@ -0,0 +1,222 @@
+use std::{fmt, rc::Rc};
+
+use miette::SourceSpan;
+
+use crate::evaluator::Value;
+
+/// ## Single instruction
+/// Operands are indexes into the pools of the chunk or jump targets
+/// (absolute positions in the code of the chunk)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// pushes the constant
+    Constant(u32),
+    Nil,
+    True,
+    False,
+    /// pushes the result of the statement that has no value
+    NoVal,
+    Pop,
+    /// pops n values
+    PopN(u16),
+    /// removes n values right under the top of the stack
+    Slide(u16),
+
+    /// globals are accessed by the name from the names pool
+    GetGlobal(u32),
+    /// same as `GetGlobal` but reports missing function
+    GetGlobalFn(u32),
+    SetGlobal(u32),
+    DefineGlobal(u32),
+    /// locals are accessed by the slot, name is kept only for errors
+    GetLocal { depth: u16, slot: u16, name: u32 },
+    SetLocal { depth: u16, slot: u16, name: u32 },
+    DefineLocal(u16),
+
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// `div`, quotient rounded down
+    IntDiv,
+    Mod,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Neg,
+    Not,
+    BitNot,
+    Print,
+
+    Jump(u32),
+    /// jumps if the top of the stack is falsy, does not pop it
+    JumpIfFalse(u32),
+    /// jumps if the top of the stack is truthy, does not pop it
+    JumpIfTrue(u32),
+
+    /// forks the environment
+    PushScope,
+    /// drops n innermost environments
+    PopScope(u16),
+
+    /// creates list from n values on the stack
+    List(u32),
+    /// creates map from n key and value pairs on the stack
+    Map(u32),
+    Index,
+    SetIndex,
+
+    /// creates closure of the function from the functions pool
+    Closure(u32),
+    /// calls the value under n arguments
+    Call(u16),
+    /// creates class from the closures of its methods on the stack,
+    /// the superclass is right under them if the class `inherits`
+    Class { name: u32, methods: u16, inherits: bool },
+    /// replaces the superclass and `this` under it with the bound method of the superclass
+    GetSuper(u32),
+    /// pushes the namespace of the module linked under the path from the names pool
+    Import(u32),
+    Return,
+
+    /// statement was used as a value
+    NoValue,
+    InvalidAssignment,
+}
+
+impl Op {
+    /// ## Stack effect
+    /// How many values the instruction leaves on the stack compared to before it
+    pub fn stack_effect(&self) -> isize {
+        match self {
+            Op::Constant(_) | Op::Nil | Op::True | Op::False | Op::NoVal => 1,
+            Op::Pop => -1,
+            Op::PopN(n) | Op::Slide(n) => -(*n as isize),
+            Op::GetGlobal(_) | Op::GetGlobalFn(_) | Op::GetLocal { .. } => 1,
+            // assignment replaces the value with nil, declaration with no value
+            Op::SetGlobal(_) | Op::SetLocal { .. } | Op::DefineGlobal(_) | Op::DefineLocal(_) => 0,
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::IntDiv | Op::Mod | Op::Pow => -1,
+            Op::BitAnd | Op::BitOr | Op::BitXor | Op::Shl | Op::Shr => -1,
+            Op::Equal | Op::NotEqual | Op::Less | Op::LessEqual | Op::Greater | Op::GreaterEqual => -1,
+            Op::Neg | Op::Not | Op::BitNot | Op::Print => 0,
+            Op::Jump(_) | Op::JumpIfFalse(_) | Op::JumpIfTrue(_) => 0,
+            Op::PushScope | Op::PopScope(_) => 0,
+            Op::List(n) => 1 - *n as isize,
+            Op::Map(n) => 1 - 2 * *n as isize,
+            Op::Index => -1,
+            Op::SetIndex => -2,
+            Op::Closure(_) => 1,
+            Op::Call(n) => -(*n as isize),
+            Op::Class { methods, inherits, .. } => 1 - *methods as isize - *inherits as isize,
+            Op::GetSuper(_) => -1,
+            Op::Import(_) => 1,
+            Op::Return => -1,
+            Op::NoValue => 0,
+            // fails before the assignment could produce the value
+            Op::InvalidAssignment => 1,
+        }
+    }
+}
+
+/// ## Compiled code
+/// Instructions together with the pools they refer to,
+/// every instruction remembers the span of the expression it was compiled from
+#[derive(Debug, Default)]
+pub struct Chunk<'de> {
+    pub(crate) code: Vec<Op>,
+    pub(crate) spans: Vec<SourceSpan>,
+    pub(crate) constants: Vec<Value<'de>>,
+    pub(crate) names: Vec<&'de str>,
+    pub(crate) functions: Vec<Rc<Function<'de>>>,
+}
+
+impl<'de> Chunk<'de> {
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, op: Op, span: SourceSpan) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    pub(crate) fn constant(&mut self, value: Value<'de>) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    pub(crate) fn name(&mut self, name: &'de str) -> usize {
+        match self.names.iter().position(|n| *n == name) {
+            Some(i) => i,
+            None => {
+                self.names.push(name);
+                self.names.len() - 1
+            }
+        }
+    }
+
+    pub(crate) fn function(&mut self, function: Function<'de>) -> usize {
+        self.functions.push(Rc::new(function));
+        self.functions.len() - 1
+    }
+}
+
+impl fmt::Display for Chunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, op) in self.code.iter().enumerate() {
+            let detail = match op {
+                Op::Constant(c) => format!(" ({})", self.constants[*c as usize]),
+                Op::GetGlobal(n) | Op::GetGlobalFn(n) | Op::SetGlobal(n) | Op::DefineGlobal(n) |
+                Op::GetLocal { name: n, .. } | Op::SetLocal { name: n, .. } |
+                Op::Class { name: n, .. } | Op::GetSuper(n) | Op::Import(n) => format!(" ({})", self.names[*n as usize]),
+                Op::Closure(fun) => format!(" (fun {})", self.functions[*fun as usize].name),
+                _ => String::new(),
+            };
+            writeln!(f, "{i:04} {:>5} {op:?}{detail}", self.spans[i].offset())?;
+        }
+        for fun in &self.functions {
+            write!(f, "\n{fun}")?;
+        }
+        Ok(())
+    }
+}
+
+/// ## Compiled function
+/// Arguments are stored in the first slots of the function's scope
+#[derive(Debug)]
+pub struct Function<'de> {
+    pub(crate) name: &'de str,
+    pub(crate) arity: usize,
+    pub(crate) chunk: Chunk<'de>,
+}
+
+impl<'de> Function<'de> {
+    pub fn name(&self) -> &'de str {
+        self.name
+    }
+
+    pub fn chunk(&self) -> &Chunk<'de> {
+        &self.chunk
+    }
+}
+
+impl fmt::Display for Function<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "== {} ({}) ==", self.name, self.arity)?;
+        write!(f, "{}", self.chunk)
+    }
+}
@@ -0,0 +1,304 @@
+use miette::{Error, SourceSpan};
+
+use crate::{error::CompileError, evaluator::Value, exptree::ExprTree, resolver::Binding};
+
+use super::chunk::{Chunk, Function, Op};
+
+/// ## Compilable node
+/// Implemented by every node of the expression tree, emits the code
+/// that leaves exactly one value (the value of the node) on the stack
+pub trait Compile<'de> {
+    fn compile(&self, compiler: &mut Compiler<'de>);
+}
+
+/// Loop that `break` and `continue` jump out of
+struct LoopFrame {
+    /// stack depth and number of open scopes when the loop's block starts
+    depth: usize,
+    scopes: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// ## Bytecode compiler
+/// Compiles resolved expression tree into a chunk, each function gets its own compiler.
+///
+/// The compiler tracks the depth of the value stack and the open scopes
+/// so control flow jumping out of the middle of an expression can clean up after itself.
+///
+/// Scopes that declare no variables are never forked at runtime,
+/// resolved depths of locals are shifted to skip them
+#[derive(Default)]
+pub struct Compiler<'de> {
+    chunk: Chunk<'de>,
+    depth: usize,
+    /// open scopes from the outermost one, including scopes of enclosing functions,
+    /// `true` for the ones that exist at runtime
+    scopes: Vec<bool>,
+    loops: Vec<LoopFrame>,
+    /// first limit of the bytecode the program ran into
+    error: Option<CompileError>,
+}
+
+impl<'de> Compiler<'de> {
+    /// ## Compiles the program
+    /// Program is compiled into the function that returns the value of the last statement,
+    /// programs that don't fit into the bytecode are reported as errors
+    pub fn compile_script(stmts: &[ExprTree<'de>]) -> Result<Function<'de>, Error> {
+        let mut compiler = Compiler::default();
+        let span = SourceSpan::from(0..0);
+        match stmts.split_last() {
+            Some((last, stmts)) => {
+                for stmt in stmts {
+                    stmt.compile(&mut compiler);
+                    compiler.emit(Op::Pop, stmt.span());
+                }
+                last.compile(&mut compiler);
+            },
+            None => { compiler.emit(Op::Nil, span); }
+        }
+        compiler.emit(Op::Return, span);
+        match compiler.error {
+            Some(e) => Err(e.into()),
+            None => Ok(Function { name: "script", arity: 0, chunk: compiler.chunk }),
+        }
+    }
+
+    /// ## Compiles the function declared at the current position
+    /// Arguments are already defined by the call in their own scope, body is compiled as usual
+    pub fn compile_function(&mut self, name: &'de str, arity: usize, body: &ExprTree<'de>) -> Function<'de> {
+        let mut scopes = self.scopes.clone();
+        scopes.push(true);
+        let mut compiler = Compiler { scopes, ..Compiler::default() };
+        compiler.operand(body);
+        compiler.emit(Op::Return, body.span());
+        if let Some(e) = compiler.error {
+            self.error.get_or_insert(e);
+        }
+        Function { name, arity, chunk: compiler.chunk }
+    }
+
+    /// ## Compiles the method declared at the current position
+    /// Methods run in the scope holding `this` that is forked when the method is bound
+    pub fn compile_method(&mut self, name: &'de str, arity: usize, body: &ExprTree<'de>) -> Function<'de> {
+        self.scopes.push(true);
+        let fun = self.compile_function(name, arity, body);
+        self.scopes.pop();
+        fun
+    }
+
+    /// ## Emits instruction
+    /// Returns its position so jumps can be patched later
+    pub fn emit(&mut self, op: Op, span: SourceSpan) -> usize {
+        self.depth = (self.depth as isize + op.stack_effect()) as usize;
+        self.chunk.push(op, span)
+    }
+
+    /// ## Compiles the operand
+    /// Statements can't be used as values, they report an error right after they run
+    pub fn operand(&mut self, expr: &ExprTree<'de>) {
+        expr.compile(self);
+        if !expr.has_value() {
+            self.emit(Op::NoValue, expr.span());
+        }
+    }
+
+    /// ## Position of the next instruction
+    pub fn here(&self) -> u32 {
+        self.chunk.len() as u32
+    }
+
+    /// ## Points the jump at the next instruction
+    pub fn patch_jump(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.chunk.code[at] {
+            Op::Jump(t) | Op::JumpIfFalse(t) | Op::JumpIfTrue(t) => *t = target,
+            op => unreachable!("{op:?} is not a jump"),
+        }
+    }
+
+    /// ## Current depth of the stack
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// ## Restores depth of the stack
+    /// Used where branches of the code meet or after jumps that never fall through
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+    }
+
+    pub fn constant(&mut self, value: Value<'de>, span: SourceSpan) {
+        let c = self.chunk.constant(value);
+        let c = self.narrow(c, "constants in one function", span);
+        self.emit(Op::Constant(c), span);
+    }
+
+    pub fn name(&mut self, name: &'de str, span: SourceSpan) -> u32 {
+        let n = self.chunk.name(name);
+        self.narrow(n, "names in one function", span)
+    }
+
+    pub fn function(&mut self, function: Function<'de>, span: SourceSpan) -> u32 {
+        let f = self.chunk.function(function);
+        self.narrow(f, "functions in one function", span)
+    }
+
+    /// ## Reads the variable
+    pub fn get_var(&mut self, name: &'de str, binding: Binding, span: SourceSpan) {
+        let n = self.name(name, span);
+        match binding {
+            Binding::Global => self.emit(Op::GetGlobal(n), span),
+            Binding::Local { depth, slot } => {
+                let (depth, slot) = self.local(depth, slot, span);
+                self.emit(Op::GetLocal { depth, slot, name: n }, span)
+            },
+        };
+    }
+
+    /// ## Reads the function to call
+    /// same as `get_var` but missing global is reported as missing function
+    pub fn get_fn(&mut self, name: &'de str, binding: Binding, span: SourceSpan) {
+        match binding {
+            Binding::Global => {
+                let n = self.name(name, span);
+                self.emit(Op::GetGlobalFn(n), span);
+            },
+            binding => self.get_var(name, binding, span),
+        }
+    }
+
+    /// ## Assigns the value on top of the stack to the variable
+    pub fn set_var(&mut self, name: &'de str, binding: Binding, span: SourceSpan) {
+        let n = self.name(name, span);
+        match binding {
+            Binding::Global => self.emit(Op::SetGlobal(n), span),
+            Binding::Local { depth, slot } => {
+                let (depth, slot) = self.local(depth, slot, span);
+                self.emit(Op::SetLocal { depth, slot, name: n }, span)
+            },
+        };
+    }
+
+    /// ## Declares the variable with the value on top of the stack
+    pub fn define_var(&mut self, name: &'de str, binding: Binding, span: SourceSpan) {
+        match binding {
+            Binding::Global => {
+                let n = self.name(name, span);
+                self.emit(Op::DefineGlobal(n), span)
+            },
+            Binding::Local { slot, .. } => {
+                let slot = self.narrow(slot, "local variables in one scope", span);
+                self.emit(Op::DefineLocal(slot), span)
+            },
+        };
+    }
+
+    /// ## Opens new scope
+    /// mirrors the scope opened by the resolver, the environment
+    /// is forked only if the scope `declares` any variables
+    pub fn begin_scope(&mut self, declares: bool, span: SourceSpan) {
+        self.scopes.push(declares);
+        if declares {
+            self.emit(Op::PushScope, span);
+        }
+    }
+
+    pub fn end_scope(&mut self, span: SourceSpan) {
+        if self.scopes.pop().expect("scope was opened") {
+            self.emit(Op::PopScope(1), span);
+        }
+    }
+
+    /// ## Depth and slot of the local at runtime
+    /// resolved depth counts all scopes, only the forked ones exist at runtime
+    fn local(&mut self, depth: usize, slot: usize, span: SourceSpan) -> (u16, u16) {
+        let depth = self.scopes.iter().rev().take(depth).filter(|forked| **forked).count();
+        let depth = self.narrow(depth, "nested scopes", span);
+        (depth, self.narrow(slot, "local variables in one scope", span))
+    }
+
+    /// ## Operand of the instruction
+    /// Records the error if the number doesn't fit into the operand,
+    /// compilation goes on so the error doesn't have to be threaded through
+    pub(crate) fn narrow<T: TryFrom<usize> + Default>(&mut self, n: usize, what: &'static str, span: SourceSpan) -> T {
+        T::try_from(n).unwrap_or_else(|_| {
+            self.error.get_or_insert(CompileError::TooMany { what, span });
+            T::default()
+        })
+    }
+
+    /// Number of forked scopes opened since `since` open scopes
+    fn forked_since(&self, since: usize) -> usize {
+        self.scopes[since..].iter().filter(|forked| **forked).count()
+    }
+
+    /// ## Starts the loop's block
+    /// `break` and `continue` unwind the stack and scopes back to this point
+    pub fn begin_loop(&mut self) {
+        self.loops.push(LoopFrame { depth: self.depth, scopes: self.scopes.len(), breaks: Vec::new(), continues: Vec::new() });
+    }
+
+    /// ## Patches all `continue` jumps to the next instruction
+    pub fn patch_continues(&mut self) {
+        let continues = std::mem::take(&mut self.loops.last_mut().expect("inside of a loop").continues);
+        for at in continues {
+            self.patch_jump(at);
+        }
+    }
+
+    /// ## Ends the loop
+    /// Patches all `break` jumps to the next instruction
+    pub fn end_loop(&mut self) {
+        let frame = self.loops.pop().expect("inside of a loop");
+        for at in frame.breaks {
+            self.patch_jump(at);
+        }
+    }
+
+    /// ## Breaks out of the loop
+    /// The value on top of the stack becomes the value of the loop
+    pub fn emit_break(&mut self, span: SourceSpan) {
+        let depth = self.depth;
+        let frame = self.loops.last().expect("resolved break is inside of a loop");
+        let (under, scopes) = (depth - 1 - frame.depth, self.forked_since(frame.scopes));
+        if under > 0 {
+            let under = self.narrow(under, "values on the stack", span);
+            self.emit(Op::Slide(under), span);
+        }
+        if scopes > 0 {
+            let scopes = self.narrow(scopes, "nested scopes", span);
+            self.emit(Op::PopScope(scopes), span);
+        }
+        let at = self.emit(Op::Jump(0), span);
+        self.loops.last_mut().expect("checked above").breaks.push(at);
+        // following code is never reached, it continues as if break was a value
+        self.depth = depth;
+    }
+
+    /// ## Continues with the next iteration of the loop
+    pub fn emit_continue(&mut self, span: SourceSpan) {
+        let depth = self.depth;
+        let frame = self.loops.last().expect("resolved continue is inside of a loop");
+        let (above, scopes) = (depth - frame.depth, self.forked_since(frame.scopes));
+        if above > 0 {
+            let above = self.narrow(above, "values on the stack", span);
+            self.emit(Op::PopN(above), span);
+        }
+        if scopes > 0 {
+            let scopes = self.narrow(scopes, "nested scopes", span);
+            self.emit(Op::PopScope(scopes), span);
+        }
+        let at = self.emit(Op::Jump(0), span);
+        self.loops.last_mut().expect("checked above").continues.push(at);
+        self.depth = depth + 1;
+    }
+
+    /// ## Returns from the function
+    /// The value on top of the stack is the result of the call
+    pub fn emit_return(&mut self, span: SourceSpan) {
+        let depth = self.depth;
+        self.emit(Op::Return, span);
+        self.depth = depth;
+    }
+}
@@ -0,0 +1,437 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, rc::Rc};
+
+use miette::Error;
+
+use crate::{context::{Captured, Capturing, CtxTree}, error::RuntimeError, evaluator::{float_rem, Class, Event, Value}, exptree::ExprTree};
+
+pub mod chunk;
+pub mod compiler;
+
+pub use chunk::*;
+pub use compiler::*;
+
+/// ## Compiled function value
+/// Compiled function together with the environment it was declared in
+pub struct Closure<'de> {
+    fun: Rc<Function<'de>>,
+    env: Captured<'de>
+}
+
+impl<'de> Closure<'de> {
+    pub fn new(fun: Rc<Function<'de>>, env: CtxTree<'de>) -> Self {
+        Self { fun, env: Captured::new(env) }
+    }
+
+    pub fn name(&self) -> &'de str {
+        self.fun.name
+    }
+
+    /// ## Calls the function outside of the machine
+    /// Runs the function on its own machine, used when the function
+    /// is called by other backends
+    pub fn call(&self, args: Vec<Value<'de>>) -> Result<Value<'de>, Error> {
+        if self.fun.arity != args.len() {
+            return Err(RuntimeError::ArityMismatch {
+                name: self.name().to_string(),
+                expected: self.fun.arity,
+                found: args.len(),
+                span: None
+            }.into())
+        }
+        let env = self.env.ctx().fork();
+        for (slot, v) in args.into_iter().enumerate() {
+            env.define(slot, v);
+        }
+        Vm::new().execute(self.fun.clone(), env)
+    }
+
+    /// ## Binds the method
+    /// see `evaluator::Closure::bind`
+    pub fn bind(&self, this: Value<'de>) -> Self {
+        let env = self.env.ctx().fork();
+        env.define(0, this);
+        Self::new(self.fun.clone(), env)
+    }
+}
+
+impl<'de> Capturing<'de> for Closure<'de> {
+    fn env(&self) -> &Captured<'de> {
+        &self.env
+    }
+
+    fn with_env(&self, env: Captured<'de>) -> Self {
+        Self { fun: self.fun.clone(), env }
+    }
+}
+
+// see `evaluator::Closure`
+impl PartialEq for Closure<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.fun, &other.fun) && self.env.same(&other.env)
+    }
+}
+
+impl PartialOrd for Closure<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+impl fmt::Debug for Closure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Closure({})", self.name())
+    }
+}
+
+impl fmt::Display for Closure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
+
+/// Function being executed
+struct Frame<'de> {
+    fun: Rc<Function<'de>>,
+    ip: usize,
+    /// position of the callee on the stack, everything above belongs to the call
+    base: usize,
+    env: CtxTree<'de>,
+    /// returned instead of the function's result, initializers return the new instance
+    instance: Option<Value<'de>>,
+}
+
+/// ## Stack based virtual machine
+/// Runs the code compiled from the resolved expression tree, variables
+/// live in the same context tree as in the tree-walking evaluator.
+///
+/// Globals are searched from the environment of the running function,
+/// functions of imported modules see the globals of their own module
+pub struct Vm<'de> {
+    stack: Vec<Value<'de>>,
+    frames: Vec<Frame<'de>>,
+}
+
+impl Default for Vm<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'de> Vm<'de> {
+    pub fn new() -> Self {
+        Self { stack: Vec::new(), frames: Vec::new() }
+    }
+
+    /// ## Runs the program
+    /// Compiles the resolved program and runs it in given context,
+    /// returns the value of the last statement
+    pub fn run(&mut self, ctx: &CtxTree<'de>, stmts: &[ExprTree<'de>]) -> Result<Value<'de>, Error> {
+        let script = Rc::new(Compiler::compile_script(stmts)?);
+        self.execute(script, ctx.clone())
+    }
+
+    /// ## Executes compiled function
+    pub fn execute(&mut self, fun: Rc<Function<'de>>, env: CtxTree<'de>) -> Result<Value<'de>, Error> {
+        self.stack.clear();
+        self.frames.clear();
+        self.stack.push(Value::Nil); // script is not called by anyone
+        self.frames.push(Frame { fun, ip: 0, base: 0, env, instance: None });
+        let res = self.run_frames();
+        if res.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+        }
+        res
+    }
+
+    fn run_frames(&mut self) -> Result<Value<'de>, Error> {
+        loop {
+            let frame = self.frames.last_mut().expect("there is always a frame while running");
+            let fun = frame.fun.clone();
+            let ip = frame.ip;
+            frame.ip += 1;
+            let op = fun.chunk.code[ip];
+            let span = fun.chunk.spans[ip];
+
+            match op {
+                Op::Constant(c) => self.stack.push(fun.chunk.constants[c as usize].clone()),
+                Op::Nil => self.stack.push(Value::Nil),
+                Op::True => self.stack.push(Value::Bool(true)),
+                Op::False => self.stack.push(Value::Bool(false)),
+                Op::NoVal => self.stack.push(Value::Event(Event::NoVal)),
+                Op::Pop => { self.pop(); },
+                Op::PopN(n) => { self.stack.truncate(self.stack.len() - n as usize); },
+                Op::Slide(n) => {
+                    let top = self.pop();
+                    self.stack.truncate(self.stack.len() - n as usize);
+                    self.stack.push(top);
+                },
+
+                Op::GetGlobal(n) => {
+                    let name = fun.chunk.names[n as usize];
+                    let v = self.env().search(name)
+                        .ok_or(RuntimeError::UndefinedVariable { name: name.to_string(), span: Some(span) })?;
+                    self.stack.push(v);
+                },
+                Op::GetGlobalFn(n) => {
+                    let name = fun.chunk.names[n as usize];
+                    let v = self.env().search(name)
+                        .ok_or(RuntimeError::UndefinedFunction { name: name.to_string(), span: Some(span) })?;
+                    self.stack.push(v);
+                },
+                Op::SetGlobal(n) => {
+                    let name = fun.chunk.names[n as usize];
+                    let v = self.pop();
+                    self.env().set(name, v)
+                        .map_err(|_| RuntimeError::UndefinedVariable { name: name.to_string(), span: Some(span) })?;
+                    self.stack.push(Value::Nil);
+                },
+                Op::DefineGlobal(n) => {
+                    let v = self.pop();
+                    self.env().insert(fun.chunk.names[n as usize], v);
+                    self.stack.push(Value::Event(Event::NoVal));
+                },
+                Op::GetLocal { depth, slot, name } => {
+                    let v = self.env().get_at(depth as usize, slot as usize)
+                        .ok_or_else(|| RuntimeError::UndefinedVariable { name: fun.chunk.names[name as usize].to_string(), span: Some(span) })?;
+                    self.stack.push(v);
+                },
+                Op::SetLocal { depth, slot, name } => {
+                    let v = self.pop();
+                    self.env().set_at(depth as usize, slot as usize, v)
+                        .map_err(|_| RuntimeError::UndefinedVariable { name: fun.chunk.names[name as usize].to_string(), span: Some(span) })?;
+                    self.stack.push(Value::Nil);
+                },
+                Op::DefineLocal(slot) => {
+                    let v = self.pop();
+                    self.env().define(slot as usize, v);
+                    self.stack.push(Value::Event(Event::NoVal));
+                },
+
+                Op::Add => self.binary(|l, r| Value::Number(l + r), |l, r| l + r, span)?,
+                Op::Sub => self.binary(|l, r| Value::Number(l - r), |l, r| l - r, span)?,
+                Op::Mul => self.binary(|l, r| Value::Number(l * r), |l, r| l * r, span)?,
+                Op::Div => self.binary(|l, r| Value::Number(l / r), |l, r| l / r, span)?,
+                Op::IntDiv => self.binary(|l, r| Value::Number((l / r).floor()), Value::int_div, span)?,
+                Op::Mod => self.binary(|l, r| Value::Number(float_rem(l, r)), |l, r| l % r, span)?,
+                Op::Pow => self.binary(|l, r| Value::Number(l.powf(r)), Value::pow, span)?,
+                Op::BitAnd => self.operation(|l, r| l & r, span)?,
+                Op::BitOr => self.operation(|l, r| l | r, span)?,
+                Op::BitXor => self.operation(|l, r| l ^ r, span)?,
+                Op::Shl => self.operation(|l, r| l << r, span)?,
+                Op::Shr => self.operation(|l, r| l >> r, span)?,
+                Op::Equal => self.binary(|l, r| Value::Bool(l == r), |l, r| Ok(Value::Bool(l == r)), span)?,
+                Op::NotEqual => self.binary(|l, r| Value::Bool(l != r), |l, r| Ok(Value::Bool(l != r)), span)?,
+                Op::Less => self.binary(|l, r| Value::Bool(l < r), |l, r| Ok(Value::Bool(l < r)), span)?,
+                Op::LessEqual => self.binary(|l, r| Value::Bool(l <= r), |l, r| Ok(Value::Bool(l <= r)), span)?,
+                Op::Greater => self.binary(|l, r| Value::Bool(l > r), |l, r| Ok(Value::Bool(l > r)), span)?,
+                Op::GreaterEqual => self.binary(|l, r| Value::Bool(l >= r), |l, r| Ok(Value::Bool(l >= r)), span)?,
+                Op::Neg => {
+                    let v = (-self.pop()).map_err(|e| e.at(span))?;
+                    self.stack.push(v);
+                },
+                Op::Not => {
+                    let v = (!self.pop()).map_err(|e| e.at(span))?;
+                    self.stack.push(v);
+                },
+                Op::BitNot => {
+                    let v = self.pop().bit_not().map_err(|e| e.at(span))?;
+                    self.stack.push(v);
+                },
+                Op::Print => {
+                    print!("{}", self.pop());
+                    self.stack.push(Value::Event(Event::NoVal));
+                },
+
+                Op::Jump(target) => self.jump(target),
+                Op::JumpIfFalse(target) => if !bool::from(self.peek().clone()) { self.jump(target) },
+                Op::JumpIfTrue(target) => if bool::from(self.peek().clone()) { self.jump(target) },
+
+                Op::PushScope => {
+                    let frame = self.frame();
+                    frame.env = frame.env.fork();
+                },
+                Op::PopScope(n) => {
+                    let frame = self.frame();
+                    for _ in 0..n {
+                        frame.env = frame.env.parent().expect("scope was pushed before");
+                    }
+                },
+
+                Op::List(n) => {
+                    let items = self.stack.split_off(self.stack.len() - n as usize);
+                    self.stack.push(Value::list(items));
+                },
+                Op::Map(n) => {
+                    let map = Value::map(BTreeMap::new());
+                    let entries = self.stack.split_off(self.stack.len() - 2 * n as usize);
+                    for entry in entries.chunks_exact(2) {
+                        map.set_index(&entry[0], entry[1].clone()).map_err(|e| e.at(span))?;
+                    }
+                    self.stack.push(map);
+                },
+                Op::Index => {
+                    let index = self.pop();
+                    let target = self.pop();
+                    let v = target.get_index(&index).map_err(|e| e.at(span))?;
+                    self.stack.push(v);
+                },
+                Op::SetIndex => {
+                    let index = self.pop();
+                    let target = self.pop();
+                    let v = self.pop();
+                    target.set_index(&index, v).map_err(|e| e.at(span))?;
+                    self.stack.push(Value::Nil);
+                },
+
+                Op::Closure(f) => {
+                    let closure = Closure::new(fun.chunk.functions[f as usize].clone(), self.env().clone());
+                    self.stack.push(Value::CompiledFunction(Rc::new(closure)));
+                },
+                Op::Call(argc) => self.call(argc as usize).map_err(|e| RuntimeError::locate(e, span))?,
+                Op::Class { name, methods, inherits } => {
+                    let methods = self.stack.split_off(self.stack.len() - methods as usize);
+                    let superclass = inherits.then(|| self.pop());
+                    let methods: HashMap<_, _> = methods.into_iter()
+                        .map(|m| match m {
+                            Value::CompiledFunction(ref fun) => (fun.name(), m),
+                            _ => unreachable!("methods are compiled closures"),
+                        })
+                        .collect();
+                    let class = Class::new(fun.chunk.names[name as usize], superclass, methods).map_err(|e| e.at(span))?;
+                    self.stack.push(Value::Class(Rc::new(class)));
+                },
+                Op::GetSuper(name) => {
+                    let this = self.pop();
+                    let superclass = self.pop();
+                    let v = superclass.get_super(fun.chunk.names[name as usize], this).map_err(|e| e.at(span))?;
+                    self.stack.push(v);
+                },
+                Op::Import(path) => {
+                    let path = fun.chunk.names[path as usize];
+                    let v = self.env().module(path)
+                        .ok_or(RuntimeError::ModuleNotLoaded { path: path.to_string(), span: Some(span) })?;
+                    self.stack.push(v);
+                },
+                Op::Return => {
+                    let v = self.pop();
+                    let frame = self.frames.pop().expect("returning from a frame");
+                    let v = frame.instance.unwrap_or(v);
+                    self.stack.truncate(frame.base);
+                    if self.frames.is_empty() {
+                        return Ok(v)
+                    }
+                    self.stack.push(v);
+                },
+
+                Op::NoValue => return Err(RuntimeError::NoValue { span: Some(span) }.into()),
+                Op::InvalidAssignment => return Err(RuntimeError::InvalidAssignmentTarget { span: Some(span) }.into()),
+            }
+        }
+    }
+
+    fn call(&mut self, argc: usize) -> Result<(), Error> {
+        let base = self.stack.len() - argc - 1;
+        match self.stack[base].clone() {
+            Value::CompiledFunction(closure) => {
+                if closure.fun.arity != argc {
+                    return Err(RuntimeError::ArityMismatch {
+                        name: closure.name().to_string(),
+                        expected: closure.fun.arity,
+                        found: argc,
+                        span: None
+                    }.into())
+                }
+                let env = closure.env.ctx().fork();
+                for (slot, v) in self.stack.drain(base + 1..).enumerate() {
+                    env.define(slot, v);
+                }
+                self.frames.push(Frame { fun: closure.fun.clone(), ip: 0, base, env, instance: None });
+            },
+            Value::NativeFunction(fun) => self.call_outside(base, |args| fun.call(args))?,
+            // functions of other backends run in their own interpreter
+            Value::Function(fun) => self.call_outside(base, |args| fun.call(args))?,
+            Value::LoweredFunction(fun) => self.call_outside(base, |args| fun.call(args))?,
+            Value::Class(class) => {
+                let (instance, init) = class.instantiate(argc)?;
+                let Some(init) = init else {
+                    self.stack.truncate(base);
+                    self.stack.push(instance);
+                    return Ok(())
+                };
+                // initializer is called in place of the class and returns the instance
+                let compiled = matches!(init, Value::CompiledFunction(_));
+                self.stack[base] = init;
+                self.call(argc)?;
+                match compiled {
+                    true => self.frame().instance = Some(instance),
+                    false => *self.stack.last_mut().expect("result of the initializer") = instance,
+                }
+            },
+            v => return Err(RuntimeError::NotCallable { target: v.type_name(), span: None }.into()),
+        }
+        Ok(())
+    }
+
+    /// ## Calls the function that doesn't run on the machine
+    /// Arguments are taken from the stack and the callee is replaced with the result
+    fn call_outside(&mut self, base: usize, call: impl FnOnce(Vec<Value<'de>>) -> Result<Value<'de>, Error>) -> Result<(), Error> {
+        let args = self.stack.split_off(base + 1);
+        let v = call(args)?;
+        self.stack.truncate(base);
+        self.stack.push(v);
+        Ok(())
+    }
+
+    /// ## Binary operation
+    /// two floats are handled by the `numbers` shortcut, everything else
+    /// including integers that may overflow by the general `op`
+    fn binary(
+        &mut self,
+        numbers: fn(f64, f64) -> Value<'de>,
+        op: fn(Value<'de>, Value<'de>) -> Result<Value<'de>, RuntimeError>,
+        span: miette::SourceSpan
+    ) -> Result<(), Error> {
+        let len = self.stack.len();
+        if let [Value::Number(l), Value::Number(r)] = self.stack[len - 2..] {
+            self.stack.pop();
+            self.stack[len - 2] = numbers(l, r);
+            return Ok(())
+        }
+        self.operation(op, span)
+    }
+
+    /// ## Operation on two values
+    /// Replaces the two values on top of the stack with the result
+    fn operation(
+        &mut self,
+        op: fn(Value<'de>, Value<'de>) -> Result<Value<'de>, RuntimeError>,
+        span: miette::SourceSpan
+    ) -> Result<(), Error> {
+        let r = self.pop();
+        let l = self.pop();
+        let v = op(l, r).map_err(|e| e.at(span))?;
+        self.stack.push(v);
+        Ok(())
+    }
+
+    fn frame(&mut self) -> &mut Frame<'de> {
+        self.frames.last_mut().expect("there is always a frame while running")
+    }
+
+    fn env(&mut self) -> &CtxTree<'de> {
+        &self.frame().env
+    }
+
+    fn jump(&mut self, target: u32) {
+        self.frame().ip = target as usize;
+    }
+
+    fn pop(&mut self) -> Value<'de> {
+        self.stack.pop().expect("compiler keeps the stack balanced")
+    }
+
+    fn peek(&self) -> &Value<'de> {
+        self.stack.last().expect("compiler keeps the stack balanced")
+    }
+}
@@ -0,0 +1,65 @@
+use lang_interpreter::docgen::{html, markdown, Module};
+
+
+const LIB: &str = "\
+// Library of helpers
+
+/// Adds two numbers
+/// together
+fun add(a, b) { a + b }
+
+// Multiplies
+// the numbers
+fun mul(a, b) {
+    fun helper() {}
+    a * b
+}
+
+// separated by a blank line
+
+fun undocumented() {}
+var square = fun (x) { x * x }; // trailing
+// not above a function
+var n = 1; fun inline() {}
+";
+
+#[test]
+fn functions_with_their_docs() {
+    let module = Module::new("lib.lang", LIB).unwrap();
+    let functions: Vec<_> = module.functions.iter().map(|f| (f.signature(), f.doc.to_vec())).collect();
+    assert_eq!(functions, vec![
+        ("fun add(a, b)".to_string(), vec!["Adds two numbers", "together"]),
+        ("fun mul(a, b)".to_string(), vec!["Multiplies", "the numbers"]),
+        ("fun undocumented()".to_string(), vec![]),
+        ("fun square(x)".to_string(), vec![]),
+        ("fun inline()".to_string(), vec![]),
+    ]);
+}
+
+#[test]
+fn markdown_page() {
+    let modules = [Module::new("lib.lang", "/// Greets\nfun greet(name) {}\nfun other() {}").unwrap()];
+    assert_eq!(markdown(&modules), "\
+# Documentation
+
+## lib.lang
+
+### `fun greet(name)`
+Greets
+
+### `fun other()`
+");
+}
+
+#[test]
+fn html_page_is_escaped() {
+    let modules = [Module::new("a<b>.lang", "// 1 < 2 & \"x\"\n// second\nfun f() {}").unwrap()];
+    let page = html(&modules);
+    assert!(page.contains("<h2>a&lt;b&gt;.lang</h2>\n<h3><code>fun f()</code></h3>\n<p>1 &lt; 2 &amp; &quot;x&quot;<br>\nsecond</p>\n"));
+    assert!(page.starts_with("<!DOCTYPE html>") && page.ends_with("</html>\n"));
+}
+
+#[test]
+fn syntax_errors_are_reported() {
+    assert!(Module::new("bad.lang", "fun f( {}").is_err());
+}
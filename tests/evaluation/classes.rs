@@ -0,0 +1,189 @@
+use lang_interpreter::{error::{ResolveError, RuntimeError}, evaluator::Value};
+
+use crate::{test_program_eval, test_resolve_error, test_runtime_error};
+
+test_program_eval!(class_fields, "
+    class Point {}
+    var p = Point();
+    p.x = 1;
+    p.y = 2;
+    p.x + p.y
+", Value::Integer(3));
+
+test_program_eval!(class_init_and_methods, "
+    class Counter {
+        init(start) {
+            this.count = start;
+        }
+
+        inc() {
+            this.count = this.count + 1;
+            return this;
+        }
+    }
+    Counter(5).inc().inc().count
+", Value::Integer(7));
+
+test_program_eval!(class_bound_method_keeps_this, "
+    class Greeter {
+        init(name) { this.name = name; }
+        greet() { \"hi \" + this.name }
+    }
+    var greet = Greeter(\"bob\").greet;
+    greet()
+", Value::String("hi bob".to_string()));
+
+test_program_eval!(class_init_returns_instance, "
+    class A {
+        init() {
+            this.a = 1;
+            return;
+            this.a = 2;
+        }
+    }
+    A().a
+", Value::Integer(1));
+
+test_resolve_error!(class_init_returns_value, "
+    class A {
+        init() {
+            return 2;
+        }
+    }
+", ResolveError::ReturnFromInitializer { .. });
+
+test_program_eval!(class_init_nested_function_returns_value, "
+    class A {
+        init() {
+            fun two() { return 2; }
+            this.a = two();
+        }
+    }
+    A().a
+", Value::Integer(2));
+
+test_program_eval!(class_instances_are_shared, "
+    class Box {}
+    var a = Box();
+    var b = a;
+    b.value = \"set\";
+    a.value
+", Value::String("set".to_string()));
+
+test_program_eval!(class_field_shadows_method, "
+    class A {
+        m() { 1 }
+    }
+    var a = A();
+    a.m = fun () { 2 };
+    a.m()
+", Value::Integer(2));
+
+test_program_eval!(class_this_in_closure, "
+    class A {
+        init() { this.n = 3; }
+        getter() {
+            return fun () { this.n };
+        }
+    }
+    A().getter()()
+", Value::Integer(3));
+
+test_program_eval!(class_inheritance, "
+    class Animal {
+        init(name) { this.name = name; }
+        speak() { this.name + \" makes a sound\" }
+        kind() { \"animal\" }
+    }
+    class Dog < Animal {
+        init(name) {
+            super.init(name);
+            this.tricks = 0;
+        }
+        speak() { super.speak() + \" and barks, it's an \" + this.kind() }
+    }
+    Dog(\"rex\").speak()
+", Value::String("rex makes a sound and barks, it's an animal".to_string()));
+
+test_program_eval!(class_inherited_init, "
+    class A {
+        init(x) { this.x = x; }
+    }
+    class B < A {}
+    B(4).x
+", Value::Integer(4));
+
+test_program_eval!(class_super_is_static, "
+    class A {
+        name() { \"A\" }
+    }
+    class B < A {
+        name() { \"B\" + super.name() }
+    }
+    class C < B {
+        name() { \"C\" + super.name() }
+    }
+    C().name()
+", Value::String("CBA".to_string()));
+
+test_program_eval!(class_declared_in_block, "
+    var res;
+    {
+        var base = 10;
+        class A {
+            get() { base }
+        }
+        class B < A {
+            get() { super.get() + 1 }
+        }
+        res = B().get();
+    }
+    res
+", Value::Integer(11));
+
+test_program_eval!(class_method_uses_class, "
+    class Node {
+        init(value) { this.value = value; }
+        twin() { Node(this.value) }
+    }
+    var a = Node(1);
+    var b = a.twin();
+    [a == b, a == a, b.value]
+", Value::from(vec![Value::Bool(false), Value::Bool(true), Value::Integer(1)]));
+
+test_program_eval!(class_index_by_name, "
+    class A {}
+    var a = A();
+    a[\"x\"] = 1;
+    a.x
+", Value::Integer(1));
+
+test_runtime_error!(class_undefined_property, "
+    class A {}
+    A().missing;
+", RuntimeError::UndefinedProperty { name, class, span: Some(_) } if name == "missing" && class == "A");
+
+test_runtime_error!(class_init_arity, "
+    class A {
+        init(a, b) {}
+    }
+    A(1);
+", RuntimeError::ArityMismatch { expected: 2, found: 1, .. });
+
+test_runtime_error!(class_without_init_takes_no_arguments, "
+    class A {}
+    A(1);
+", RuntimeError::ClassArityMismatch { class, found: 1, span: Some(_) } if class == "A");
+
+test_runtime_error!(class_invalid_superclass, "
+    var A = 1;
+    class B < A {}
+", RuntimeError::InvalidSuperclass { found: "integer", span: Some(_) });
+
+test_runtime_error!(class_not_indexable, "
+    class A {}
+    A.x;
+", RuntimeError::NotIndexable { target: "class", .. });
+
+test_resolve_error!(class_inherits_itself, "class A < A {}",
+    ResolveError::SelfInheritance { name, span } if name == "A" && span.offset() == 10);
@@ -0,0 +1,119 @@
+use lang_interpreter::error::ControlFlowError;
+use lang_interpreter::evaluator::Value;
+
+use crate::test_program_eval;
+use super::eval_program;
+
+test_program_eval!(return_from_loop, "
+    fun find(items, x) {
+        for (var i = 0; i < 10; i = i + 1) {
+            if (items[i] == x) { return i; }
+        }
+        return -1;
+    }
+    find([5, 6, 7, 8], 7)
+", Value::Integer(2));
+test_program_eval!(return_from_nested_loops, "
+    fun first_pair(n) {
+        var i = 0;
+        while (i < n) {
+            var j = 0;
+            while (j < n) {
+                if (i * j == 6) { return [i, j]; }
+                j = j + 1;
+            }
+            i = i + 1;
+        }
+    }
+    first_pair(5)[0] * 10 + first_pair(5)[1]
+", Value::Integer(23));
+test_program_eval!(return_from_nested_blocks, "
+    fun sign(x) {
+        {
+            { if (x < 0) { return -1; } }
+            if (x > 0) { return 1; }
+        }
+        0
+    }
+    [sign(-5), sign(5), sign(0)][0] + 10 * sign(3)
+", Value::Integer(9));
+test_program_eval!(return_inside_expression, "
+    fun f() {
+        var a = 1 + { return 2; };
+        return 3;
+    }
+    f()
+", Value::Integer(2));
+test_program_eval!(return_value_is_not_event, "
+    fun f() { return 1; }
+    var a = f();
+    a + 1
+", Value::Integer(2));
+test_program_eval!(return_stops_at_closure, "
+    fun outer() {
+        var inner = fun () { return 1; };
+        inner();
+        return 2;
+    }
+    outer()
+", Value::Integer(2));
+test_program_eval!(break_inside_expression, "
+    var sum = 0;
+    for (var i = 0; i < 10; i = i + 1) {
+        sum = sum + { if (i == 3) { break; } i };
+    }
+    sum
+", Value::Integer(3));
+test_program_eval!(continue_inside_expression, "
+    var sum = 0;
+    for (var i = 0; i < 5; i = i + 1) {
+        sum = sum + { if (i == 2) { continue; } i };
+    }
+    sum
+", Value::Integer(8));
+test_program_eval!(break_inner_loop_only, "
+    var count = 0;
+    for (var i = 0; i < 3; i = i + 1) {
+        while (true) { break; }
+        count = count + 1;
+    }
+    count
+", Value::Integer(3));
+
+fn assert_static_error(code: &str, keyword: &str) {
+    let err = eval_program(code).unwrap_err();
+    let err = err.downcast_ref::<ControlFlowError>()
+        .unwrap_or_else(|| panic!("expected control flow error, got: {err:?}"));
+    assert_eq!(err.keyword, keyword);
+}
+
+#[test]
+fn break_outside_loop() {
+    assert_static_error("break;", "break");
+    assert_static_error("if (true) { break 1; }", "break");
+}
+
+#[test]
+fn continue_outside_loop() {
+    assert_static_error("continue;", "continue");
+    assert_static_error("var a = 1 + { continue };", "continue");
+}
+
+#[test]
+fn break_from_function_inside_loop() {
+    assert_static_error("while (true) { fun f() { break; } }", "break");
+    assert_static_error("for (;;) { var f = fun () { continue; }; }", "continue");
+}
+
+#[test]
+fn return_outside_function() {
+    assert_static_error("return 1;", "return");
+    assert_static_error("while (true) { return; }", "return");
+}
+test_program_eval!(break_through_mixed_scopes, "
+    var total = 0;
+    for (var i = 0; i < 10; i = i + 1) {
+        { var a = i; { if (a == 2) { continue; } { var b = a * 2; { if (b > 10) { break; } } total = total + b; } } }
+    }
+    total
+", Value::Integer(26));
@@ -0,0 +1,87 @@
+use lang_interpreter::{backend::Backend, context::CtxTree, error::{CompileError, ResolveError, RuntimeError}};
+
+use crate::{test_resolve_error, test_runtime_error};
+
+use super::eval_program_in;
+
+test_resolve_error!(undefined_variable, "print a;", 
+    ResolveError::UndeclaredVariable { name, .. } if name == "a");
+test_resolve_error!(undefined_variable_assignment, "a = 1;", 
+    ResolveError::UndeclaredVariable { name, .. } if name == "a");
+test_resolve_error!(undefined_function, "test(1, 2);", 
+    ResolveError::UndeclaredFunction { name, .. } if name == "test");
+test_resolve_error!(undefined_variable_in_function, "fun f() { a }", 
+    ResolveError::UndeclaredVariable { name, .. } if name == "a");
+test_resolve_error!(undefined_variable_out_of_scope, "{ var a = 1; } a;", 
+    ResolveError::UndeclaredVariable { name, .. } if name == "a");
+test_resolve_error!(self_referencing_local, "{ var a = 1; { var a = a + 1; } }", 
+    ResolveError::SelfReferencingInitializer { name, .. } if name == "a");
+test_resolve_error!(self_referencing_global, "var a = [a];", 
+    ResolveError::SelfReferencingInitializer { name, .. } if name == "a");
+test_resolve_error!(self_referencing_closure, "{ var f = fun () { f() }; }", 
+    ResolveError::SelfReferencingInitializer { name, .. } if name == "f");
+test_resolve_error!(used_before_declaration, "print a; var a = 1;", 
+    ResolveError::UsedBeforeDeclaration { name, .. } if name == "a");
+test_resolve_error!(used_before_declaration_in_block, "{ a = 2; } var a = 1;", 
+    ResolveError::UsedBeforeDeclaration { name, .. } if name == "a");
+test_resolve_error!(called_before_declaration, "f(); fun f() {}", 
+    ResolveError::UsedBeforeDeclaration { name, .. } if name == "f");
+test_resolve_error!(duplicate_parameter, "fun f(a, b, a) {}", 
+    ResolveError::DuplicateParameter { name, .. } if name == "a");
+test_resolve_error!(duplicate_parameter_of_anonymous, "var f = fun (a, a) { a };", 
+    ResolveError::DuplicateParameter { name, .. } if name == "a");
+test_runtime_error!(arity_mismatch, "fun test(a, b) { } test(1);", 
+    RuntimeError::ArityMismatch { expected: 2, found: 1, .. });
+test_runtime_error!(type_mismatch, "1 + \"test\";", 
+    RuntimeError::TypeMismatch { lhs: "integer", rhs: "string", .. });
+test_runtime_error!(integer_overflow, "9223372036854775807 + 1;",
+    RuntimeError::IntegerOverflow { op, span: Some(_) } if op == "+");
+test_runtime_error!(integer_overflow_of_product, "var a = 4294967296; a * a;",
+    RuntimeError::IntegerOverflow { op, span: Some(span) } if op == "*" && span.offset() == 20);
+test_runtime_error!(integer_overflow_of_negation, "var a = -9223372036854775807 - 1; -a;",
+    RuntimeError::IntegerOverflow { op, .. } if op == "-");
+test_runtime_error!(integer_division_by_zero, "1 div 0;", RuntimeError::DivisionByZero { span: Some(_) });
+test_runtime_error!(modulo_by_zero, "1 % 0;", RuntimeError::DivisionByZero { span: Some(_) });
+test_runtime_error!(exponent_overflow, "2 ** 63;",
+    RuntimeError::IntegerOverflow { op, .. } if op == "**");
+test_runtime_error!(shift_overflow, "3 << 62;",
+    RuntimeError::IntegerOverflow { op, .. } if op == "<<");
+test_runtime_error!(negative_shift, "1 >> -1;",
+    RuntimeError::NegativeShift { op, span: Some(_) } if op == ">>");
+test_runtime_error!(bitwise_of_floats, "1.5 & 1;",
+    RuntimeError::TypeMismatch { lhs: "number", rhs: "integer", .. });
+test_runtime_error!(bitwise_not_of_float, "~1.5;",
+    RuntimeError::UnaryTypeMismatch { operand: "number", .. });
+test_runtime_error!(unary_type_mismatch, "-\"test\";", 
+    RuntimeError::UnaryTypeMismatch { operand: "string", .. });
+test_runtime_error!(invalid_assignment_target, "var a = 1; 1 = a;", 
+    RuntimeError::InvalidAssignmentTarget { .. });
+test_runtime_error!(statement_without_value, "var a = { print 1 };", 
+    RuntimeError::NoValue { .. });
+test_runtime_error!(module_not_loaded, "import \"lib.lang\"; lib.f();", 
+    RuntimeError::ModuleNotLoaded { path, .. } if path == "lib.lang");
+
+test_resolve_error!(span_of_undefined_variable, "var a = 1;\nprint a + b;", 
+    ResolveError::UndeclaredVariable { span, .. } if span.offset() == 21 && span.len() == 1);
+test_runtime_error!(span_of_type_mismatch, "var a = 1 + (2 + nil);", 
+    RuntimeError::TypeMismatch { span: Some(span), .. } if span.offset() == 13 && span.len() == 7);
+test_runtime_error!(span_of_arity_mismatch, "fun test(a) { } test(1, 2);", 
+    RuntimeError::ArityMismatch { span: Some(span), .. } if span.offset() == 16 && span.len() == 10);
+
+#[test]
+fn too_many_locals_for_vm() {
+    let vars: String = (0..=u16::MAX as usize + 1).map(|i| format!("var v{i} = {i}; ")).collect();
+    let code = format!("{{ {vars}v0 }}");
+    let err = eval_program_in(Backend::Vm, &CtxTree::new(), &code).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CompileError>(),
+        Some(CompileError::TooMany { what: "local variables in one scope", .. })), "unexpected error: {err:?}");
+}
+
+#[test]
+fn too_many_arguments_for_vm() {
+    let args = vec!["0"; u16::MAX as usize + 1].join(", ");
+    let code = format!("fun f() {{ 1 }} f({args})");
+    let err = eval_program_in(Backend::Vm, &CtxTree::new(), &code).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CompileError>(),
+        Some(CompileError::TooMany { what: "arguments", .. })), "unexpected error: {err:?}");
+}
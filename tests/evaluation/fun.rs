@@ -0,0 +1,154 @@
+use std::rc::Rc;
+
+use lang_interpreter::{backend::Backend, context::CtxTree, error::RuntimeError, evaluator::Value};
+
+use crate::{test_program_eval, test_runtime_error};
+
+use super::eval_program_in;
+
+test_program_eval!(fun_call, "
+    fun add(a, b) {
+        return a + b;
+    }
+    add(1, 2)
+", Value::Integer(3));
+
+test_program_eval!(fun_block_value, "
+    fun add(a, b) { a + b }
+    add(1, 2)
+", Value::Integer(3));
+
+test_program_eval!(fun_declaration_keeps_scope, "
+    {
+        var a = 1;
+        fun f() { 2 }
+        var b = 3;
+        a + f() + b
+    }
+", Value::Integer(6));
+
+test_program_eval!(fun_assigns_declaring_scope, "
+    var a = 1;
+    fun f() { a = 2; }
+    f();
+    a
+", Value::Integer(2));
+
+test_program_eval!(fun_recursion, "
+    fun fib(n) {
+        if (n < 2) { return n; }
+        return fib(n - 1) + fib(n - 2);
+    }
+    fib(10)
+", Value::Integer(55));
+
+test_program_eval!(fun_anonymous, "
+    var add = fun (a, b) { a + b };
+    add(2, 3)
+", Value::Integer(5));
+
+test_program_eval!(fun_anonymous_called_in_place, "
+    (fun (a) { a * 2 })(4)
+", Value::Integer(8));
+
+test_program_eval!(fun_passed_as_argument, "
+    fun twice(f, x) { f(f(x)) }
+    fun inc(x) { x + 1 }
+    twice(inc, 1)
+", Value::Integer(3));
+
+test_program_eval!(fun_returned_from_function, "
+    fun adder(n) {
+        return fun (x) { x + n };
+    }
+    adder(10)(5)
+", Value::Integer(15));
+
+test_program_eval!(fun_closure_counter, "
+    fun make_counter() {
+        var count = 0;
+        fun inc() {
+            count = count + 1;
+            return count;
+        }
+        return inc;
+    }
+    var c1 = make_counter();
+    var c2 = make_counter();
+    c1();
+    c1();
+    c2();
+    [c1(), c2()]
+", Value::list(vec![Value::Integer(3), Value::Integer(2)]));
+
+test_program_eval!(fun_closure_captures_definition_scope, "
+    var x = \"global\";
+    fun show() { x }
+    fun test() {
+        var x = \"local\";
+        return show();
+    }
+    test()
+", Value::String("global".to_string()));
+
+test_program_eval!(fun_callback_in_list, "
+    var callbacks = [fun (x) { x + 1 }, fun (x) { x * 2 }];
+    callbacks[1](callbacks[0](2))
+", Value::Integer(6));
+
+test_program_eval!(fun_equality_by_identity, "
+    fun a() {}
+    var b = a;
+    [a == b, a == fun () {}]
+", Value::list(vec![Value::Bool(true), Value::Bool(false)]));
+
+// named functions are stored in the scope they capture, the scope
+// has to be freed together with everything it holds
+#[test]
+fn fun_declaration_scope_is_freed() {
+    for backend in Backend::ALL {
+        let token = Rc::new(());
+        let ctx = CtxTree::new();
+        let held = token.clone();
+        ctx.register_fn("marker", Some(0), move |_| {
+            let _ = &held;
+            Ok(().into())
+        });
+        eval_program_in(backend, &ctx, "
+            fun outer() {
+                var keep = marker;
+                fun inner() { keep() }
+                inner();
+                inner == inner
+            }
+            outer();
+        ").unwrap();
+        drop(ctx);
+        assert_eq!(Rc::strong_count(&token), 1, "backend {backend:?}");
+    }
+}
+
+// functions and classes declared by one backend can be used by the others
+#[test]
+fn fun_called_across_backends() {
+    for declaring in Backend::ALL {
+        for calling in Backend::ALL {
+            let ctx = CtxTree::new();
+            eval_program_in(declaring, &ctx, "
+                fun add(a, b) { a + b }
+                var double = fun (x) { x * 2 };
+                class Point {
+                    init(x) { this.x = x; }
+                    get() { this.x }
+                }
+            ").unwrap();
+            let res = eval_program_in(calling, &ctx, "double(add(1, 2)) + Point(4).get()");
+            assert_eq!(res.unwrap(), Value::Integer(10), "declared by {declaring:?}, called by {calling:?}");
+        }
+    }
+}
+
+test_runtime_error!(fun_not_callable, "var a = 1; a();", 
+    RuntimeError::NotCallable { target: "integer", .. });
+test_runtime_error!(fun_anonymous_arity, "(fun (a) { a })();", 
+    RuntimeError::ArityMismatch { expected: 1, found: 0, .. });
@@ -0,0 +1,41 @@
+use lang_interpreter::{context::CtxTree, error::RuntimeError, evaluator::Value, parser::Parser};
+
+use crate::{test_expr_eval, test_program_eval, test_runtime_error};
+
+test_expr_eval!(list_index, "[1, 2, 3][1]", Value::Integer(2));
+test_expr_eval!(list_nested_index, "[[1, 2], [3, 4]][1][0]", Value::Integer(3));
+test_expr_eval!(list_concat, "([1] + [2, 3])[2]", Value::Integer(3));
+test_expr_eval!(list_equality, "[1, \"a\"] == [1, \"a\"]", Value::Bool(true));
+test_expr_eval!(string_index, "\"test\"[1]", Value::String("e".to_string()));
+
+test_program_eval!(list_write, "
+    var xs = [1, 2, 3];
+    xs[0] = 10;
+    xs[0] + xs[2]
+", Value::Integer(13));
+
+test_program_eval!(list_reference_semantics, "
+    var xs = [1, 2, 3];
+    var ys = xs;
+    ys[1] = \"changed\";
+    xs[1]
+", Value::String("changed".to_string()));
+
+test_program_eval!(list_write_in_loop, "
+    var xs = [0, 0, 0];
+    for (var i = 0; i < 3; i = i + 1) {
+        xs[i] = i * 2;
+    }
+    xs
+", Value::list(vec![Value::Integer(0), Value::Integer(2), Value::Integer(4)]));
+
+test_runtime_error!(index_out_of_bounds, "[1, 2][2];", 
+    RuntimeError::IndexOutOfBounds { index: 2, len: 2, .. });
+test_runtime_error!(index_negative, "var xs = [1]; xs[-1] = 2;", 
+    RuntimeError::IndexOutOfBounds { index: -1, len: 1, .. });
+test_runtime_error!(index_fractional, "[1, 2][0.5];", 
+    RuntimeError::InvalidIndex { .. });
+test_runtime_error!(index_not_indexable, "var a = 1; a[0];", 
+    RuntimeError::NotIndexable { target: "integer", .. });
+test_runtime_error!(string_index_write, "var s = \"abc\"; s[0] = \"d\";", 
+    RuntimeError::NotIndexable { target: "string", .. });
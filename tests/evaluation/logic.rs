@@ -0,0 +1,39 @@
+use lang_interpreter::{context::CtxTree, error::RuntimeError, evaluator::Value, parser::Parser};
+
+use crate::{test_expr_eval, test_program_eval, test_runtime_error};
+
+// the deciding operand is returned as it is
+test_expr_eval!(and_returns_falsy_lhs, "nil and 1", Value::Nil);
+test_expr_eval!(and_returns_rhs, "1 and \"a\"", Value::String("a".to_string()));
+test_expr_eval!(or_returns_truthy_lhs, "\"a\" or 1", Value::String("a".to_string()));
+test_expr_eval!(or_returns_rhs, "false or nil", Value::Nil);
+test_expr_eval!(or_default_value, "nil or 5", Value::Integer(5));
+test_expr_eval!(and_or_chain, "1 and nil or 3", Value::Integer(3));
+// `and` and `or` bind equally and associate to the left
+test_expr_eval!(or_and_left_associative, "true or false and false", Value::Bool(false));
+
+// rhs is not evaluated at all when lhs decides
+test_expr_eval!(and_skips_error, "false and -\"a\"", Value::Bool(false));
+test_expr_eval!(or_skips_error, "true or 1 + \"a\"", Value::Bool(true));
+test_runtime_error!(and_evaluates_rhs, "true and -\"a\";",
+    RuntimeError::UnaryTypeMismatch { operand: "string", .. });
+
+test_program_eval!(and_skips_call, "
+    var calls = 0;
+    fun f() { calls = calls + 1; true }
+    false and f();
+    true and f();
+    calls
+", Value::Integer(1));
+test_program_eval!(or_skips_call, "
+    var calls = 0;
+    fun f() { calls = calls + 1; true }
+    true or f();
+    false or f();
+    calls
+", Value::Integer(1));
+test_program_eval!(and_skips_assignment, "
+    var a = 1;
+    false and { a = 2; true };
+    a
+", Value::Integer(1));
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use lang_interpreter::{context::CtxTree, error::RuntimeError, evaluator::Value, parser::Parser};
+
+use crate::{test_expr_eval, test_program_eval, test_runtime_error};
+
+fn string(s: &str) -> Value<'static> {
+    Value::String(s.to_string())
+}
+
+test_expr_eval!(map_index, "{\"a\": 1, \"b\": 2}[\"b\"]", Value::Integer(2));
+test_expr_eval!(map_field, "{\"a\": {\"b\": \"c\"}}.a.b", string("c"));
+test_expr_eval!(map_last_entry_wins, "{\"a\": 1, \"a\": 2}.a", Value::Integer(2));
+test_expr_eval!(map_equality, "{\"a\": [1], \"b\": nil} == {\"b\": nil, \"a\": [1]}", Value::Bool(true));
+test_expr_eval!(map_inequality, "{\"a\": 1} != {\"a\": 1, \"b\": 1}", Value::Bool(true));
+test_expr_eval!(map_empty, "{:}", Value::map(BTreeMap::new()));
+test_expr_eval!(map_block_still_block, "{ \"a\" }", string("a"));
+
+test_program_eval!(map_write, "
+    var m = {:};
+    m.a = 1;
+    m[\"b\"] = m.a + 1;
+    m.a = m.a + m.b;
+    m
+", Value::from(BTreeMap::from([("a".to_string(), 3i64), ("b".to_string(), 2i64)])));
+
+test_program_eval!(map_reference_semantics, "
+    var m = {\"k\": 1};
+    var n = m;
+    n.k = \"changed\";
+    m.k
+", string("changed"));
+
+test_program_eval!(map_as_statement, "
+    var key = \"b\";
+    {\"a\": 1};
+    var m = {\"a\": key, \"list\": [{\"x\": 1}]};
+    if (m.a == key) { m.list[0].x } else { 0 }
+", Value::Integer(1));
+
+// printed map is a literal evaluating to the same map
+#[test]
+fn map_display_reads_back() {
+    let map = super::eval_program("{\"a\\\"b\": \"x\\\\y\\n\", \"é\\t\": [1, \"q\\\"r\"], \"c\": {:},}").unwrap();
+    let printed = map.to_string();
+    assert_eq!(printed, "{\"a\\\"b\": \"x\\\\y\\n\", \"c\": {:}, \"é\\t\": [1, \"q\\\"r\"]}");
+    assert_eq!(super::eval_program(&printed).unwrap().to_string(), printed);
+}
+
+test_runtime_error!(map_missing_key, "var m = {\"a\": 1}; m.b;",
+    RuntimeError::KeyNotFound { key, span: Some(_) } if key == "b");
+test_runtime_error!(map_invalid_key, "var m = {:}; m[1] = 2;",
+    RuntimeError::InvalidKey { found: "integer", span: Some(_) });
+test_runtime_error!(field_of_number, "var n = 1; n.a;",
+    RuntimeError::NotIndexable { target: "integer", .. });
@@ -1,22 +1,53 @@
-use lang_interpreter::{context::CtxTree, evaluator::{Eval, Value}, parser::Parser};
+use lang_interpreter::{context::CtxTree, evaluator::Value, parser::Parser};
 
 use crate::test_expr_eval;
 
 
 
-test_expr_eval!(addition, "1 + 2 + 3", Value::Number(6f64));
-test_expr_eval!(substraction, "11 - 3", Value::Number(8f64));
-test_expr_eval!(multiplication, "5 * 3", Value::Number(15f64));
+test_expr_eval!(addition, "1 + 2 + 3", Value::Integer(6));
+test_expr_eval!(substraction, "11 - 3", Value::Integer(8));
+test_expr_eval!(multiplication, "5 * 3", Value::Integer(15));
 test_expr_eval!(division, "12 / 4", Value::Number(3f64));
+test_expr_eval!(bool_subtraction, "false - true", Value::Integer(-1));
 
-test_expr_eval!(mixed1, "3 * 2 + 3", Value::Number(9f64));
+test_expr_eval!(division_of_integers, "7 / 2", Value::Number(3.5f64));
+test_expr_eval!(integer_division, "7 div 2", Value::Integer(3));
+test_expr_eval!(integer_division_rounds_down, "-7 div 2", Value::Integer(-4));
+test_expr_eval!(integer_division_of_floats, "7.5 div 2", Value::Number(3f64));
+test_expr_eval!(modulo, "7 % 3", Value::Integer(1));
+test_expr_eval!(modulo_takes_sign_of_divisor, "-7 % 3 + 7 % -3", Value::Integer(0));
+test_expr_eval!(modulo_of_floats, "7.5 % 2", Value::Number(1.5f64));
+test_expr_eval!(modulo_pairs_with_div, "-7 div 3 * 3 + -7 % 3", Value::Integer(-7));
+test_expr_eval!(promotion, "1 + 0.5", Value::Number(1.5f64));
+test_expr_eval!(promotion_of_bool, "true + 1", Value::Integer(2));
+test_expr_eval!(integer_max, "9223372036854775807 - 1 + 1", Value::Integer(i64::MAX));
+test_expr_eval!(integer_min, "-9223372036854775808 == -9223372036854775807 - 1", Value::Bool(true));
+test_expr_eval!(integer_min_literal, "-9223372036854775808", Value::Integer(i64::MIN));
+test_expr_eval!(integer_past_max_as_float, "9223372036854775807 + 1.0", Value::Number(9223372036854775808f64));
+test_expr_eval!(float_division_by_zero, "1.0 div 0", Value::Number(f64::INFINITY));
+
+test_expr_eval!(exponent, "2 ** 10", Value::Integer(1024));
+test_expr_eval!(exponent_right_associative, "2 ** 3 ** 2", Value::Integer(512));
+test_expr_eval!(exponent_negative, "2 ** -1", Value::Number(0.5f64));
+test_expr_eval!(exponent_of_float, "4.0 ** 0.5", Value::Number(2f64));
+test_expr_eval!(exponent_before_negation, "-2 ** 2", Value::Integer(-4));
+test_expr_eval!(bitwise_and, "12 & 10", Value::Integer(8));
+test_expr_eval!(bitwise_or, "12 | 10", Value::Integer(14));
+test_expr_eval!(bitwise_xor, "12 ^ 10", Value::Integer(6));
+test_expr_eval!(bitwise_not, "~5", Value::Integer(-6));
+test_expr_eval!(shift_left, "1 << 62", Value::Integer(1 << 62));
+test_expr_eval!(shift_right_keeps_sign, "-16 >> 2", Value::Integer(-4));
+test_expr_eval!(shift_right_past_bits, "-16 >> 100", Value::Integer(-1));
+test_expr_eval!(bitwise_precedence, "1 | 6 & 3 << 1", Value::Integer(7));
+
+test_expr_eval!(mixed1, "3 * 2 + 3", Value::Integer(9));
 test_expr_eval!(mixed2, "3 * 4 / 2", Value::Number(6f64));
-test_expr_eval!(mixed3, "5 - 2 * 2", Value::Number(1f64));
+test_expr_eval!(mixed3, "5 - 2 * 2", Value::Integer(1));
 
-test_expr_eval!(group1, "3 * (2 + 3)", Value::Number(15f64));
-test_expr_eval!(group2, "(5 - 2) * 2", Value::Number(6f64));
+test_expr_eval!(group1, "3 * (2 + 3)", Value::Integer(15));
+test_expr_eval!(group2, "(5 - 2) * 2", Value::Integer(6));
 
-test_expr_eval!(unaryop1, "-(5 - 2) * 2", Value::Number(-6f64));
+test_expr_eval!(unaryop1, "-(5 - 2) * 2", Value::Integer(-6));
 test_expr_eval!(unaryop2, "!(5 - 2 == 3)", Value::Bool(false));
 
 test_expr_eval!(equalequal1, "5 - 2 == 3", Value::Bool(true));
@@ -32,6 +63,12 @@ test_expr_eval!(greater2, "3 > 3", Value::Bool(false));
 test_expr_eval!(greaterequal1, "4 >= 3", Value::Bool(true));
 test_expr_eval!(greaterequal2, "3 >= 3", Value::Bool(true));
 test_expr_eval!(greaterequal3, "2 >= 3", Value::Bool(false));
+test_expr_eval!(integer_equals_float, "3 == 3.0 and 3 != 3.5", Value::Bool(true));
+test_expr_eval!(integer_less_than_float, "2 < 2.5 and 3.5 > 3", Value::Bool(true));
+test_expr_eval!(large_integers_are_exact, "9007199254740993 == 9007199254740992", Value::Bool(false));
+test_expr_eval!(large_integer_not_equal_to_float, "9007199254740993 != 9007199254740992.0 and 9007199254740993 > 9007199254740992.0", Value::Bool(true));
+test_expr_eval!(max_integer_less_than_float, "9223372036854775807 < 9223372036854775808.0", Value::Bool(true));
+test_expr_eval!(negative_integer_compared_to_float, "-3 < -2.5 and -2 > -2.5 and -2.5 < -2", Value::Bool(true));
 
 test_expr_eval!(and1, "1 == 1 and 2 == 2", Value::Bool(true));
 test_expr_eval!(and2, "1 == 2 and 2 == 2", Value::Bool(false));
@@ -1,8 +1,19 @@
-use lang_interpreter::{context::CtxTree, evaluator::{Eval, Value}, parser::Parser};
+use lang_interpreter::{backend::Backend, context::CtxTree, evaluator::Value, parser::Parser, resolver::Resolver};
+use miette::Error;
 
 use crate::test_expr_eval;
 
 pub mod math;
+pub mod errors;
+pub mod lists;
+pub mod maps;
+pub mod classes;
+pub mod fun;
+pub mod control_flow;
+pub mod logic;
+pub mod variables;
+pub mod native;
+pub mod stdlib;
 
 // #[test]
 // fn t1() {
@@ -16,7 +27,17 @@ pub mod math;
 //     println!("ret: {}", val);
 // }
 
-test_expr_eval!(basic_test1, "1 + 2 * 3", Value::Number(7f64));
+test_expr_eval!(basic_test1, "1 + 2 * 3", Value::Integer(7));
+
+/// Asserts the values are equal and of the same type, as integers are equal to floats
+#[macro_export]
+macro_rules! assert_same {
+    ($val:expr, $out:expr $(, $($arg:tt)+)?) => {
+        let (val, out) = (&$val, &$out);
+        assert_eq!(val, out $(, $($arg)+)?);
+        assert_eq!(format!("{val:?}"), format!("{out:?}") $(, $($arg)+)?);
+    }
+}
 
 #[macro_export]
 macro_rules! test_expr_eval {
@@ -25,10 +46,86 @@ macro_rules! test_expr_eval {
         fn $name() {
             let expr = $inp;
             let mut parser = Parser::new(expr);
-            let ctx = CtxTree::new();
-            let exp_tree = parser.parse_expression_within(0).unwrap();
-            let val = exp_tree.eval(&ctx).unwrap();
-            assert_eq!(val, $out);
+            let mut exp_tree = parser.parse_expression_within(0).unwrap();
+            lang_interpreter::resolver::Resolver::new().resolve(std::slice::from_mut(&mut exp_tree)).unwrap();
+            let out = $out;
+            for backend in lang_interpreter::backend::Backend::ALL {
+                let ctx = CtxTree::new();
+                let val = backend.run(&ctx, std::slice::from_ref(&exp_tree)).unwrap();
+                $crate::assert_same!(val, out, "backend {backend:?}");
+            }
+        }
+    }
+}
+
+/// Runs the whole program on every backend and returns the value of its last statement,
+/// all backends have to agree on the outcome
+pub fn eval_program(code: &str) -> Result<Value<'_>, Error> {
+    eval_program_with(CtxTree::new, code)
+}
+
+/// Same as `eval_program`, every backend runs in fresh context made by `new_ctx`
+pub fn eval_program_with<'a>(new_ctx: impl Fn() -> CtxTree<'a>, code: &'a str) -> Result<Value<'a>, Error> {
+    let [first, rest @ ..] = Backend::ALL.map(|backend| eval_program_in(backend, &new_ctx(), code));
+    for (backend, res) in Backend::ALL[1..].iter().zip(rest) {
+        assert_eq!(outcome(&res), outcome(&first), "backend {backend:?} disagrees with {:?}", Backend::ALL[0]);
+    }
+    first
+}
+
+/// Runs the whole program in given context and returns the value of its last statement
+pub fn eval_program_in<'a>(backend: Backend, ctx: &CtxTree<'a>, code: &'a str) -> Result<Value<'a>, Error> {
+    let mut stmts = Parser::new(code).parse()?;
+    Resolver::with_globals(ctx).resolve(&mut stmts)?;
+    backend.run(ctx, &stmts)
+}
+
+/// Comparable description of the result, functions of different backends are
+/// compared by their name and errors by their message and labels
+fn outcome(res: &Result<Value<'_>, Error>) -> String {
+    match res {
+        Ok(v @ (Value::Function(_) | Value::NativeFunction(_) | Value::CompiledFunction(_) | Value::LoweredFunction(_))) => format!("{v}"),
+        Ok(v) => format!("{v:?}"),
+        Err(e) => {
+            let labels: Vec<_> = e.labels().into_iter().flatten().map(|l| (l.offset(), l.len())).collect();
+            format!("error: {e} at {labels:?}")
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! test_program_eval {
+    ($name:ident, $inp:literal, $out:expr) => {
+        #[test]
+        fn $name() {
+            let val = $crate::evaluation::eval_program($inp).unwrap();
+            $crate::assert_same!(val, $out);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! test_resolve_error {
+    ($name:ident, $inp:literal, $err:pat $(if $guard:expr)?) => {
+        #[test]
+        fn $name() {
+            let err = $crate::evaluation::eval_program($inp).unwrap_err();
+            let err = err.downcast_ref::<lang_interpreter::error::ResolveError>()
+                .expect("expected resolve error");
+            assert!(matches!(err, $err $(if $guard)?), "unexpected error: {err:?}");
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! test_runtime_error {
+    ($name:ident, $inp:literal, $err:pat $(if $guard:expr)?) => {
+        #[test]
+        fn $name() {
+            let err = $crate::evaluation::eval_program($inp).unwrap_err();
+            let err = err.downcast_ref::<lang_interpreter::error::RuntimeError>()
+                .expect("expected runtime error");
+            assert!(matches!(err, $err $(if $guard)?), "unexpected error: {err:?}");
         }
     }
-}
\ No newline at end of file
+}
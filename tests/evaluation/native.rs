@@ -0,0 +1,86 @@
+use std::{cell::RefCell, rc::Rc};
+
+use lang_interpreter::{backend::Backend, context::CtxTree, error::RuntimeError, evaluator::{Event, Value}};
+
+use super::{eval_program_in, eval_program_with};
+
+fn host_ctx<'a>() -> CtxTree<'a> {
+    let ctx = CtxTree::new();
+    ctx.register_fn("square", Some(1), |args| {
+        let n = f64::try_from(args[0].clone())?;
+        Ok((n * n).into())
+    });
+    ctx.register_fn("sum", None, |args| {
+        let mut sum = 0f64;
+        for a in args {
+            sum += f64::try_from(a)?;
+        }
+        Ok(sum.into())
+    });
+    ctx.register_fn("greet", Some(1), |args| {
+        let name = String::try_from(args[0].clone())?;
+        Ok(format!("hello {name}").into())
+    });
+    ctx.register_fn("range", Some(1), |args| {
+        let n = f64::try_from(args[0].clone())? as usize;
+        Ok((0..n).map(|i| i as f64).collect::<Vec<f64>>().into())
+    });
+    ctx
+}
+
+#[test]
+fn native_call() {
+    crate::assert_same!(eval_program_with(host_ctx, "square(4)").unwrap(), Value::Number(16f64));
+    crate::assert_same!(eval_program_with(host_ctx, "sum()").unwrap(), Value::Number(0f64));
+    crate::assert_same!(eval_program_with(host_ctx, "sum(1, 2, 3)").unwrap(), Value::Number(6f64));
+    crate::assert_same!(eval_program_with(host_ctx, "greet(\"world\")").unwrap(), Value::String("hello world".to_string()));
+    crate::assert_same!(eval_program_with(host_ctx, "range(3)[2]").unwrap(), Value::Number(2f64));
+}
+
+#[test]
+fn native_as_value() {
+    let code = "
+        fun apply(f, x) { f(x) }
+        var sq = square;
+        apply(sq, 3)
+    ";
+    crate::assert_same!(eval_program_with(host_ctx, code).unwrap(), Value::Number(9f64));
+}
+
+#[test]
+fn native_calls_back_into_script_state() {
+    for backend in Backend::ALL {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let ctx = CtxTree::new();
+        let host_log = log.clone();
+        ctx.register_fn("log", Some(1), move |args| {
+            host_log.borrow_mut().push(args[0].to_string());
+            Ok(().into())
+        });
+        eval_program_in(backend, &ctx, "for (var i = 0; i < 3; i = i + 1) { log(i * 2); }").unwrap();
+        assert_eq!(*log.borrow(), vec!["0", "2", "4"], "backend {backend:?}");
+    }
+}
+
+#[test]
+fn native_errors() {
+    let err = eval_program_with(host_ctx, "square(1, 2);").unwrap_err();
+    assert!(matches!(err.downcast_ref::<RuntimeError>(), 
+        Some(RuntimeError::ArityMismatch { expected: 1, found: 2, span: Some(_), .. })));
+
+    let err = eval_program_with(host_ctx, "square(\"test\");").unwrap_err();
+    assert!(matches!(err.downcast_ref::<RuntimeError>(), 
+        Some(RuntimeError::UnexpectedType { expected: "number", found: "string", span: Some(_) })));
+}
+
+#[test]
+fn native_returning_event() {
+    let ctx = || {
+        let ctx = CtxTree::new();
+        ctx.register_fn("escape", Some(0), |_| Ok(Value::Event(Event::Break(Box::new(Value::Nil)))));
+        ctx
+    };
+    let err = eval_program_with(ctx, "while (true) { escape(); }").unwrap_err();
+    assert!(matches!(err.downcast_ref::<RuntimeError>(), 
+        Some(RuntimeError::EventEscape { event: "break", span: Some(_) })));
+}
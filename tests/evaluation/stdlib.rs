@@ -0,0 +1,93 @@
+use lang_interpreter::{context::CtxTree, error::RuntimeError, evaluator::Value, stdlib};
+use miette::Error;
+
+use super::eval_program_with;
+
+fn eval_std(code: &str) -> Result<Value<'_>, Error> {
+    eval_program_with(|| {
+        let ctx = CtxTree::new();
+        stdlib::install(&ctx);
+        ctx
+    }, code)
+}
+
+macro_rules! test_std_eval {
+    ($name:ident, $code:expr, $value:expr) => {
+        #[test]
+        fn $name() {
+            $crate::assert_same!(eval_std($code).unwrap(), $value);
+        }
+    };
+}
+
+macro_rules! test_std_error {
+    ($name:ident, $code:expr, $err:pat) => {
+        #[test]
+        fn $name() {
+            let err = eval_std($code).unwrap_err();
+            assert!(matches!(err.downcast_ref::<RuntimeError>(), Some($err)), "unexpected error: {err:?}");
+        }
+    };
+}
+
+fn string(s: &str) -> Value<'static> {
+    Value::String(s.to_string())
+}
+
+test_std_eval!(len_string, "len(\"zażółć\")", Value::Integer(6));
+test_std_eval!(len_list, "len([1, 2, 3])", Value::Integer(3));
+test_std_eval!(substring, "substring(\"hello world\", 6, 11)", string("world"));
+test_std_eval!(substring_empty, "substring(\"hello\", 5, 5)", string(""));
+test_std_eval!(split, "var p = split(\"a,b,c\", \",\"); p[0] + p[2] + to_string(len(p))", string("ac3"));
+test_std_eval!(split_chars, "len(split(\"abc\", \"\"))", Value::Integer(3));
+test_std_eval!(upper, "upper(\"Hello\")", string("HELLO"));
+test_std_eval!(lower, "lower(\"Hello\")", string("hello"));
+test_std_eval!(contains_string, "contains(\"hello\", \"ell\")", Value::Bool(true));
+test_std_eval!(contains_list, "contains([1, \"a\"], 2)", Value::Bool(false));
+
+test_std_eval!(sqrt, "sqrt(16)", Value::Number(4f64));
+test_std_eval!(floor, "floor(-1.5)", Value::Number(-2f64));
+test_std_eval!(pow, "pow(2, 10)", Value::Number(1024f64));
+test_std_eval!(abs, "abs(-3)", Value::Integer(3));
+test_std_eval!(min, "min(3, 1, 2)", Value::Integer(1));
+test_std_eval!(max, "max(3, 1, 2)", Value::Integer(3));
+test_std_eval!(random_seeded, "
+    seed(42);
+    var a = random();
+    seed(42);
+    var b = random();
+    a == b and a >= 0 and a < 1
+", Value::Bool(true));
+
+test_std_eval!(type_of, "type_of(1) + type_of(1.5) + type_of(\"\") + type_of([]) + type_of(nil) + type_of(len) + type_of({:})",
+    string("integernumberstringlistnilfunctionmap"));
+test_std_eval!(type_of_arithmetic, "type_of(7 div 2) + type_of(7 / 2) + type_of(1 + 1.0) + type_of(2 * 3)",
+    string("integernumbernumberinteger"));
+test_std_eval!(integers_stay_integers, "type_of(len([])) + type_of(abs(-3)) + type_of(min(3, 1.5)) + type_of(to_number(\"12\"))",
+    string("integerintegernumberinteger"));
+test_std_eval!(to_number, "to_number(\" 12.5 \") + to_number(true)", Value::Number(13.5f64));
+test_std_eval!(to_number_invalid, "to_number(\"abc\")", Value::Nil);
+test_std_eval!(to_integer, "to_integer(2.7) + to_integer(-2.7) + to_integer(3) + to_integer(true)", Value::Integer(4));
+test_std_eval!(to_integer_limits, "to_integer(-9223372036854775808.0) == -9223372036854775807 - 1", Value::Bool(true));
+test_std_error!(to_integer_out_of_range, "to_integer(9223372036854775808.0)", RuntimeError::InvalidArgument { .. });
+test_std_error!(to_integer_nan, "to_integer(0.0 / 0.0)", RuntimeError::InvalidArgument { .. });
+test_std_eval!(to_string, "to_string(1.5) + to_string([1, 2])", string("1.5[1, 2]"));
+test_std_eval!(to_string_map, "to_string({\"b\": \"x\", \"a\": [1, \"y\"], \"c\": {:}})",
+    string("{\"a\": [1, \"y\"], \"b\": \"x\", \"c\": {:}}"));
+test_std_eval!(len_map, "len({\"a\": 1, \"b\": 2})", Value::Integer(2));
+test_std_eval!(contains_map_key, "contains({\"a\": 1}, \"a\") and !contains({\"a\": 1}, \"b\")", Value::Bool(true));
+test_std_eval!(keys_and_values, "var m = {\"b\": 2, \"a\": 1}; keys(m) + values(m)",
+    Value::from(vec![string("a"), string("b"), Value::Integer(1), Value::Integer(2)]));
+test_std_eval!(entries, "var sum = \"\"; var e = entries({\"x\": 1, \"y\": 2}); for (var i = 0; i < len(e); i = i + 1) { sum = sum + e[i][0] + to_string(e[i][1]); } sum",
+    string("x1y2"));
+test_std_eval!(remove, "var m = {\"a\": 1, \"b\": 2}; var r = remove(m, \"a\"); [r, remove(m, \"a\"), len(m)]",
+    Value::from(vec![Value::Integer(1), Value::Nil, Value::Integer(1)]));
+test_std_eval!(clock, "clock() > 0", Value::Bool(true));
+test_std_eval!(shadowing, "fun len(x) { 0 } len(\"abc\")", Value::Integer(0));
+
+test_std_error!(len_number, "len(1);", RuntimeError::UnexpectedType { found: "integer", span: Some(_), .. });
+test_std_error!(substring_bounds, "substring(\"abc\", 1, 4);", RuntimeError::IndexOutOfBounds { index: 4, len: 3, .. });
+test_std_error!(substring_reversed, "substring(\"abc\", 2, 1);", RuntimeError::InvalidArgument { .. });
+test_std_error!(min_empty, "min();", RuntimeError::InvalidArgument { span: Some(_), .. });
+test_std_error!(remove_from_list, "remove([1], \"a\");", RuntimeError::UnexpectedType { expected: "map", found: "list", .. });
+test_std_error!(sqrt_arity, "sqrt(1, 2);", RuntimeError::ArityMismatch { expected: 1, found: 2, .. });
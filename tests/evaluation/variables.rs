@@ -0,0 +1,89 @@
+use lang_interpreter::evaluator::Value;
+
+use crate::test_program_eval;
+
+test_program_eval!(global_variable, "var a = 1; a = a + 1; a", Value::Integer(2));
+test_program_eval!(global_redeclaration, "var a = 1; var a = a + 1; a", Value::Integer(2));
+test_program_eval!(local_shadowing, "
+    var a = 1;
+    var b = { var a = 2; a * 10 };
+    a + b
+", Value::Integer(21));
+test_program_eval!(local_redeclaration, "{ var a = 1; var a = a + 1; a }", Value::Integer(2));
+test_program_eval!(block_value_uses_block_scope, "var a = 1; { var a = 2; a }", Value::Integer(2));
+test_program_eval!(outer_local_assignment, "
+    {
+        var a = 1;
+        { { a = a + 1; } }
+        a
+    }
+", Value::Integer(2));
+test_program_eval!(local_declared_after_use, "
+    var a = 1;
+    {
+        var b = a;
+        var a = 10;
+        a + b
+    }
+", Value::Integer(11));
+test_program_eval!(local_recursive_function, "
+    {
+        fun fact(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }
+        fact(5)
+    }
+", Value::Integer(120));
+test_program_eval!(function_declared_later, "
+    fun is_even(n) { if (n == 0) { true } else { is_odd(n - 1) } }
+    fun is_odd(n) { if (n == 0) { false } else { is_even(n - 1) } }
+    is_even(10)
+", Value::Bool(true));
+test_program_eval!(closure_in_loop, "
+    var funs = [nil, nil, nil];
+    for (var i = 0; i < 3; i = i + 1) {
+        var j = i;
+        funs[i] = fun () { j };
+    }
+    funs[0]() + funs[1]() * 10 + funs[2]() * 100
+", Value::Integer(210));
+test_program_eval!(closure_shares_variable, "
+    fun pair() {
+        var n = 0;
+        var inc = fun () { n = n + 1; };
+        var get = fun () { n };
+        [inc, get]
+    }
+    var p = pair();
+    p[0]();
+    p[0]();
+    p[1]()
+", Value::Integer(2));
+test_program_eval!(parameter_shadows_global, "
+    var x = 1;
+    fun f(x) { x = x + 1; x }
+    f(10) + x
+", Value::Integer(12));
+test_program_eval!(local_through_scopes_without_declarations, "
+    {
+        var a = 1;
+        { { var b = 2; { { a = a + b; } } } }
+        a
+    }
+", Value::Integer(3));
+test_program_eval!(closure_in_loop_without_declarations, "
+    fun make() {
+        var fs = [];
+        var n = 0;
+        while (n < 3) { n = n + 1; fs = fs + [fun () { n * 10 }]; }
+        fs
+    }
+    var fs = make();
+    fs[0]() + fs[2]()
+", Value::Integer(60));
+test_program_eval!(closures_capture_each_iteration, "
+    var fs = [];
+    for (var i = 0; i < 3; i = i + 1) {
+        var j = i;
+        { fs = fs + [fun () { j }]; }
+    }
+    fs[0]() + fs[1]() * 10 + fs[2]() * 100
+", Value::Integer(210));
@@ -0,0 +1,241 @@
+use lang_interpreter::{formatter::{comments, format}, parser::Parser};
+
+
+#[test]
+fn spacing_and_indentation() {
+    test_format("var a=1;var b  =  \"x\" ;fun   add(x,y){return x+y;} print add(a,-2)*(3/4);", "\
+var a = 1;
+var b = \"x\";
+fun add(x, y) {
+    return x + y;
+}
+print add(a, -2) * (3 / 4);
+");
+}
+
+#[test]
+fn control_flow() {
+    test_format("for(var i=0;i<3;i=i+1){if(i==1){continue;}else{print i;}} while(true){break;} for(;;){break 1;}", "\
+for (var i = 0; i < 3; i = i + 1) {
+    if (i == 1) {
+        continue;
+    } else {
+        print i;
+    }
+}
+while (true) {
+    break;
+}
+for (;;) {
+    break 1;
+}
+");
+}
+
+#[test]
+fn for_loop_parts() {
+    test_format("for(;true;){break;} for(var i=0;;i=i+1){break;} for(; ;i=i+1){break;}", "\
+for (; true;) {
+    break;
+}
+for (var i = 0;; i = i + 1) {
+    break;
+}
+for (;; i = i + 1) {
+    break;
+}
+");
+}
+
+#[test]
+fn expressions_keep_their_shape() {
+    test_format("var f=fun(a){a*2}; var l=[1,[2],\"s\"]; l[0]=f(l[1][0]) ; var x = if (a and !b or c) {1} else {{}}; var n; 1_000.50;", "\
+var f = fun (a) {
+    a * 2
+};
+var l = [1, [2], \"s\"];
+l[0] = f(l[1][0]);
+var x = if (a and !b or c) {
+    1
+} else {
+    {}
+};
+var n;
+1_000.50;
+");
+}
+
+#[test]
+fn maps_and_fields() {
+    test_format("var m={\"a\" :1,\"b\":{ : }};m . a=m[\"b\"];{\"x\":[1]};", "\
+var m = {\"a\": 1, \"b\": {:}};
+m.a = m[\"b\"];
+{\"x\": [1]};
+");
+}
+
+#[test]
+fn block_statements_keep_their_value() {
+    test_format("{ if (c) {1} }", "\
+{
+    if (c) {
+        1
+    }
+}
+");
+    test_format("{ if (c) {1}; }", "\
+{
+    if (c) {
+        1
+    };
+}
+");
+    test_format("fun f() { return; } { fun g() {} g() }", "\
+fun f() {
+    return;
+}
+{
+    fun g() {}
+    g()
+}
+");
+}
+
+#[test]
+fn comments_are_kept() {
+    test_format("\
+// header
+var a = 1;   // trailing
+fun f() {   // after brace
+
+    // inside
+    a   // after return expression
+    // before end
+}
+
+
+// end
+", "\
+// header
+var a = 1; // trailing
+fun f() {
+    // after brace
+
+    // inside
+    a // after return expression
+    // before end
+}
+
+// end
+");
+    test_format("var a = 1; var b = 2; // second\n", "var a = 1;\nvar b = 2; // second\n");
+    test_format("{ // only comment\n}", "{\n    // only comment\n}\n");
+}
+
+#[test]
+fn block_and_doc_comments_are_kept() {
+    test_format("/// Doubles\nfun  double(x) { /* nested /* comment */ */ x*2 }\n/* multi\n   line */ var a=1;", "\
+/// Doubles
+fun double(x) {
+    /* nested /* comment */ */
+    x * 2
+}
+/* multi
+   line */
+var a = 1;
+");
+}
+
+#[test]
+fn comments_inside_of_expressions_stay_after_their_token() {
+    test_format("var a = 1 + // c\n 2;", "var a = 1 + // c\n    2;\n");
+    test_format("print f(1, /* x */ 2, // y\n 3 // z\n);\nprint 2;", "\
+print f(1, /* x */ 2, // y
+    3); // z
+print 2;
+");
+}
+
+#[test]
+fn doc_comments_stay_with_the_next_statement() {
+    test_format("var a = 1; /// doc\nfun f() {}", "var a = 1;\n/// doc\nfun f() {}\n");
+    test_format("fun g() {} /// doc\nfun f() {} //// ordinary", "fun g() {}\n/// doc\nfun f() {} //// ordinary\n");
+}
+
+#[test]
+fn blank_lines_are_collapsed() {
+    test_format("var a = 1;\n\n\n\nvar b = 2;\n{\n\n    a\n\n}", "var a = 1;\n\nvar b = 2;\n{\n    a\n}\n");
+}
+
+#[test]
+fn comments_are_found_between_tokens() {
+    let src = "print \"// not a comment\"; // comment\n// another";
+    let found: Vec<_> = comments(src).into_iter().map(|c| (c.offset, c.text)).collect();
+    assert_eq!(found, vec![(26, "// comment"), (37, "// another")]);
+}
+
+#[test]
+fn formatting_preserves_the_program() {
+    let programs = [
+        "var a = 1; { var a = a + 1; print a; } print a;",
+        "fun fib(n) { if (n < 2) { return n; } fib(n - 1) + fib(n - 2) } print fib(10);",
+        "var l = []; for (var i = 0; i < 5; i = i + 1) { push(l, i * i); } print l;",
+        "var x = while (true) { break 1; }; print -(x - -1);",
+        "var s = \"a\\\"b\"; print s + \"\";",
+        "var m = ~(1<<4|2&3^-2**2**3)>>1 % 7 div 2;",
+    ];
+    for src in programs {
+        let formatted = format(src).unwrap();
+        assert_eq!(parsed(&formatted), parsed(src), "{formatted}");
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+}
+
+#[test]
+fn syntax_errors_are_reported() {
+    assert!(format("var a = ;").is_err());
+    assert_eq!(format("").unwrap(), "");
+}
+
+fn parsed(src: &str) -> Vec<String> {
+    Parser::new(src).parse().unwrap().iter().map(|e| e.to_string()).collect()
+}
+
+#[test]
+fn classes() {
+    test_format("class A{init(x){this.x=x;}\n\n\n// getter\nget(){this.x}} class B<A{get(){super.get()+1}} class C{} print B(1).get();", "\
+class A {
+    init(x) {
+        this.x = x;
+    }
+
+    // getter
+    get() {
+        this.x
+    }
+}
+class B < A {
+    get() {
+        super.get() + 1
+    }
+}
+class C {}
+print B(1).get();
+");
+}
+
+#[test]
+fn imports() {
+    test_format("import   \"lib/math.lang\" ;import \"../util.lang\";\nprint math.sqrt(util.x);", "\
+import \"lib/math.lang\";
+import \"../util.lang\";
+print math.sqrt(util.x);
+");
+}
+
+fn test_format(src: &str, expected: &str) {
+    let formatted = format(src).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format(&formatted).unwrap(), formatted, "formatting is idempotent");
+    assert_eq!(parsed(&formatted), parsed(src));
+}
@@ -1,4 +1,4 @@
-use lang_interpreter::lexer::Lexer;
+use lang_interpreter::{lexer::Lexer, token::{TokenKind, Trivia, TriviaKind}};
 
 
 #[test]
@@ -21,7 +21,13 @@ fn one_letter_signs() {
     test_lex("-", "MINUS - null\n");
     test_lex("+", "PLUS + null\n");
     test_lex(";", "SEMICOLON ; null\n");
+    test_lex(":", "COLON : null\n");
     test_lex("*", "STAR * null\n");
+    test_lex("%", "PERCENT % null\n");
+    test_lex("&", "AMPERSAND & null\n");
+    test_lex("|", "PIPE | null\n");
+    test_lex("^", "CARET ^ null\n");
+    test_lex("~", "TILDE ~ null\n");
     test_lex("=", "EQUAL = null\n");
     test_lex("<", "LESS < null\n");
     test_lex(">", "GREATER > null\n");
@@ -35,6 +41,11 @@ fn two_letter_signs() {
     test_lex("==", "EQUAL_EQUAL == null\n");
     test_lex("<=", "LESS_EQUAL <= null\n");
     test_lex(">=", "GREATER_EQUAL >= null\n");
+    test_lex("**", "STAR_STAR ** null\n");
+    test_lex("<<", "LESS_LESS << null\n");
+    test_lex(">>", "GREATER_GREATER >> null\n");
+    test_lex("* *", "STAR * null\nSTAR * null\n");
+    test_lex("<<=", "LESS_LESS << null\nEQUAL = null\n");
 }
 
 #[test]
@@ -52,6 +63,11 @@ fn special_keywords() {
     test_lex("true", "TRUE true null\n");
     test_lex("var", "VAR var null\n");
     test_lex("while", "WHILE while null\n");
+    test_lex("class", "CLASS class null\n");
+    test_lex("this", "THIS this null\n");
+    test_lex("super", "SUPER super null\n");
+    test_lex("import", "IMPORT import null\n");
+    test_lex("div", "DIV div null\n");
 }
 
 #[test]
@@ -67,6 +83,33 @@ fn numbers() {
     test_lex("3", "NUMBER 3 3.0\n");
     test_lex("13.21", "NUMBER 13.21 13.21\n");
     test_lex("3.4 + 3", "NUMBER 3.4 3.4\nPLUS + null\nNUMBER 3 3.0\n");
+    test_lex("3.0", "NUMBER 3.0 3.0\n");
+    test_lex("1_000_000", "NUMBER 1_000_000 1000000.0\n");
+    test_lex("9223372036854775807", "NUMBER 9223372036854775807 9223372036854775807.0\n");
+}
+
+#[test]
+fn integers_print_like_numbers() {
+    let kinds: Vec<_> = Lexer::new("3 3.0").map(|t| t.unwrap().kind).collect();
+    assert_eq!(kinds, vec![TokenKind::INTEGER(3), TokenKind::NUMBER(3.0)]);
+    test_lex("3 3.0", "NUMBER 3 3.0\nNUMBER 3.0 3.0\n");
+}
+
+#[test]
+fn integer_out_of_range() {
+    test_lex_with_errors("1 + 9223372036854775809", "NUMBER 1 1.0\nPLUS + null\n", &[4]);
+    // the parser only accepts it negated
+    test_lex("-9223372036854775808", "MINUS - null\nNUMBER 9223372036854775808 9223372036854775808.0\n");
+    // floats can hold it
+    test_lex("9223372036854775808.0", "NUMBER 9223372036854775808.0 9223372036854775808.0\n");
+}
+
+#[test]
+fn integer_too_large_for_u64() {
+    let err = Lexer::new("1 + 99_999_999_999_999_999_999").nth(2).unwrap().unwrap_err();
+    assert_eq!(err.to_string(), "Integer literal '99_999_999_999_999_999_999' is out of range");
+    let label = err.labels().and_then(|mut l| l.next()).expect("error is labeled");
+    assert_eq!((label.offset(), label.len()), (4, 26));
 }
 
 #[test]
@@ -122,11 +165,153 @@ SEMICOLON ; null
 "#.trim_ascii_start());
 }
 
+#[test]
+fn continues_after_unexpected_character() {
+    test_lex_with_errors("var $a = 1 # 2;", "VAR var null\nIDENTIFIER a null\nEQUAL = null\nNUMBER 1 1.0\nNUMBER 2 2.0\nSEMICOLON ; null\n", &[4, 11]);
+    test_lex_with_errors("@@", "", &[0, 1]);
+}
+
+#[test]
+fn unterminated_string_ends_input() {
+    test_lex_with_errors("print $ \"abc", "PRINT print null\n", &[6, 8]);
+}
+
+#[test]
+fn block_comments_nest() {
+    test_lex("a /* b */ c", "IDENTIFIER a null\nIDENTIFIER c null\n");
+    test_lex("/* outer /* inner */ still comment */ 1 /**/ / 2", "NUMBER 1 1.0\nSLASH / null\nNUMBER 2 2.0\n");
+    test_lex("/* ż\n */ \"x\"", "STRING \"x\" x\n");
+}
+
+#[test]
+fn unterminated_block_comment_ends_input() {
+    test_lex_with_errors("a /* b /* c */ d", "IDENTIFIER a null\n", &[2]);
+    test_lex_with_errors("a /", "IDENTIFIER a null\nSLASH / null\n", &[]);
+}
+
+#[test]
+fn doc_comments_belong_to_next_token() {
+    let tokens: Vec<_> = Lexer::new("/// Adds\n///   two numbers  \n//// not doc\n// nor this\nfun add() {} ///\nvar a;")
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(tokens[0].doc, vec!["Adds", "  two numbers"]);
+    assert!(tokens[1..6].iter().all(|t| t.doc.is_empty()));
+    assert_eq!(tokens[6].origin, "var");
+    assert_eq!(tokens[6].doc, vec![""]);
+}
+
+#[test]
+fn tokens_know_line_and_column() {
+    let tokens = Lexer::new("var a = \"żółw\"; // ü\n  print\n\"x\nźy\" a;")
+        .map(|t| t.map(|t| (t.origin, t.line, t.column)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(tokens, vec![
+        ("var", 1, 1),
+        ("a", 1, 5),
+        ("=", 1, 7),
+        ("\"żółw\"", 1, 9),
+        (";", 1, 15),
+        ("print", 2, 3),
+        ("\"x\nźy\"", 3, 1),
+        ("a", 4, 5),
+        (";", 4, 6),
+    ]);
+}
+
+#[test]
+fn crlf_is_single_line_break() {
+    let tokens = Lexer::new("var a\r\n  = \"x\r\ny\";\r\nprint a;")
+        .map(|t| t.map(|t| (t.origin, t.line, t.column)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(tokens, vec![
+        ("var", 1, 1),
+        ("a", 1, 5),
+        ("=", 2, 3),
+        ("\"x\r\ny\"", 2, 5),
+        (";", 3, 3),
+        ("print", 4, 1),
+        ("a", 4, 7),
+        (";", 4, 8),
+    ]);
+}
+
+#[test]
+fn trivia_is_attached_to_next_token() {
+    let mut lexer = Lexer::with_trivia("var a = 1; // one\n\t// two\nprint a;  ");
+    let tokens: Vec<_> = lexer.by_ref().map(Result::unwrap).collect();
+    assert_eq!(tokens[0].trivia, vec![]);
+    assert_eq!(tokens[1].trivia, vec![Trivia { kind: TriviaKind::Whitespace, text: " ", offset: 3 }]);
+    assert_eq!(tokens[5].origin, "print");
+    assert_eq!(tokens[5].trivia, vec![
+        Trivia { kind: TriviaKind::Whitespace, text: " ", offset: 10 },
+        Trivia { kind: TriviaKind::Comment, text: "// one", offset: 11 },
+        Trivia { kind: TriviaKind::Whitespace, text: "\n\t", offset: 17 },
+        Trivia { kind: TriviaKind::Comment, text: "// two", offset: 19 },
+        Trivia { kind: TriviaKind::Whitespace, text: "\n", offset: 25 },
+    ]);
+    assert_eq!(lexer.trailing_trivia(), &[Trivia { kind: TriviaKind::Whitespace, text: "  ", offset: 34 }]);
+}
+
+#[test]
+fn trivia_reconstructs_source() {
+    let sources = [
+        "",
+        "  // only comment",
+        "fun f(a, b) {\r\n  return a  +  b; // sum\r\n}\n\n print f(1, 2);\n",
+        "var s = \"żółw // not comment\"; ! = s;",
+        "var a = $ 1; @@ var b = \"unterminated",
+        "/// doc\nvar a /* 1 /* 2 */ */ = 1; /* unterminated",
+    ];
+    for src in sources {
+        let mut lexer = Lexer::with_trivia(src);
+        let mut out = String::new();
+        for token in lexer.by_ref().flatten() {
+            out.extend(token.trivia.iter().map(|t| t.text));
+            out.push_str(token.origin);
+        }
+        out.extend(lexer.trailing_trivia().iter().map(|t| t.text));
+        assert_eq!(out, src);
+    }
+}
+
+#[test]
+fn skipped_input_is_invalid_trivia() {
+    let mut lexer = Lexer::with_trivia("a $ b \"c");
+    let tokens: Vec<_> = lexer.by_ref().collect();
+    assert!(tokens[1].is_err());
+    let b = tokens[2].as_ref().unwrap();
+    assert_eq!(b.trivia.iter().map(|t| t.kind).collect::<Vec<_>>(), vec![TriviaKind::Whitespace, TriviaKind::Invalid, TriviaKind::Whitespace]);
+    assert!(tokens[3].is_err());
+    assert_eq!(lexer.trailing_trivia().last().map(|t| (t.kind, t.text)), Some((TriviaKind::Invalid, "\"c")));
+}
+
+#[test]
+fn trivia_is_not_collected_by_default() {
+    let tokens: Vec<_> = Lexer::new(" // c\n a").map(Result::unwrap).collect();
+    assert!(tokens[0].trivia.is_empty());
+}
+
+pub fn test_lex_with_errors(inp: &str, expected_out: &str, expected_errors: &[usize]) {
+    let lexer = Lexer::new(inp);
+    let mut out = String::new();
+    let mut errors = Vec::new();
+    for n in lexer.into_iter() {
+        match n {
+            Ok(token) => out.push_str(&format!("{token}\n")),
+            Err(e) => errors.push(e.labels().and_then(|mut l| l.next()).expect("error is labeled").offset()),
+        }
+    }
+    assert_eq!(&out, expected_out);
+    assert_eq!(&errors, expected_errors);
+}
+
 pub fn test_lex(inp: &str, expected_out: &str) {
-    let lexer = Lexer::new(&inp);
+    let lexer = Lexer::new(inp);
     let mut out = String::new();
     for n in lexer.into_iter() {
         out.push_str(&format!("{}\n", &n.unwrap().to_string()))
     }
     assert_eq!(&out, expected_out);
-}
\ No newline at end of file
+}
@@ -0,0 +1,106 @@
+use lang_interpreter::{ir::{Builder, Inst}, parser::Parser, resolver::Resolver};
+
+
+#[test]
+fn groups_are_removed() {
+    test_lower("(1 + 2) * 3", "\
+fun script (0 args, 5 temps)
+    1     %0 = const 1
+    5     %1 = const 2
+    1     %2 = %0 + %1
+   10     %3 = const 3
+    0     %4 = %2 * %3
+    0     return %4
+");
+}
+
+#[test]
+fn declarations_have_no_value() {
+    test_lower("var a = 1; print a;", "\
+fun script (0 args, 2 temps)
+    8     %0 = const 1
+    0     define @a, %0
+   17     %1 = load @a
+   11     print %1
+    0     return
+");
+}
+
+#[test]
+fn locals_are_resolved() {
+    test_lower("{ var a = 1; a }", "\
+fun script (0 args, 2 temps)
+    0     enter scope
+   10     %0 = const 1
+    2     define a[0:0], %0
+   13     %1 = load a[0:0]
+    0     exit scope 1
+    0     return %1
+");
+}
+
+#[test]
+fn and_keeps_deciding_operand() {
+    test_lower("true and 2", "\
+fun script (0 args, 3 temps)
+    0     %1 = const true
+    0     %0 = %1
+    0     branch %0, L0, L1
+    0 L0:
+    9     %2 = const 2
+    0     %0 = %2
+    0 L1:
+    0     return %0
+");
+}
+
+#[test]
+fn nested_functions() {
+    test_lower("fun f(a) { (fun () { a }) }", "\
+fun script (0 args, 1 temps)
+    0     %0 = closure #0
+    0     define @f, %0
+    0     return
+
+fun f #0 (1 args, 1 temps)
+    9     enter scope
+   12     %0 = closure #0
+    9     exit scope 1
+    9     return %0
+
+fun anonymous #0.0 (0 args, 1 temps)
+   19     enter scope
+   21     %0 = load a[3:0]
+   19     exit scope 1
+   19     return %0
+");
+}
+
+#[test]
+fn loop_control_flow_is_explicit() {
+    let code = "
+        var i = 0;
+        while (i < 10) {
+            i = i + 1;
+            if (i == 2) { continue; }
+            if (i == 5) { break i; }
+        }
+    ";
+    let fun = lower(code);
+    let labels = fun.code().iter().filter(|i| matches!(i, Inst::Label(_))).count();
+    let jumps = fun.code().iter().filter(|i| matches!(i, Inst::Jump(_) | Inst::Branch { .. })).count();
+    assert_eq!(labels, 11);
+    assert_eq!(jumps, 8);
+    // break and continue leave the scopes of both the if and the loop's block
+    assert_eq!(fun.code().iter().filter(|i| **i == Inst::ExitScope(2)).count(), 2);
+}
+
+fn lower(code: &str) -> lang_interpreter::ir::Function<'_> {
+    let mut stmts = Parser::new(code).parse().unwrap();
+    Resolver::new().resolve(&mut stmts).unwrap();
+    Builder::lower_script(&stmts)
+}
+
+fn test_lower(code: &str, ir: &str) {
+    assert_eq!(lower(code).to_string(), ir);
+}
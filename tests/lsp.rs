@@ -0,0 +1,222 @@
+use lang_interpreter::lsp::{write_message, Analysis, Server, SymbolKind};
+use serde_json::{json, Value};
+
+
+#[test]
+fn symbols_of_declarations() {
+    let src = "var a = 1; fun f(x, y) { var b = x; fun g() { b } g() } var h = fun (z) { var c = z; c };";
+    let analysis = Analysis::new(src);
+    let outline: Vec<_> = analysis.symbols.iter()
+        .map(|s| (s.signature(), s.children.iter().map(|c| c.signature()).collect::<Vec<_>>()))
+        .collect();
+    assert_eq!(outline, vec![
+        ("var a".to_string(), vec![]),
+        ("fun f(x, y)".to_string(), vec!["var b".to_string(), "fun g()".to_string()]),
+        ("fun h(z)".to_string(), vec!["var c".to_string()]),
+    ]);
+    assert_eq!(analysis.symbols[1].kind, SymbolKind::Function);
+    assert_eq!(analysis.span_of(analysis.symbols[1].name).offset(), 15);
+}
+
+#[test]
+fn declarations_in_nested_statements() {
+    let src = "if (true) { var a = 1; } else { for (var i = 0; i < 1; i = i + 1) { var b = i; } }";
+    let names: Vec<_> = Analysis::new(src).symbols.iter().map(|s| s.name).collect();
+    assert_eq!(names, vec!["a", "i", "b"]);
+}
+
+#[test]
+fn definition_of_variables() {
+    let src = "var a = 1; fun f(a) { a + b } var b = a; { var a = 2; a; }";
+    let analysis = Analysis::new(src);
+    // parameter shadows the global
+    assert_eq!(definition(&analysis, src, 22), Some(17));
+    // global declared later
+    assert_eq!(definition(&analysis, src, 26), Some(34));
+    assert_eq!(definition(&analysis, src, 38), Some(4));
+    assert_eq!(definition(&analysis, src, 54), Some(47));
+    // declaration points to itself
+    assert_eq!(definition(&analysis, src, 4), Some(4));
+    assert_eq!(definition(&analysis, src, 8), None);
+}
+
+#[test]
+fn definition_of_redeclared_local() {
+    let src = "{ var a = 1; var a = a + 1; a }";
+    let analysis = Analysis::new(src);
+    assert_eq!(definition(&analysis, src, 21), Some(6));
+    assert_eq!(definition(&analysis, src, 28), Some(17));
+}
+
+#[test]
+fn hover_shows_parameters() {
+    let src = "fun add(a, b) { a + b } var x = add(1, 2); len(\"ab\"); var id = fun (v) { v };";
+    let analysis = Analysis::new(src);
+    assert_eq!(analysis.hover(32).as_deref(), Some("fun add(a, b)"));
+    assert_eq!(analysis.hover(5).as_deref(), Some("fun add(a, b)"));
+    assert_eq!(analysis.hover(16).as_deref(), Some("parameter a"));
+    assert_eq!(analysis.hover(28).as_deref(), Some("var x"));
+    assert_eq!(analysis.hover(43).as_deref(), Some("fun len(_)"));
+    assert_eq!(analysis.hover(59).as_deref(), Some("fun id(v)"));
+    assert_eq!(analysis.hover(10), None);
+}
+
+#[test]
+fn hover_shows_doc_comments() {
+    let src = "/// Adds numbers\n/// together\nfun add(a, b) { a + b }\n/// answer\nvar x = add(1, 2);\nvar y; /// of z\nprint fun (z) { z };";
+    let analysis = Analysis::new(src);
+    assert_eq!(*analysis.symbols[0].doc, ["Adds numbers", "together"]);
+    assert_eq!(analysis.doc(35).as_deref(), Some("Adds numbers\ntogether"));
+    assert_eq!(analysis.doc(74).as_deref(), Some("Adds numbers\ntogether"));
+    assert_eq!(analysis.doc(69).as_deref(), Some("answer"));
+    // parameters, undocumented variables and anonymous functions have no doc
+    assert_eq!(analysis.doc(38), None);
+    assert_eq!(analysis.doc(88), None);
+    assert_eq!(analysis.doc(116), None);
+
+    let mut server = Server::new();
+    server.handle(&json!({ "method": "textDocument/didOpen", "params": {
+        "textDocument": { "uri": "file:///doc.lang", "text": src }
+    }}));
+    let reply = server.handle(&json!({ "id": 1, "method": "textDocument/hover", "params": {
+        "textDocument": { "uri": "file:///doc.lang" }, "position": { "line": 2, "character": 5 }
+    }}));
+    assert_eq!(reply[0]["result"]["contents"]["value"], "```\nfun add(a, b)\n```\n\nAdds numbers\ntogether");
+}
+
+#[test]
+fn classes_and_methods() {
+    let src = "/// Shapes\nclass Shape { area() { 0 } }\nclass Square < Shape {\n    /// Builds the square\n    init(side) { var s = side; this.side = s; }\n    area() { this.side * super.area() }\n}";
+    let analysis = Analysis::new(src);
+    assert!(analysis.problems.is_empty());
+    let outline: Vec<_> = analysis.symbols.iter()
+        .map(|s| (s.signature(), s.children.iter().map(|c| c.signature()).collect::<Vec<_>>()))
+        .collect();
+    assert_eq!(outline, vec![
+        ("class Shape".to_string(), vec!["area()".to_string()]),
+        ("class Square".to_string(), vec!["init(side)".to_string(), "area()".to_string()]),
+    ]);
+    assert_eq!(analysis.symbols[1].children[0].kind, SymbolKind::Method);
+    assert_eq!(analysis.symbols[1].children[0].children[0].signature(), "var s");
+    assert_eq!(analysis.doc(17).as_deref(), Some("Shapes"));
+    assert_eq!(analysis.doc(93).as_deref(), Some("Builds the square"));
+    // `this` and `super` refer to the class they are used in
+    assert_eq!(analysis.hover(120).as_deref(), Some("class Square"));
+    assert_eq!(definition(&analysis, src, 162), Some(46));
+    assert_eq!(definition(&analysis, src, 132), Some(110));
+}
+
+#[test]
+fn imported_namespaces() {
+    let src = "import \"lib/math.lang\";\nprint math.sqrt(2);";
+    let analysis = Analysis::new(src);
+    assert!(analysis.problems.is_empty());
+    assert_eq!(analysis.symbols[0].kind, SymbolKind::Module);
+    assert_eq!(analysis.symbols[0].signature(), "module math");
+    // the namespace is declared by the name of the file
+    assert_eq!(definition(&analysis, src, 30), Some(12));
+}
+
+#[test]
+fn problems_of_all_errors() {
+    let analysis = Analysis::new("var a = ; var b = 1 +;");
+    let offsets: Vec<_> = analysis.problems.iter().map(|p| p.span.offset()).collect();
+    assert_eq!(offsets, vec![8, 21]);
+    assert!(analysis.problems[0].message.contains("\nin variable declaration"));
+    assert!(analysis.symbols.is_empty());
+
+    let analysis = Analysis::new("fun f() { x } var a = 1;");
+    assert_eq!(analysis.problems.len(), 1);
+    assert_eq!(analysis.problems[0].span.offset(), 10);
+    assert!(analysis.problems[0].message.contains('x'));
+    assert_eq!(analysis.symbols.len(), 2);
+}
+
+#[test]
+fn session() {
+    let uri = "file:///test.lang";
+    let messages = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": { "uri": uri, "languageId": "lang", "version": 1, "text": "var a = ;" }
+        }}),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": "// ż\nfun f(x) { x }\nf(1);" }]
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/documentSymbol", "params": {
+            "textDocument": { "uri": uri }
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": {
+            "textDocument": { "uri": uri }, "position": { "line": 2, "character": 0 }
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "textDocument/hover", "params": {
+            "textDocument": { "uri": uri }, "position": { "line": 1, "character": 11 }
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 5, "method": "unknown", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 6, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ];
+    let mut input = Vec::new();
+    for m in &messages {
+        write_message(&mut input, m).unwrap();
+    }
+    let mut output = Vec::new();
+    assert!(Server::new().run(input.as_slice(), &mut output).unwrap());
+
+    let mut output = output.as_slice();
+    let mut replies = Vec::new();
+    while let Some(reply) = lang_interpreter::lsp::read_message(&mut output).unwrap() {
+        replies.push(reply);
+    }
+    assert_eq!(replies.len(), 8);
+    assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+
+    let diagnostics = &replies[1]["params"]["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 0, "character": 8 }));
+    assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+
+    let symbols = &replies[3]["result"];
+    assert_eq!(symbols[0]["name"], "f");
+    assert_eq!(symbols[0]["kind"], 12);
+    assert_eq!(symbols[0]["selectionRange"], range((1, 4), (1, 5)));
+
+    assert_eq!(replies[4]["result"], json!({ "uri": uri, "range": range((1, 4), (1, 5)) }));
+    assert_eq!(replies[5]["result"]["contents"]["value"], "```\nparameter x\n```");
+    assert_eq!(replies[6]["error"]["code"], -32601);
+    assert_eq!(replies[6]["id"], 5);
+    assert_eq!(replies[7], json!({ "jsonrpc": "2.0", "id": 6, "result": null }));
+}
+
+#[test]
+fn positions_count_utf16() {
+    let uri = "file:///test.lang";
+    let mut server = Server::new();
+    server.handle(&json!({ "method": "textDocument/didOpen", "params": {
+        "textDocument": { "uri": uri, "text": "var s = \"😀\"; var t = s;" }
+    }}));
+    let reply = server.handle(&json!({ "id": 1, "method": "textDocument/definition", "params": {
+        "textDocument": { "uri": uri }, "position": { "line": 0, "character": 23 }
+    }}));
+    assert_eq!(reply[0]["result"]["range"], range((0, 4), (0, 5)));
+    let reply = server.handle(&json!({ "id": 2, "method": "textDocument/documentSymbol", "params": {
+        "textDocument": { "uri": uri }
+    }}));
+    assert_eq!(reply[0]["result"][1]["selectionRange"], range((0, 18), (0, 19)));
+}
+
+fn range(start: (usize, usize), end: (usize, usize)) -> Value {
+    json!({
+        "start": { "line": start.0, "character": start.1 },
+        "end": { "line": end.0, "character": end.1 },
+    })
+}
+
+fn definition(analysis: &Analysis, src: &str, offset: usize) -> Option<usize> {
+    analysis.definition(offset).map(|span| {
+        assert!(span.offset() + span.len() <= src.len());
+        span.offset()
+    })
+}
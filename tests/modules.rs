@@ -0,0 +1,135 @@
+use std::{fs, path::PathBuf};
+
+use lang_interpreter::{backend::Backend, evaluator::Value, loader::Loader};
+use miette::Error;
+
+/// Writes the files into fresh directory named after the test,
+/// returns the path of the first one
+fn write(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lang-modules-{}-{test}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (name, code) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, code).unwrap();
+    }
+    dir.join(files[0].0)
+}
+
+/// Runs the first file on every backend and returns the value of its last statement,
+/// all backends have to agree on the outcome
+fn run(test: &str, files: &[(&str, &str)]) -> Result<Value<'static>, Error> {
+    let path = write(test, files);
+    let [first, rest @ ..] = Backend::ALL.map(|backend| Loader::new(backend).run(&path));
+    for (backend, res) in Backend::ALL[1..].iter().zip(rest) {
+        assert_eq!(outcome(&res), outcome(&first), "backend {backend:?} disagrees with {:?}", Backend::ALL[0]);
+    }
+    first
+}
+
+/// Offsets and lengths of the labels, errors come with the source code
+/// of their file so they can't be downcast
+fn labels(err: &Error) -> Vec<(usize, usize)> {
+    err.labels().into_iter().flatten().map(|l| (l.offset(), l.len())).collect()
+}
+
+fn outcome(res: &Result<Value<'_>, Error>) -> String {
+    match res {
+        Ok(v) => format!("{v:?}"),
+        Err(e) => format!("error: {e}"),
+    }
+}
+
+#[test]
+fn namespace_holds_functions_and_variables() {
+    let val = run("namespace", &[
+        ("main.lang", "import \"lib.lang\";\nlib.double(20) + lib.scale"),
+        ("lib.lang", "var scale = 2;\nfun double(x) { x * scale }"),
+    ]).unwrap();
+    assert_eq!(val, Value::Integer(42));
+}
+
+#[test]
+fn functions_use_globals_of_their_module() {
+    let val = run("globals", &[
+        ("main.lang", "var count = 100;\nimport \"counter.lang\";\ncounter.next();\ncounter.next() + count"),
+        ("counter.lang", "var count = 0;\nfun next() { count = count + 1; count }"),
+    ]).unwrap();
+    assert_eq!(val, Value::Integer(102));
+}
+
+#[test]
+fn module_does_not_see_importing_file() {
+    let err = run("isolated", &[
+        ("main.lang", "var secret = 1;\nimport \"lib.lang\";"),
+        ("lib.lang", "fun f() { secret }"),
+    ]).unwrap_err();
+    assert_eq!(err.to_string(), "Undeclared variable 'secret'");
+    assert_eq!(labels(&err), [(10, 6)]);
+}
+
+#[test]
+fn paths_are_relative_to_importing_file() {
+    let val = run("relative", &[
+        ("app/main.lang", "import \"../lib/shapes.lang\";\nshapes.square(3)"),
+        ("lib/shapes.lang", "import \"util/math.lang\";\nfun square(x) { math.mul(x, x) }"),
+        ("lib/util/math.lang", "fun mul(a, b) { a * b }"),
+    ]).unwrap();
+    assert_eq!(val, Value::Integer(9));
+}
+
+#[test]
+fn module_runs_once() {
+    let val = run("once", &[
+        ("main.lang", "import \"state.lang\";\nimport \"user.lang\";\nstate.hits.n = 5;\n[state.hits.n, user.shared.n]"),
+        ("user.lang", "import \"state.lang\";\nvar shared = state.hits;"),
+        ("state.lang", "var hits = {\"n\": 0};\nhits.n = hits.n + 1;"),
+    ]).unwrap();
+    assert_eq!(val, Value::list(vec![Value::Integer(5), Value::Integer(5)]));
+}
+
+#[test]
+fn imports_are_not_part_of_namespace() {
+    let val = run("reexport", &[
+        ("main.lang", "import \"lib.lang\";\n[contains(lib, \"helper\"), contains(lib, \"f\")]"),
+        ("lib.lang", "import \"helper.lang\";\nfun f() { helper.g() }"),
+        ("helper.lang", "fun g() { 1 }"),
+    ]).unwrap();
+    assert_eq!(val, Value::list(vec![Value::Bool(false), Value::Bool(true)]));
+}
+
+#[test]
+fn import_cycle_is_reported() {
+    let err = run("cycle", &[
+        ("a.lang", "import \"b.lang\";"),
+        ("b.lang", "import \"a.lang\";"),
+    ]).unwrap_err();
+    let message = err.to_string();
+    let cycle = message.strip_prefix("Import cycle ").expect("cycle is reported");
+    let files: Vec<_> = cycle.split(" -> ").map(|path| path.rsplit(['/', '\\']).next().unwrap()).collect();
+    assert_eq!(files, ["a.lang", "b.lang", "a.lang"]);
+    // reported in the file closing the cycle
+    assert_eq!(labels(&err), [(0, 15)]);
+}
+
+#[test]
+fn module_importing_itself_is_a_cycle() {
+    let err = run("self_import", &[("lib.lang", "import \"lib.lang\";")]).unwrap_err();
+    assert!(err.to_string().starts_with("Import cycle "));
+}
+
+#[test]
+fn missing_module_is_reported() {
+    let err = run("missing", &[("main.lang", "var a = 1;\nimport \"nope.lang\";")]).unwrap_err();
+    assert!(err.to_string().contains("nope.lang' can't be read"));
+    assert_eq!(labels(&err), [(11, 18)]);
+}
+
+#[test]
+fn errors_of_module_are_reported() {
+    let err = run("failing", &[
+        ("main.lang", "import \"lib.lang\";"),
+        ("lib.lang", "var a = 1 + nil;"),
+    ]).unwrap_err();
+    assert_eq!(labels(&err), [(8, 7)]);
+}
@@ -1,31 +1,91 @@
+use lang_interpreter::parser::Parser;
+
 use super::test_parse_expr;
 
 
 #[test]
 fn math_and_binding_power() {
-    test_parse_expr("1 + 2 + 3", "(+ (+ 1.0 2.0) 3.0)");
-    test_parse_expr("1 + 2 - 3", "(- (+ 1.0 2.0) 3.0)");
-    test_parse_expr("1 + 2 * 3", "(+ 1.0 (* 2.0 3.0))");
-    test_parse_expr("1 - 2 / 3", "(- 1.0 (/ 2.0 3.0))");
-    test_parse_expr("(1 + 2) * 3", "(* (group (+ 1.0 2.0)) 3.0)");
+    test_parse_expr("1 + 2 + 3", "(+ (+ 1 2) 3)");
+    test_parse_expr("1 + 2 - 3", "(- (+ 1 2) 3)");
+    test_parse_expr("1 + 2 * 3", "(+ 1 (* 2 3))");
+    test_parse_expr("1 - 2 / 3", "(- 1 (/ 2 3))");
+    test_parse_expr("(1 + 2) * 3", "(* (group (+ 1 2)) 3)");
+    test_parse_expr("1 + 7 div 2 % 3", "(+ 1 (% (div 7 2) 3))");
+    test_parse_expr("1.5 * 2 - 0.25", "(- (* 1.5 2) 0.25)");
 }
 
 #[test]
 fn condiotions() {
-    test_parse_expr("1 < 2 and 2 < 3", "(&& (< 1.0 2.0) (< 2.0 3.0))");
-    test_parse_expr("1 < 2 or 2 < 3", "(|| (< 1.0 2.0) (< 2.0 3.0))");
-    test_parse_expr("1 != 2 or 2 > 3", "(|| (!= 1.0 2.0) (> 2.0 3.0))");
-    test_parse_expr("(1 > 2) == (2 > 3)", "(== (group (> 1.0 2.0)) (group (> 2.0 3.0)))");
-    test_parse_expr("1 + 2 == 4 - 1", "(== (+ 1.0 2.0) (- 4.0 1.0))");
-    test_parse_expr("1 * 3 != 3 * 1", "(!= (* 1.0 3.0) (* 3.0 1.0))");
+    test_parse_expr("1 < 2 and 2 < 3", "(&& (< 1 2) (< 2 3))");
+    test_parse_expr("1 < 2 or 2 < 3", "(|| (< 1 2) (< 2 3))");
+    test_parse_expr("1 != 2 or 2 > 3", "(|| (!= 1 2) (> 2 3))");
+    test_parse_expr("(1 > 2) == (2 > 3)", "(== (group (> 1 2)) (group (> 2 3)))");
+    test_parse_expr("1 + 2 == 4 - 1", "(== (+ 1 2) (- 4 1))");
+    test_parse_expr("1 * 3 != 3 * 1", "(!= (* 1 3) (* 3 1))");
 }
 
 #[test]
 fn unary_ops() {
-    test_parse_expr("-1 + -2 + -3", "(+ (+ (- 1.0) (- 2.0)) (- 3.0))");
-    test_parse_expr("1 + -2 * 3", "(+ 1.0 (* (- 2.0) 3.0))");
-    test_parse_expr("1 + -(2 * 3)", "(+ 1.0 (- (group (* 2.0 3.0))))");
+    test_parse_expr("-1 + -2 + -3", "(+ (+ (- 1) (- 2)) (- 3))");
+    test_parse_expr("1 + -2 * 3", "(+ 1 (* (- 2) 3))");
+    test_parse_expr("1 + -(2 * 3)", "(+ 1 (- (group (* 2 3))))");
     test_parse_expr("!true == false", "(== (! true) false)");
-    test_parse_expr("!(1 < 2)", "(! (group (< 1.0 2.0)))");
-    test_parse_expr("!(1 < 2) == true", "(== (! (group (< 1.0 2.0))) true)");
-}
\ No newline at end of file
+    test_parse_expr("!(1 < 2)", "(! (group (< 1 2)))");
+    test_parse_expr("!(1 < 2) == true", "(== (! (group (< 1 2))) true)");
+}
+#[test]
+fn exponent_is_right_associative() {
+    test_parse_expr("2 ** 3 ** 2", "(** 2 (** 3 2))");
+    test_parse_expr("2 ** 3 * 4", "(* (** 2 3) 4)");
+    test_parse_expr("4 * 2 ** 3", "(* 4 (** 2 3))");
+    test_parse_expr("(2 ** 3) ** 2", "(** (group (** 2 3)) 2)");
+}
+
+#[test]
+fn exponent_and_unary_ops() {
+    test_parse_expr("-2 ** 2", "(- (** 2 2))");
+    test_parse_expr("2 ** -1", "(** 2 (- 1))");
+    test_parse_expr("~2 ** 2", "(~ (** 2 2))");
+    test_parse_expr("a[0] ** b.c", "(** (index id(a) 0) (index id(b) c))");
+}
+
+#[test]
+fn modulo_binds_like_multiplication() {
+    test_parse_expr("1 + 7 % 3", "(+ 1 (% 7 3))");
+    test_parse_expr("7 % 3 * 2", "(* (% 7 3) 2)");
+    test_parse_expr("7 * 3 % 2", "(% (* 7 3) 2)");
+}
+
+#[test]
+fn bitwise_binding_power() {
+    test_parse_expr("1 | 2 ^ 3 & 4", "(| 1 (^ 2 (& 3 4)))");
+    test_parse_expr("1 & 2 ^ 3 | 4", "(| (^ (& 1 2) 3) 4)");
+    test_parse_expr("1 << 2 + 3", "(<< 1 (+ 2 3))");
+    test_parse_expr("1 & 2 << 3", "(& 1 (<< 2 3))");
+    test_parse_expr("1 | 2 == 3", "(== (| 1 2) 3)");
+    test_parse_expr("~1 & 2", "(& (~ 1) 2)");
+}
+
+#[test]
+fn bitwise_left_associative() {
+    test_parse_expr("1 << 2 >> 3", "(>> (<< 1 2) 3)");
+    test_parse_expr("1 - 2 % 3 - 4", "(- (- 1 (% 2 3)) 4)");
+    test_parse_expr("1 ^ 2 ^ 3", "(^ (^ 1 2) 3)");
+}
+
+#[test]
+fn smallest_integer_literal() {
+    test_parse_expr("-9223372036854775808", "-9223372036854775808");
+    test_parse_expr("- 9223372036854775808 + 1", "(+ -9223372036854775808 1)");
+    test_parse_expr("-9223372036854775807", "(- 9223372036854775807)");
+    let err = Parser::new("1 - 9223372036854775808").parse_expression_within(0).unwrap_err();
+    assert_eq!(err.root_cause().to_string(), "Integer literal '9223372036854775808' is out of range");
+}
+
+#[test]
+fn unexpected_token_help() {
+    let err = Parser::new("1 + ;").parse_expression_within(0).unwrap_err();
+    let report = format!("{err:?}");
+    assert!(report.contains("Unexpected SEMICOLON"), "{report}");
+    assert!(!report.contains("trivia"), "{report}");
+}
@@ -5,12 +5,12 @@ use super::*;
 
 test_parse!(block1, 
     "{ print 1; print 2; print \"test\";}",
-    "(block (print 1.0) (print 2.0) (print test))"
+    "(block (print 1) (print 2) (print test))"
 );
 
 test_parse!(block2, 
     "{ print 1; print 2; \"test\"}",
-    "(block (print 1.0) (print 2.0) test)"
+    "(block (print 1) (print 2) test)"
 );
 
 test_parse!(block3, "{ }", "(block)");
@@ -25,7 +25,7 @@ test_parse!(block4,
         print 3;
     }
     ",
-    "(block (print 1.0) (if (< 1.0 2.0) (block (print 2.0))) (print 3.0))"
+    "(block (print 1) (if (< 1 2) (block (print 2))) (print 3))"
 );
 
 test_parse!(block5, 
@@ -39,7 +39,7 @@ test_parse!(block5,
         1
     }
     ",
-    "(block (print 1.0) (if (< 1.0 2.0) (block (print 2.0))) (print 3.0) 1.0)"
+    "(block (print 1) (if (< 1 2) (block (print 2))) (print 3) 1)"
 );
 
 test_parse!(block_incorrect_syntax1, ERROR, "{ print 1 print 2; 1 }");
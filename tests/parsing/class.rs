@@ -0,0 +1,31 @@
+use crate::test_parse;
+
+use super::*;
+
+test_parse!(class_empty, STMT, "class A {}", "(class A)");
+test_parse!(class_methods, STMT,
+    "class A { init(x) { this.x = x; } get() { this.x } }",
+    "(class A (fun init (x) (block (= (index this x) id(x)))) (fun get () (block (index this x))))"
+);
+test_parse!(class_superclass, STMT,
+    "class B < A { get() { super.get() + 1 } }",
+    "(class B < A (fun get () (block (+ (call (super get)) 1))))"
+);
+test_parse!(class_in_method, STMT,
+    "class A { m() { class B < A { n() { super.m } } } }",
+    "(class A (fun m () (block (class B < A (fun n () (block (super m)))))))"
+);
+test_parse!(class_property_chain, EXP, "a.b.c().d = 1", "(= (index (call (index (index id(a) b) c)) d) 1)");
+
+test_parse!(class_is_not_an_expression, EXP, ERROR, "class A {}");
+test_parse!(class_incorrect_syntax1, STMT, ERROR, "class {}");
+test_parse!(class_incorrect_syntax2, STMT, ERROR, "class A");
+test_parse!(class_incorrect_syntax3, STMT, ERROR, "class A < {}");
+test_parse!(class_incorrect_syntax4, STMT, ERROR, "class A { fun m() {} }");
+test_parse!(class_incorrect_syntax5, STMT, ERROR, "class A { var x = 1; }");
+test_parse!(class_incorrect_syntax6, STMT, ERROR, "class A { m() {}");
+test_parse!(this_outside_of_class, ERROR, "this");
+test_parse!(this_in_function_outside_of_class, STMT, ERROR, "fun f() { this }");
+test_parse!(super_outside_of_class, ERROR, "super.m");
+test_parse!(super_without_superclass, STMT, ERROR, "class A { m() { super.m() } }");
+test_parse!(super_without_method, STMT, ERROR, "class B < A { m() { super } }");
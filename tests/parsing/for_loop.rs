@@ -4,17 +4,17 @@ use super::*;
 
 test_parse!(for_simple,
     "for (var i = 1; i < 10; i = i + 1) { print i; }",
-    "(loop (var id(i) 1.0) (< id(i) 10.0) (= id(i) (+ id(i) 1.0)) (block (print id(i))))"
+    "(loop (var id(i) 1) (< id(i) 10) (= id(i) (+ id(i) 1)) (block (print id(i))))"
 );
 
 test_parse!(for_weird,
     "for ({ print 1; print 2;}; print 3; print 4) { print 5; }",
-    "(loop (block (print 1.0) (print 2.0)) (print 3.0) (print 4.0) (block (print 5.0)))"
+    "(loop (block (print 1) (print 2)) (print 3) (print 4) (block (print 5)))"
 );
 
 test_parse!(for_infinite, 
     "for (;;) { print 1; }",
-    "(loop nil true nil (block (print 1.0)))"
+    "(loop nil true nil (block (print 1)))"
 );
 
 test_parse!(for_incomplete_params1, OK, "for(var i = 1; ; i = i + 1) { print i; }");
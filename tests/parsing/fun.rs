@@ -32,3 +32,26 @@ test_parse!(fun_incorrect_syntax9, STMT, ERROR, "fun test p1) { }");
 test_parse!(fun_incorrect_syntax10, STMT, ERROR, "fun test p1 { }");
 test_parse!(fun_incorrect_syntax11, STMT, ERROR, "fun test(1) { }");
 test_parse!(fun_incorrect_syntax12, STMT, ERROR, "fun test(1 + 2) { }");
+
+test_parse!(fun_anonymous, EXP,
+    "fun (a, b) { a + b }",
+    "(fun (a b) (block (+ id(a) id(b))))"
+);
+test_parse!(fun_anonymous_in_var, STMT,
+    "var f = fun () { };",
+    "(var id(f) (fun () (block)))"
+);
+test_parse!(fun_call_chained, EXP,
+    "test(1)(2)",
+    "(call (call test (1)) (2))"
+);
+test_parse!(fun_call_of_index, EXP,
+    "test[0](1)",
+    "(call (index id(test) 0) (1))"
+);
+test_parse!(fun_anonymous_called_in_place, EXP,
+    "(fun (a) { a })(1)",
+    "(call (group (fun (a) (block id(a)))) (1))"
+);
+test_parse!(fun_anonymous_incorrect_syntax1, EXP, ERROR, "fun (a { }");
+test_parse!(fun_anonymous_incorrect_syntax2, EXP, ERROR, "fun (a)");
@@ -4,7 +4,7 @@ use super::*;
 
 test_parse!(if1,
     "if (1 < 3) { print 1; }", 
-    "(if (< 1.0 3.0) (block (print 1.0)))");
+    "(if (< 1 3) (block (print 1)))");
 
 test_parse!(if2,
     "if (true) {
@@ -12,11 +12,11 @@ test_parse!(if2,
         print 2;
         print 3;
     }", 
-    "(if true (block (print 1.0) (print 2.0) (print 3.0)))");
+    "(if true (block (print 1) (print 2) (print 3)))");
 
 test_parse!(if_else1,
     "if (1 < 3) { print 1; } else { print 2; }",
-    "(if (< 1.0 3.0) (block (print 1.0)) (block (print 2.0)))");
+    "(if (< 1 3) (block (print 1)) (block (print 2)))");
 
 test_parse!(if_else2,
     "
@@ -28,7 +28,7 @@ test_parse!(if_else2,
         print 4;
     }
     ",
-    "(if true (block (print 1.0) (print 2.0)) (block (print 3.0) (print 4.0)))");
+    "(if true (block (print 1) (print 2)) (block (print 3) (print 4)))");
 
 test_parse!(if_incorrect_syntax1, ERROR, "if (true)");
 test_parse!(if_incorrect_syntax2, ERROR, "if (true) else");
@@ -0,0 +1,40 @@
+use lang_interpreter::{error::ControlFlowError, parser::Parser};
+
+use crate::test_parse;
+
+use super::*;
+
+fn parse_program(code: &str) -> Vec<String> {
+    Parser::new(code).parse().unwrap().iter().map(|e| e.to_string()).collect()
+}
+
+#[test]
+fn import_on_top_level() {
+    assert_eq!(parse_program("import \"lib.lang\";\nprint lib.f();"), ["(import \"lib.lang\")", "(print (call (index id(lib) f)))"]);
+}
+
+#[test]
+fn import_relative_path() {
+    assert_eq!(parse_program("import \"../util/strings.lang\";"), ["(import \"../util/strings.lang\")"]);
+}
+
+#[test]
+fn import_is_not_allowed_in_blocks() {
+    let err = Parser::new("fun f() { import \"lib.lang\"; }").parse().unwrap_err();
+    assert!(err.chain().any(|e| e.downcast_ref::<ControlFlowError>().is_some_and(|e| e.keyword == "import")));
+}
+
+#[test]
+fn import_needs_identifier_file_name() {
+    assert!(Parser::new("import \"my-lib.lang\";").parse().is_err());
+    assert!(Parser::new("import \"class.lang\";").parse().is_err());
+    assert!(Parser::new("import \"\";").parse().is_err());
+}
+
+#[test]
+fn import_needs_string_path() {
+    assert!(Parser::new("import lib;").parse().is_err());
+}
+
+test_parse!(import_is_not_a_statement, STMT, ERROR, "import \"lib.lang\"");
+test_parse!(import_is_not_an_expression, EXP, ERROR, "import \"lib.lang\"");
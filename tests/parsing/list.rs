@@ -0,0 +1,20 @@
+use crate::test_parse;
+
+use super::*;
+
+test_parse!(list_empty, "[]", "(list)");
+test_parse!(list_literal, "[1, 2 + 3, \"test\"]", "(list 1 (+ 2 3) test)");
+test_parse!(list_nested, "[[1], []]", "(list (list 1) (list))");
+
+test_parse!(index_read, "test[0]", "(index id(test) 0)");
+test_parse!(index_chained, "test[0][1 + 1]", "(index (index id(test) 0) (+ 1 1))");
+test_parse!(index_literal, "[1, 2][1]", "(index (list 1 2) 1)");
+test_parse!(index_write, "test[1] = 2", "(= (index id(test) 1) 2)");
+test_parse!(index_binds_tighter_than_unary, "-test[0] * 2", "(* (- (index id(test) 0)) 2)");
+test_parse!(index_of_call, "test()[0]", "(index (call test) 0)");
+
+test_parse!(list_incorrect_syntax1, ERROR, "[1, 2");
+test_parse!(list_incorrect_syntax2, ERROR, "[1 2]");
+test_parse!(list_incorrect_syntax3, ERROR, "[1, ]");
+test_parse!(index_incorrect_syntax1, ERROR, "test[0");
+test_parse!(index_incorrect_syntax2, ERROR, "test[]");
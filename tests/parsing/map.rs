@@ -0,0 +1,22 @@
+use crate::test_parse;
+
+use super::*;
+
+test_parse!(map_empty, "{:}", "(map)");
+test_parse!(map_literal, "{\"a\": 1, \"b c\": 2 + 3}", "(map (a 1) (b c (+ 2 3)))");
+test_parse!(map_trailing_comma, "{\"a\": 1, \"b\": 2,}", "(map (a 1) (b 2))");
+test_parse!(map_nested, "{\"a\": {\"b\": [1]}}", "(map (a (map (b (list 1)))))");
+test_parse!(block_starting_with_string, "{ \"a\"; \"b\" }", "(block a b)");
+
+test_parse!(field_read, "test.a", "(index id(test) a)");
+test_parse!(field_chained, "test.a[0].b()", "(call (index (index (index id(test) a) 0) b))");
+test_parse!(field_write, "test.a = 2", "(= (index id(test) a) 2)");
+test_parse!(field_binds_tighter_than_unary, "-test.a * 2", "(* (- (index id(test) a)) 2)");
+
+test_parse!(map_incorrect_syntax1, ERROR, "{\"a\": 1");
+test_parse!(map_incorrect_syntax2, ERROR, "{\"a\": 1 \"b\": 2}");
+test_parse!(map_incorrect_syntax3, ERROR, "{\"a\": 1, b: 2}");
+test_parse!(map_incorrect_syntax4, ERROR, "{\"a\": 1,,}");
+test_parse!(map_incorrect_syntax5, ERROR, "{\"a\"; \"b\": 1}");
+test_parse!(field_incorrect_syntax1, ERROR, "test.");
+test_parse!(field_incorrect_syntax2, ERROR, "test.\"a\"");
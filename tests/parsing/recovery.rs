@@ -0,0 +1,77 @@
+use lang_interpreter::{error::{ControlFlowError, SyntaxErrors}, parser::Parser};
+use miette::Error;
+
+/// Offsets of all errors reported by the parser
+fn error_offsets(code: &str) -> Vec<usize> {
+    let err = Parser::new(code).parse().unwrap_err();
+    match err.downcast_ref::<SyntaxErrors>() {
+        Some(errors) => errors.errors.iter().map(offset).collect(),
+        None => vec![offset(&err)],
+    }
+}
+
+fn offset(err: &Error) -> usize {
+    err.labels().and_then(|mut l| l.next()).expect("error points to the code").offset()
+}
+
+#[test]
+fn single_error_is_not_wrapped() {
+    let err = Parser::new("var a = ;\nprint 1;").parse().unwrap_err();
+    assert!(err.downcast_ref::<SyntaxErrors>().is_none());
+    assert_eq!(offset(&err), 8);
+}
+
+#[test]
+fn synchronizes_on_semicolon() {
+    assert_eq!(error_offsets("var a = ; var b = 1 +* 2; print b; var = 3;"), vec![8, 21, 39]);
+}
+
+#[test]
+fn synchronizes_on_statement_keyword() {
+    assert_eq!(error_offsets("var a = 1 1\nprint * 2\nvar c = 3;"), vec![10, 18]);
+}
+
+#[test]
+fn recovers_within_block() {
+    let code = "
+        fun f(x) {
+            var y = x + ;
+            print y;
+            return x * ;
+        }
+        f(1)
+    ";
+    assert_eq!(error_offsets(code), vec![44, 90]);
+}
+
+#[test]
+fn skips_block_of_broken_statement() {
+    // body of the broken function is not reported again
+    let code = "fun f(x, ) { return x + ; } while (true { break; } var a = ;";
+    assert_eq!(error_offsets(code), vec![9, 40, 59]);
+}
+
+#[test]
+fn error_at_semicolon_ends_statement() {
+    assert_eq!(error_offsets("var a = ; a = 1 +* 2; { var b = ; b = -; }"), vec![8, 17, 32, 39]);
+}
+
+#[test]
+fn stray_closing_brace() {
+    assert_eq!(error_offsets("print 1; } print * 1;"), vec![9, 17]);
+}
+
+#[test]
+fn collects_static_errors() {
+    let err = Parser::new("break; continue; return 1;").parse().unwrap_err();
+    let errors = &err.downcast_ref::<SyntaxErrors>().unwrap().errors;
+    let keywords: Vec<_> = errors.iter()
+        .map(|e| e.downcast_ref::<ControlFlowError>().unwrap().keyword)
+        .collect();
+    assert_eq!(keywords, vec!["break", "continue", "return"]);
+}
+
+#[test]
+fn unclosed_block_ends_parsing() {
+    assert!(Parser::new("var a = ;\n{ print 1;").parse().unwrap_err().downcast_ref::<SyntaxErrors>().is_some());
+}
@@ -3,8 +3,8 @@ use crate::test_parse;
 
 use super::*;
 
-test_parse!(var1, STMT, "var test = 1;", "(var id(test) 1.0)");
-test_parse!(var2, STMT, "var test = 1 + 2;", "(var id(test) (+ 1.0 2.0))");
+test_parse!(var1, STMT, "var test = 1;", "(var id(test) 1)");
+test_parse!(var2, STMT, "var test = 1 + 2;", "(var id(test) (+ 1 2))");
 test_parse!(var3, STMT, "var test;", "(var id(test) nil)");
 
 
@@ -5,17 +5,17 @@ use super::*;
 
 test_parse!(while_simple,
     "while (i < 10) { print i; i = i + 1; }",
-    "(loop nil (< id(i) 10.0) nil (block (print id(i)) (= id(i) (+ id(i) 1.0))))"
+    "(loop nil (< id(i) 10) nil (block (print id(i)) (= id(i) (+ id(i) 1))))"
 );
 
 test_parse!(while_infinite,
     "while (true) { print 1; }",
-    "(loop nil true nil (block (print 1.0)))"
+    "(loop nil true nil (block (print 1)))"
 );
 
 test_parse!(while_weird,
     "while ({print 1; true}) {}",
-    "(loop nil (block (print 1.0) true) nil (block))"
+    "(loop nil (block (print 1) true) nil (block))"
 );
 
 
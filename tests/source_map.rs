@@ -0,0 +1,50 @@
+use lang_interpreter::source_map::SourceMap;
+
+
+#[test]
+fn offsets_to_lines_and_columns() {
+    let map = SourceMap::new("ab\ncd\n\nef");
+    assert_eq!(map.line_col(0), (1, 1));
+    assert_eq!(map.line_col(2), (1, 3));
+    assert_eq!(map.line_col(3), (2, 1));
+    assert_eq!(map.line_col(7), (4, 1));
+    assert_eq!(map.line_col(6), (3, 1));
+    assert_eq!(map.line_col(100), (4, 3));
+    assert_eq!(map.lines(), 4);
+}
+
+#[test]
+fn multi_byte_characters() {
+    let src = "żółw = 1;\n\"ü\" ä";
+    let map = SourceMap::new(src);
+    assert_eq!(map.line_col(src.find('=').unwrap()), (1, 6));
+    assert_eq!(map.line_col(src.find('ä').unwrap()), (2, 5));
+    // offset inside of the character points to it
+    assert_eq!(map.line_col(1), (1, 1));
+    assert_eq!(map.offset(1, 6), src.find('='));
+    assert_eq!(map.offset(2, 5), src.find('ä'));
+}
+
+#[test]
+fn lines_and_columns_to_offsets() {
+    let map = SourceMap::new("ab\r\ncd");
+    assert_eq!(map.offset(1, 1), Some(0));
+    assert_eq!(map.offset(1, 3), Some(2));
+    assert_eq!(map.offset(2, 2), Some(5));
+    assert_eq!(map.offset(2, 3), Some(6));
+    assert_eq!(map.offset(2, 4), None);
+    assert_eq!(map.offset(3, 1), None);
+    assert_eq!(map.offset(0, 1), None);
+    assert_eq!(map.offset(1, 0), None);
+    assert_eq!(map.line(1), Some("ab"));
+    assert_eq!(map.line(2), Some("cd"));
+}
+
+#[test]
+fn crlf_line_breaks() {
+    let map = SourceMap::new("ab\r\ncd");
+    assert_eq!(map.line_col(2), (1, 3));
+    assert_eq!(map.line_col(3), (1, 3));
+    assert_eq!(map.line_col(4), (2, 1));
+    assert_eq!(map.offset(1, 3), Some(2));
+}